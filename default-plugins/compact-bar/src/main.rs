@@ -89,6 +89,8 @@ impl ZellijPlugin for State {
             return;
         }
         let mut all_tabs: Vec<LinePart> = vec![];
+        // every tab gets an equal share of the line to show who is on it
+        let max_tab_len = cols.saturating_sub(1) / self.tabs.len();
         let mut active_tab_index = 0;
         let mut active_swap_layout_name = None;
         let mut is_swap_layout_dirty = false;
@@ -111,6 +113,7 @@ impl ZellijPlugin for State {
                 is_alternate_tab,
                 self.mode_info.style.colors,
                 self.mode_info.capabilities,
+                max_tab_len,
             );
             is_alternate_tab = !is_alternate_tab;
             all_tabs.push(tab);
@@ -1,17 +1,55 @@
 use crate::{line::tab_separator, LinePart};
 use ansi_term::{ANSIString, ANSIStrings};
-use unicode_width::UnicodeWidthStr;
+use std::collections::BTreeMap;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 use zellij_tile::prelude::*;
 use zellij_tile_utils::style;
 
-fn cursors(focused_clients: &[ClientId], palette: Palette) -> (Vec<ANSIString>, usize) {
+// the longest start of `name` that is at most `max_width` columns wide
+fn truncate(name: &str, max_width: usize) -> String {
+    let mut width = 0;
+    name.chars()
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width <= max_width
+        })
+        .collect()
+}
+
+fn cursors(
+    focused_clients: &[ClientId],
+    client_names: &BTreeMap<ClientId, String>,
+    max_len: usize,
+    palette: Palette,
+) -> (Vec<ANSIString<'static>>, usize) {
     // cursor section, text length
+    let named_clients = focused_clients
+        .iter()
+        .filter(|client_id| client_names.contains_key(client_id))
+        .count();
+    // the named clients share what the others' cursors leave, 2 of it go to padding
+    let name_width = max_len
+        .saturating_sub(focused_clients.len() - named_clients)
+        .checked_div(named_clients)
+        .unwrap_or(0)
+        .saturating_sub(2);
     let mut len = 0;
     let mut cursors = vec![];
     for client_id in focused_clients.iter() {
         if let Some(color) = client_id_to_colors(*client_id, palette) {
-            cursors.push(style!(color.1, color.0).paint(" "));
-            len += 1;
+            // clients that picked a name are marked with as much of it as fits, like on the pane
+            // frames, or with its first letter when there is no room
+            let mark = match client_names.get(client_id) {
+                Some(name) if name_width > 0 => format!(" {} ", truncate(name, name_width)),
+                Some(name) => name
+                    .chars()
+                    .next()
+                    .map(|first_letter| first_letter.to_string())
+                    .unwrap_or_else(|| " ".to_owned()),
+                None => " ".to_owned(),
+            };
+            len += mark.width();
+            cursors.push(style!(color.1, color.0).bold().paint(mark));
         }
     }
     (cursors, len)
@@ -23,6 +61,7 @@ pub fn render_tab(
     is_alternate_tab: bool,
     palette: Palette,
     separator: &str,
+    max_len: usize,
 ) -> LinePart {
     let focused_clients = tab.other_focused_clients.as_slice();
     let separator_width = separator.width();
@@ -51,8 +90,14 @@ pub fn render_tab(
 
     let right_separator = style!(background_color, foreground_color).paint(separator);
     let tab_styled_text = if !focused_clients.is_empty() {
-        let (cursor_section, extra_length) = cursors(focused_clients, palette);
-        tab_text_len += extra_length;
+        // 2 for the brackets around the cursors
+        let (cursor_section, extra_length) = cursors(
+            focused_clients,
+            &tab.other_focused_client_names,
+            max_len.saturating_sub(tab_text_len + 2),
+            palette,
+        );
+        tab_text_len += extra_length + 2;
         let mut s = String::new();
         let cursor_beginning = style!(foreground_color, background_color)
             .bold()
@@ -87,6 +132,7 @@ pub fn tab_style(
     mut is_alternate_tab: bool,
     palette: Palette,
     capabilities: PluginCapabilities,
+    max_len: usize,
 ) -> LinePart {
     let separator = tab_separator(capabilities);
     if tab.is_sync_panes_active {
@@ -97,7 +143,7 @@ pub fn tab_style(
         is_alternate_tab = false;
     }
 
-    render_tab(tabname, tab, is_alternate_tab, palette, separator)
+    render_tab(tabname, tab, is_alternate_tab, palette, separator, max_len)
 }
 
 pub(crate) fn get_tab_to_focus(
@@ -1,19 +1,196 @@
+use ansi_term::Style;
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthStr;
 use zellij_tile::prelude::*;
 
+const DEFAULT_CHOICES: &[&str] = &["Yes", "No"];
+
+/// A prompt opened with its question in the configuration:
+///
+/// - `message`: the question
+/// - `choices`: comma separated answers, the first one is selected [default: Yes,No]
+/// - `title`: name of the pane
+/// - `timeout`: seconds until the prompt is dismissed on its own
+/// - `prompt_id`: who to send the answer to, when a client asked the question
+///
+/// The plugin closes itself once the prompt is answered or dismissed.
 #[derive(Default)]
-struct State {}
+struct State {
+    prompt_id: Option<u32>,
+    message: String,
+    choices: Vec<String>,
+    selected: usize,
+    seconds_left: Option<u64>,
+    answered: bool,
+}
 
 register_plugin!(State);
 
 impl ZellijPlugin for State {
+    fn load(&mut self, configuration: BTreeMap<String, String>) {
+        self.prompt_id = configuration
+            .get("prompt_id")
+            .and_then(|prompt_id| prompt_id.parse().ok());
+        self.message = configuration.get("message").cloned().unwrap_or_default();
+        self.choices = configuration
+            .get("choices")
+            .map(|choices| {
+                choices
+                    .split(',')
+                    .map(|choice| choice.trim().to_owned())
+                    .filter(|choice| !choice.is_empty())
+                    .collect()
+            })
+            .filter(|choices: &Vec<String>| !choices.is_empty())
+            .unwrap_or_else(|| DEFAULT_CHOICES.iter().map(|c| c.to_string()).collect());
+        self.seconds_left = configuration
+            .get("timeout")
+            .and_then(|timeout| timeout.parse().ok())
+            .filter(|timeout| *timeout > 0);
+        subscribe(&[EventType::Key, EventType::Timer]);
+        if let Some(title) = configuration.get("title") {
+            rename_plugin_pane(get_plugin_ids().plugin_id, title);
+        }
+        if self.seconds_left.is_some() {
+            set_timeout(1.0);
+        }
+    }
+
+    fn update(&mut self, event: Event) -> bool {
+        if self.answered {
+            return false;
+        }
+        match event {
+            Event::Key(key) => self.handle_key(key),
+            Event::Timer(_) => match self.seconds_left {
+                Some(seconds_left) if seconds_left <= 1 => {
+                    self.answer(None);
+                    false
+                },
+                Some(seconds_left) => {
+                    self.seconds_left = Some(seconds_left - 1);
+                    set_timeout(1.0);
+                    true
+                },
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
     fn render(&mut self, rows: usize, cols: usize) {
-        //println!("{}hello", "".to_string().repeat(30))
-        let buf = [
-            27, 91, 50, 59, 56, 72, 67, 111, 110, 102, 105, 114, 109, 27, 91, 51, 57, 109, 27, 91,
-            52, 57, 109, 27, 91, 53, 57, 109, 27, 91, 48, 109, 27, 91, 63, 50, 53, 108, 27, 91, 63,
-            50, 53, 104,
-        ];
-        print!("{}", String::from_utf8_lossy(&buf));
-        print!("{:?} {:?}", rows, cols)
+        let mut lines = wrap(&self.message, cols.saturating_sub(2));
+        lines.push(String::new());
+        let choices = self
+            .choices
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| {
+                if i == self.selected {
+                    Style::new().reverse().bold().paint(format!(" {} ", choice))
+                } else {
+                    Style::new().paint(format!(" {} ", choice))
+                }
+                .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        let choices_width = self
+            .choices
+            .iter()
+            .map(|choice| choice.width() + 2)
+            .sum::<usize>()
+            + 2 * self.choices.len().saturating_sub(1);
+        let hint = match self.seconds_left {
+            Some(seconds_left) => format!(
+                "<←→> choose, <ENTER> confirm, <ESC> dismiss ({}s left)",
+                seconds_left
+            ),
+            None => "<←→> choose, <ENTER> confirm, <ESC> dismiss".to_owned(),
+        };
+
+        let height = lines.len() + 3;
+        for _ in 0..rows.saturating_sub(height) / 2 {
+            println!();
+        }
+        for line in lines {
+            println!("{}{}", padding(line.width(), cols), line);
+        }
+        println!("{}{}", padding(choices_width, cols), choices);
+        println!();
+        print!(
+            "{}{}",
+            padding(hint.width(), cols),
+            Style::new().dimmed().paint(hint)
+        );
+    }
+}
+
+impl State {
+    fn handle_key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Left | Key::BackTab | Key::Char('h') => {
+                self.selected = self.selected.saturating_sub(1);
+                true
+            },
+            Key::Right | Key::Char('\t') | Key::Char('l') => {
+                self.selected = (self.selected + 1).min(self.choices.len().saturating_sub(1));
+                true
+            },
+            Key::Char('\n') => {
+                let choice = self.choices.get(self.selected).cloned();
+                self.answer(choice);
+                false
+            },
+            Key::Esc | Key::Ctrl('c') => {
+                self.answer(None);
+                false
+            },
+            // the first letter of a choice picks it
+            Key::Char(c) => {
+                let choice = self.choices.iter().find(|choice| {
+                    choice
+                        .chars()
+                        .next()
+                        .map_or(false, |first| first.eq_ignore_ascii_case(&c))
+                });
+                if let Some(choice) = choice.cloned() {
+                    self.answer(Some(choice));
+                }
+                false
+            },
+            _ => false,
+        }
+    }
+
+    fn answer(&mut self, choice: Option<String>) {
+        self.answered = true;
+        if let Some(prompt_id) = self.prompt_id {
+            answer_prompt(prompt_id, choice.as_deref());
+        }
+        close_plugin_pane(get_plugin_ids().plugin_id);
+    }
+}
+
+fn padding(width: usize, cols: usize) -> String {
+    " ".repeat(cols.saturating_sub(width) / 2)
+}
+
+/// Breaks `text` into lines at most `cols` wide, at spaces where it can
+fn wrap(text: &str, cols: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.width() + 1 + word.width() > cols {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
     }
+    lines
 }
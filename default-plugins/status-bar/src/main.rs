@@ -17,8 +17,8 @@ use zellij_tile_utils::{palette_match, style};
 use first_line::first_line;
 use second_line::{
     floating_panes_are_visible, fullscreen_panes_to_hide, keybinds,
-    locked_floating_panes_are_visible, locked_fullscreen_panes_to_hide, system_clipboard_error,
-    text_copied_hint,
+    locked_floating_panes_are_visible, locked_fullscreen_panes_to_hide, notification,
+    system_clipboard_error, text_copied_hint, view_only_hint,
 };
 use tip::utils::get_cached_tip_name;
 
@@ -35,6 +35,7 @@ struct State {
     mode_info: ModeInfo,
     text_copy_destination: Option<CopyDestination>,
     display_system_clipboard_failure: bool,
+    notification: Option<String>,
 }
 
 register_plugin!(State);
@@ -187,6 +188,7 @@ impl ZellijPlugin for State {
             EventType::CopyToClipboard,
             EventType::InputReceived,
             EventType::SystemClipboardFailure,
+            EventType::Notification,
         ]);
     }
 
@@ -222,14 +224,20 @@ impl ZellijPlugin for State {
                 should_render = true;
                 self.display_system_clipboard_failure = true;
             },
+            Event::Notification(message) => {
+                should_render = true;
+                self.notification = Some(message);
+            },
             Event::InputReceived => {
                 if self.text_copy_destination.is_some()
                     || self.display_system_clipboard_failure == true
+                    || self.notification.is_some()
                 {
                     should_render = true;
                 }
                 self.text_copy_destination = None;
                 self.display_system_clipboard_failure = false;
+                self.notification = None;
             },
             _ => {},
         };
@@ -294,6 +302,13 @@ impl State {
             text_copied_hint(&self.mode_info.style.colors, copy_destination)
         } else if self.display_system_clipboard_failure {
             system_clipboard_error(&self.mode_info.style.colors)
+        } else if let Some(message) = &self.notification {
+            notification(&self.mode_info.style.colors, message, cols)
+        } else if self.mode_info.read_only
+            && matches!(self.mode_info.mode, InputMode::Normal | InputMode::Locked)
+        {
+            // other modes still show their keybinds, e.g. how to detach from session mode
+            view_only_hint(&self.mode_info.style.colors)
         } else if let Some(active_tab) = active_tab {
             if active_tab.is_fullscreen_active {
                 match self.mode_info.mode {
@@ -534,6 +534,35 @@ pub fn locked_floating_panes_are_visible(palette: &Palette) -> LinePart {
     }
 }
 
+pub fn view_only_hint(palette: &Palette) -> LinePart {
+    let orange_color = palette_match!(palette.orange);
+    let text_color = palette_match!(match palette.theme_hue {
+        ThemeHue::Dark => palette.white,
+        ThemeHue::Light => palette.black,
+    });
+    let view_only_text = " VIEW ONLY";
+    let explanation = " - your input is not sent to this session";
+
+    let len = view_only_text.chars().count() + explanation.chars().count();
+    LinePart {
+        part: format!(
+            "{}{}",
+            Style::new().fg(orange_color).bold().paint(view_only_text),
+            Style::new().fg(text_color).paint(explanation),
+        ),
+        len,
+    }
+}
+
+pub fn notification(palette: &Palette, message: &str, max_len: usize) -> LinePart {
+    let green_color = palette_match!(palette.green);
+    let text: String = format!(" {}", message).chars().take(max_len).collect();
+    LinePart {
+        part: Style::new().fg(green_color).bold().paint(&text).to_string(),
+        len: text.chars().count(),
+    }
+}
+
 #[cfg(test)]
 /// Unit tests.
 ///
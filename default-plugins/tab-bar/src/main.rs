@@ -90,6 +90,8 @@ impl ZellijPlugin for State {
             return;
         }
         let mut all_tabs: Vec<LinePart> = vec![];
+        // every tab gets an equal share of the line to show who is on it
+        let max_tab_len = cols.saturating_sub(1) / self.tabs.len();
         let mut active_tab_index = 0;
         let mut is_alternate_tab = false;
         for t in &mut self.tabs {
@@ -108,6 +110,7 @@ impl ZellijPlugin for State {
                 is_alternate_tab,
                 self.mode_info.style.colors,
                 self.mode_info.capabilities,
+                max_tab_len,
             );
             is_alternate_tab = !is_alternate_tab;
             all_tabs.push(tab);
@@ -135,7 +135,6 @@ fn main() {
         commands::delete_session(target_session, force);
     } else if let Some(Command::Ssh(ssh_opts)) = opts.command.clone() {
         configure_logger(true, Some(logger_handle));
-        println!("{:?}", ssh_opts);
         ssh::start(opts, ssh_opts)
     } else if let Some(path) = opts.server {
         commands::start_server(path, opts.debug);
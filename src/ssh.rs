@@ -1,11 +1,241 @@
-use sshd::{server::Server, zellij::init_zellij_server};
+use std::time::Duration;
+
+use sshd::{
+    audit::{parse_time, print_audit_records, AuditLog},
+    forward::print_active_forwards,
+    passcode::{print_passcode, print_passcodes, PasscodeStore},
+    relay::{relay_id, Relay, RelayAddress},
+    server::Server,
+    share::{print_connect_strings, print_relay_connect_strings, ShareRegistry},
+    zellij::init_zellij_server,
+};
 use tokio::runtime::Builder;
-use zellij_utils::{cli::CliArgs, ssh::Ssh};
+use zellij_utils::{
+    cli::CliArgs,
+    envs,
+    input::config::{Config, ConfigError},
+    miette::Report,
+    ssh::{PasscodeCommand, Ssh, SshCommand},
+};
+
+use crate::sessions::get_sessions;
 
 // In ssh mode, it will first the start server,then start client, finally deatch the session
 pub(crate) fn start(args: CliArgs, ssh_opts: Ssh) {
-    init_zellij_server(args.clone());
-    let server = Server::new(args, ssh_opts);
+    // the cli flags override the `ssh` block of the config file, which has to load as a broken
+    // one would start the server without the auth and limits it sets
+    let ssh_opts = match Config::try_from(&args) {
+        Ok(config) => config.ssh.merge(ssh_opts),
+        Err(e) => {
+            if let ConfigError::KdlError(error) = e {
+                let report: Report = error.into();
+                eprintln!("{:?}", report);
+            } else {
+                eprintln!("{}", e);
+            }
+            std::process::exit(1);
+        },
+    };
+    match ssh_opts.command.clone() {
+        Some(SshCommand::Share { session_name }) => {
+            share(&args, &ssh_opts, session_name);
+            return;
+        },
+        Some(SshCommand::Forwards) => {
+            print_active_forwards(args.data_dir.as_deref());
+            return;
+        },
+        Some(SshCommand::Audit {
+            session,
+            since,
+            until,
+            json,
+        }) => {
+            audit(&args, session, since, until, json);
+            return;
+        },
+        Some(SshCommand::Passcode(command)) => {
+            passcode(&args, &ssh_opts, command);
+            return;
+        },
+        Some(SshCommand::Relay) => {
+            relay(&args, ssh_opts);
+            return;
+        },
+        None => {},
+    }
+    let stdio = ssh_opts.stdio;
+    if stdio {
+        // stdout carries the connection, so nothing else may be printed. The connection joins
+        // a session that is already running instead of starting one
+        if let Some(session_name) = &args.session {
+            envs::set_session_name(session_name.clone());
+        }
+    } else {
+        init_zellij_server(args.clone());
+    }
+    let server = match Server::new(args, ssh_opts) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+    };
     let rt = Builder::new_multi_thread().enable_all().build().unwrap();
-    rt.block_on(server.listen()).unwrap();
+    let served = if stdio {
+        rt.block_on(server.serve_stdio())
+    } else {
+        rt.block_on(server.listen())
+    };
+    if let Err(e) = served {
+        log::error!("{}", e);
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn share(args: &CliArgs, ssh_opts: &Ssh, session_name: Option<String>) {
+    let registry = match ShareRegistry::new(args.data_dir.as_deref()) {
+        Ok(registry) => registry,
+        Err(e) => {
+            eprintln!("Failed to open the share tokens directory: {}", e);
+            std::process::exit(1);
+        },
+    };
+    let running_sessions: Vec<String> = get_sessions()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let session_names = match session_name {
+        Some(session_name) if running_sessions.contains(&session_name) => vec![session_name],
+        Some(session_name) => {
+            eprintln!("No session named {:?} found.", session_name);
+            std::process::exit(1);
+        },
+        None if running_sessions.is_empty() => {
+            eprintln!("No active zellij sessions found.");
+            std::process::exit(1);
+        },
+        None => running_sessions,
+    };
+    print_connect_strings(&registry, &session_names, ssh_opts.port());
+    if let Some(relay) = &ssh_opts.relay {
+        match (
+            relay.parse::<RelayAddress>(),
+            relay_id(args.data_dir.as_deref()),
+        ) {
+            (Ok(relay), Ok(relay_id)) => print_relay_connect_strings(
+                &registry,
+                &session_names,
+                &relay.to_string(),
+                &relay_id,
+            ),
+            (Err(e), _) => eprintln!("Invalid relay address: {}", e),
+            (_, Err(e)) => eprintln!("Failed to read the relay id: {}", e),
+        }
+    }
+}
+
+fn relay(args: &CliArgs, ssh_opts: Ssh) {
+    let relay = match Relay::new(args.data_dir.clone(), ssh_opts) {
+        Ok(relay) => relay,
+        Err(e) => {
+            eprintln!("Failed to open the relay hosts file: {}", e);
+            std::process::exit(1);
+        },
+    };
+    let rt = Builder::new_multi_thread().enable_all().build().unwrap();
+    if let Err(e) = rt.block_on(relay.listen()) {
+        log::error!("{}", e);
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn passcode(args: &CliArgs, ssh_opts: &Ssh, command: PasscodeCommand) {
+    let store = match PasscodeStore::new(args.data_dir.as_deref()) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Failed to open the passcodes directory: {}", e);
+            std::process::exit(1);
+        },
+    };
+    match command {
+        PasscodeCommand::New {
+            session_name,
+            role,
+            valid_for,
+        } => {
+            // from within a session, the passcode is for that session
+            let session_name = match session_name.or_else(|| envs::get_session_name().ok()) {
+                Some(session_name) => session_name,
+                None => {
+                    eprintln!("Give the name of the session the passcode is for.");
+                    std::process::exit(1);
+                },
+            };
+            let session_is_running = get_sessions()
+                .unwrap_or_default()
+                .iter()
+                .any(|(name, _)| name == &session_name);
+            if !session_is_running {
+                eprintln!("No session named {:?} found.", session_name);
+                std::process::exit(1);
+            }
+            match store.generate(
+                &session_name,
+                role,
+                Duration::from_secs(valid_for.saturating_mul(60)),
+            ) {
+                Ok(passcode) => print_passcode(&passcode, ssh_opts.port()),
+                Err(e) => {
+                    eprintln!("Failed to generate a passcode: {}", e);
+                    std::process::exit(1);
+                },
+            }
+        },
+        PasscodeCommand::Revoke { passcode } => match store.revoke(&passcode) {
+            Ok(true) => println!("Passcode revoked."),
+            Ok(false) => {
+                eprintln!("No unused passcode {:?} found.", passcode);
+                std::process::exit(1);
+            },
+            Err(e) => {
+                eprintln!("Failed to revoke the passcode: {}", e);
+                std::process::exit(1);
+            },
+        },
+        PasscodeCommand::List => match store.list() {
+            Ok(passcodes) => print_passcodes(&passcodes),
+            Err(e) => {
+                eprintln!("Failed to list the passcodes: {}", e);
+                std::process::exit(1);
+            },
+        },
+    }
+}
+
+fn audit(
+    args: &CliArgs,
+    session: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    json: bool,
+) {
+    let parse = |time: Option<String>| match time.as_deref().map(parse_time).transpose() {
+        Ok(time) => time,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        },
+    };
+    let (since, until) = (parse(since), parse(until));
+    match AuditLog::open(args.data_dir.as_deref()).query(session.as_deref(), since, until) {
+        Ok(records) => print_audit_records(&records, json),
+        Err(e) => {
+            eprintln!("Failed to read the audit log: {}", e);
+            std::process::exit(1);
+        },
+    }
 }
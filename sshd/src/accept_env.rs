@@ -0,0 +1,56 @@
+use crate::authorized_keys::wildcard_match;
+
+/// Accepted when no patterns are configured, what a terminal needs to look and behave right
+const DEFAULT_ACCEPTED_ENV: &[&str] = &["TERM", "COLORTERM", "LANG", "LC_*"];
+
+/// The environment variables clients may send with env requests, like `AcceptEnv` of sshd
+#[derive(Clone, Debug)]
+pub struct AcceptedEnv(Vec<String>);
+
+impl AcceptedEnv {
+    pub fn new(patterns: &[String]) -> Self {
+        if patterns.is_empty() {
+            AcceptedEnv(
+                DEFAULT_ACCEPTED_ENV
+                    .iter()
+                    .map(|pattern| pattern.to_string())
+                    .collect(),
+            )
+        } else {
+            AcceptedEnv(patterns.to_vec())
+        }
+    }
+
+    pub fn accepts(&self, name: &str, value: &str) -> bool {
+        // a nul byte can't be put in the environment of a process
+        !name.is_empty()
+            && !name.contains(|c| c == '=' || c == '\0')
+            && !value.contains('\0')
+            && self.0.iter().any(|pattern| wildcard_match(pattern, name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn terminal_variables_are_accepted_by_default() {
+        let accepted_env = AcceptedEnv::new(&[]);
+        assert!(accepted_env.accepts("TERM", "xterm-256color"));
+        assert!(accepted_env.accepts("COLORTERM", "truecolor"));
+        assert!(accepted_env.accepts("LANG", "en_US.UTF-8"));
+        assert!(accepted_env.accepts("LC_ALL", "C"));
+        assert!(!accepted_env.accepts("LD_PRELOAD", "/tmp/evil.so"));
+        assert!(!accepted_env.accepts("PATH", "/tmp"));
+        assert!(!accepted_env.accepts("LANG", "en\0"));
+    }
+
+    #[test]
+    fn configured_patterns_replace_the_defaults() {
+        let accepted_env = AcceptedEnv::new(&["GIT_*".to_string()]);
+        assert!(accepted_env.accepts("GIT_AUTHOR_NAME", "Alice"));
+        assert!(!accepted_env.accepts("TERM", "xterm"));
+        assert!(!accepted_env.accepts("GIT_=X", "Alice"));
+    }
+}
@@ -0,0 +1,136 @@
+use std::{net::SocketAddr, time::Duration};
+
+use zellij_utils::{ipc::Prompt, ssh::ShareRole};
+
+use crate::{audit::AuthMethod, exec::ask_session};
+
+pub(crate) const DEFAULT_APPROVAL_TIMEOUT_SECS: u64 = 60;
+// the dialog dismisses itself once the timeout passed, this covers the way back of its answer
+const ANSWER_DELAY: Duration = Duration::from_secs(2);
+
+const ALLOW: &str = "Allow";
+const VIEW_ONLY: &str = "View-only";
+const DENY: &str = "Deny";
+
+/// What the host answered to a guest asking to join a session
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Approval {
+    Allow,
+    ViewOnly,
+    Deny,
+}
+
+impl Approval {
+    /// A dismissed prompt denies the guest
+    fn from_answer(answer: Option<&str>) -> Self {
+        match answer {
+            Some(ALLOW) => Approval::Allow,
+            Some(VIEW_ONLY) => Approval::ViewOnly,
+            _ => Approval::Deny,
+        }
+    }
+}
+
+/// A guest waiting for the host of a session to let them in
+#[derive(Clone, Debug)]
+pub(crate) struct Guest {
+    pub user: String,
+    pub peer_addr: Option<String>,
+    pub auth_method: Option<AuthMethod>,
+    pub role: ShareRole,
+}
+
+impl Guest {
+    fn prompt(&self, timeout: Duration) -> Prompt {
+        let mut message = match &self.peer_addr {
+            Some(peer_addr) => format!("{}@{}", self.user, peer_addr),
+            None => self.user.clone(),
+        };
+        if let Some(auth_method) = &self.auth_method {
+            message.push_str(&format!(" ({})", auth_method));
+        }
+        message.push_str(&format!(" wants to join as {}", self.role));
+        // viewers can only be let in as they are
+        let choices = match self.role {
+            ShareRole::Collaborator => vec![ALLOW, VIEW_ONLY, DENY],
+            ShareRole::Viewer => vec![ALLOW, DENY],
+        };
+        Prompt {
+            title: "SSH guest".to_string(),
+            message,
+            choices: choices
+                .into_iter()
+                .map(|choice| choice.to_string())
+                .collect(),
+            timeout_secs: timeout.as_secs(),
+        }
+    }
+}
+
+/// Shows the host of `session_name` who wants to join and waits up to `timeout` for them to
+/// answer, the guest is denied when nobody does
+pub(crate) async fn ask_host(session_name: &str, guest: &Guest, timeout: Duration) -> Approval {
+    let prompt = guest.prompt(timeout);
+    let session_name = session_name.to_string();
+    let answer = tokio::time::timeout(
+        timeout + ANSWER_DELAY,
+        tokio::task::spawn_blocking(move || ask_session(&session_name, prompt)),
+    )
+    .await;
+    match answer {
+        Ok(Ok(answer)) => Approval::from_answer(answer.as_deref()),
+        Ok(Err(e)) => {
+            log::error!("Failed to ask for approval: {}", e);
+            Approval::Deny
+        },
+        Err(_) => {
+            log::info!("No answer from the host of the session in time");
+            Approval::Deny
+        },
+    }
+}
+
+/// The address a guest connects from as shown to the host, without the port
+pub(crate) fn peer_ip(peer_addr: Option<SocketAddr>) -> Option<String> {
+    peer_addr.map(|peer_addr| peer_addr.ip().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prompt_shows_who_wants_to_join() {
+        let guest = Guest {
+            user: "alice".to_string(),
+            peer_addr: Some("10.0.0.5".to_string()),
+            auth_method: Some(AuthMethod::PublicKey("SHA256:abc".to_string())),
+            role: ShareRole::Collaborator,
+        };
+        let prompt = guest.prompt(Duration::from_secs(30));
+        assert_eq!(
+            prompt.message,
+            "alice@10.0.0.5 (key SHA256:abc) wants to join as collaborator"
+        );
+        assert_eq!(prompt.choices, vec!["Allow", "View-only", "Deny"]);
+        assert_eq!(prompt.timeout_secs, 30);
+
+        let viewer = Guest {
+            peer_addr: None,
+            auth_method: None,
+            role: ShareRole::Viewer,
+            ..guest
+        };
+        let prompt = viewer.prompt(Duration::from_secs(30));
+        assert_eq!(prompt.message, "alice wants to join as viewer");
+        assert_eq!(prompt.choices, vec!["Allow", "Deny"]);
+    }
+
+    #[test]
+    fn unanswered_prompts_deny() {
+        assert_eq!(Approval::from_answer(Some("Allow")), Approval::Allow);
+        assert_eq!(Approval::from_answer(Some("View-only")), Approval::ViewOnly);
+        assert_eq!(Approval::from_answer(Some("Deny")), Approval::Deny);
+        assert_eq!(Approval::from_answer(None), Approval::Deny);
+    }
+}
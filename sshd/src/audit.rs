@@ -0,0 +1,256 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+use zellij_utils::{humantime, ssh::ShareRole};
+
+use crate::host_keys::{create_ssh_data_dir, ssh_data_dir};
+
+const AUDIT_LOG_FILE: &str = "audit.jsonl";
+
+/// How a connection authenticated
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// the fingerprint of the key
+    PublicKey(String),
+    Passcode,
+    None,
+}
+
+impl Display for AuthMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthMethod::PublicKey(fingerprint) => write!(f, "key {}", fingerprint),
+            AuthMethod::Passcode => write!(f, "passcode"),
+            AuthMethod::None => write!(f, "no auth"),
+        }
+    }
+}
+
+/// One connection to the ssh server, written to the audit log once it is closed
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub peer_addr: Option<String>,
+    /// empty when the connection never authenticated
+    pub user: String,
+    pub auth_method: Option<AuthMethod>,
+    pub role: Option<ShareRole>,
+    pub session: Option<String>,
+    /// rfc3339, in UTC
+    pub connected_at: String,
+    pub disconnected_at: Option<String>,
+    /// terminal input and data sent through forwards
+    pub bytes_in: u64,
+    /// terminal output and data received through forwards
+    pub bytes_out: u64,
+}
+
+impl AuditRecord {
+    pub fn new(peer_addr: Option<String>) -> Self {
+        AuditRecord {
+            peer_addr,
+            connected_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn overlaps(&self, since: Option<SystemTime>, until: Option<SystemTime>) -> bool {
+        let connected_at = humantime::parse_rfc3339_weak(&self.connected_at).ok();
+        let disconnected_at = self
+            .disconnected_at
+            .as_deref()
+            .and_then(|at| humantime::parse_rfc3339_weak(at).ok());
+        let ended_before = match (since, disconnected_at) {
+            (Some(since), Some(disconnected_at)) => disconnected_at < since,
+            _ => false,
+        };
+        let started_after = match (until, connected_at) {
+            (Some(until), Some(connected_at)) => connected_at > until,
+            _ => false,
+        };
+        !ended_before && !started_after
+    }
+}
+
+impl Display for AuditRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let user = if self.user.is_empty() {
+            "(not authenticated)"
+        } else {
+            &self.user
+        };
+        write!(
+            f,
+            "{} - {}  {} from {}",
+            self.connected_at,
+            self.disconnected_at.as_deref().unwrap_or("?"),
+            user,
+            self.peer_addr.as_deref().unwrap_or("unknown address")
+        )?;
+        if let Some(auth_method) = &self.auth_method {
+            write!(f, " ({})", auth_method)?;
+        }
+        if let Some(session) = &self.session {
+            write!(f, "  session {}", session)?;
+        }
+        if let Some(role) = &self.role {
+            write!(f, " as {}", role)?;
+        }
+        write!(f, "  in {} B, out {} B", self.bytes_in, self.bytes_out)
+    }
+}
+
+/// The JSON lines audit log of the ssh server, `ssh/audit.jsonl` in the data dir
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(data_dir: Option<&Path>) -> io::Result<Self> {
+        Ok(AuditLog {
+            path: create_ssh_data_dir(data_dir)?.join(AUDIT_LOG_FILE),
+        })
+    }
+
+    pub fn open(data_dir: Option<&Path>) -> Self {
+        AuditLog {
+            path: ssh_data_dir(data_dir).join(AUDIT_LOG_FILE),
+        }
+    }
+
+    pub fn append(&self, record: &AuditRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line + "\n",
+            Err(e) => {
+                log::error!("Failed to serialize audit record: {}", e);
+                return;
+            },
+        };
+        // a single write of a whole line, so records of connections closing at the same time
+        // don't interleave
+        let written = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .mode(0o600)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            log::error!("Failed to write to the audit log {:?}: {}", self.path, e);
+        }
+    }
+
+    /// The records of connections to `session` (any session if `None`) that were open at
+    /// some point between `since` and `until`
+    pub fn query(
+        &self,
+        session: Option<&str>,
+        since: Option<SystemTime>,
+        until: Option<SystemTime>,
+    ) -> io::Result<Vec<AuditRecord>> {
+        let raw = match fs::read_to_string(&self.path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        Ok(raw
+            .lines()
+            .filter_map(|line| serde_json::from_str::<AuditRecord>(line).ok())
+            .filter(|record| session.is_none() || record.session.as_deref() == session)
+            .filter(|record| record.overlaps(since, until))
+            .collect())
+    }
+}
+
+/// Parses the bounds of an audit log query, either an rfc3339 time (`2023-11-01T09:00:00Z`)
+/// or how long ago (`2h`, `3days`)
+pub fn parse_time(time: &str) -> Result<SystemTime, String> {
+    humantime::parse_rfc3339_weak(time)
+        .or_else(|_| {
+            humantime::parse_duration(time).map(|ago| {
+                SystemTime::now()
+                    .checked_sub(ago)
+                    .unwrap_or(SystemTime::UNIX_EPOCH)
+            })
+        })
+        .map_err(|_| format!("{:?} is neither a time nor a duration", time))
+}
+
+/// Prints `records`, one per line, as JSON when `json` is set
+pub fn print_audit_records(records: &[AuditRecord], json: bool) {
+    if records.is_empty() && !json {
+        println!("No connections found.");
+    }
+    for record in records {
+        if json {
+            if let Ok(line) = serde_json::to_string(record) {
+                println!("{}", line);
+            }
+        } else {
+            println!("{}", record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+    use zellij_utils::tempfile::tempdir;
+
+    fn record(session: &str, connected_at: &str, disconnected_at: &str) -> AuditRecord {
+        AuditRecord {
+            peer_addr: Some("10.0.0.5:52000".to_string()),
+            user: "alice".to_string(),
+            auth_method: Some(AuthMethod::PublicKey("SHA256:abc".to_string())),
+            role: Some(ShareRole::Collaborator),
+            session: Some(session.to_string()),
+            connected_at: connected_at.to_string(),
+            disconnected_at: Some(disconnected_at.to_string()),
+            bytes_in: 10,
+            bytes_out: 2000,
+        }
+    }
+
+    #[test]
+    fn records_can_be_queried_by_session_and_time() {
+        let data_dir = tempdir().unwrap();
+        let audit_log = AuditLog::new(Some(data_dir.path())).unwrap();
+        let morning = record("pairing", "2023-11-01T09:00:00Z", "2023-11-01T10:00:00Z");
+        let evening = record("pairing", "2023-11-01T18:00:00Z", "2023-11-01T19:00:00Z");
+        let other = record("other", "2023-11-01T09:30:00Z", "2023-11-01T09:45:00Z");
+        for r in [&morning, &evening, &other] {
+            audit_log.append(r);
+        }
+        let at = |t: &str| Some(humantime::parse_rfc3339_weak(t).unwrap());
+
+        assert_eq!(
+            audit_log.query(Some("pairing"), None, None).unwrap(),
+            vec![morning.clone(), evening.clone()]
+        );
+        assert_eq!(
+            audit_log
+                .query(None, at("2023-11-01T09:40:00Z"), at("2023-11-01T12:00:00Z"))
+                .unwrap(),
+            vec![morning.clone(), other]
+        );
+        assert_eq!(
+            audit_log
+                .query(Some("pairing"), at("2023-11-01T18:30:00Z"), None)
+                .unwrap(),
+            vec![evening]
+        );
+        let future = SystemTime::now() + Duration::from_secs(3600);
+        assert!(audit_log
+            .query(None, Some(future), None)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use russh_keys::key::PublicKey;
+
+/// The key types we recognize at the start of an authorized_keys entry, used to tell
+/// whether a line starts with options or with the key itself
+const KEY_TYPE_PREFIXES: &[&str] = &["ssh-", "ecdsa-sha2-", "sk-ssh-", "sk-ecdsa-sha2-"];
+
+/// Options of an authorized_keys entry, see the AUTHORIZED_KEYS FILE FORMAT section of sshd(8)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyOptions {
+    /// `from="pattern-list"`, the key is only accepted from matching peer addresses
+    pub from: Option<Vec<String>>,
+    /// `command="..."`, zellij cli arguments used instead of the ones sent by the client
+    pub command: Option<String>,
+    /// `no-pty` or `restrict`, pty requests are refused
+    pub no_pty: bool,
+    /// `no-port-forwarding` or `restrict`, direct-tcpip channels are refused
+    pub no_port_forwarding: bool,
+    /// `permitopen="host:port"`, forwards are limited to these targets, `*` matches any port
+    pub permit_open: Option<Vec<String>>,
+    /// `view-only`, the key can watch sessions but not type into them
+    pub view_only: bool,
+}
+
+impl KeyOptions {
+    fn parse(raw: &str) -> Result<Self, String> {
+        let mut options = KeyOptions::default();
+        for option in split_options(raw)? {
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.to_lowercase(), Some(unquote(value)?)),
+                None => (option.to_lowercase(), None),
+            };
+            match (name.as_str(), value) {
+                ("from", Some(patterns)) => {
+                    options.from = Some(patterns.split(',').map(|p| p.to_string()).collect())
+                },
+                ("command", Some(command)) => options.command = Some(command),
+                ("no-pty", None) => options.no_pty = true,
+                ("no-port-forwarding", None) => options.no_port_forwarding = true,
+                ("restrict", None) => {
+                    options.no_pty = true;
+                    options.no_port_forwarding = true;
+                },
+                ("permitopen", Some(target)) => options
+                    .permit_open
+                    .get_or_insert_with(Vec::new)
+                    .push(target),
+                ("view-only", None) => options.view_only = true,
+                (name, _) => log::debug!("Ignoring unsupported authorized_keys option {}", name),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Whether `peer_addr` satisfies the `from=` restriction of this key, if any.
+    ///
+    /// As in OpenSSH, a negated pattern (`!pattern`) that matches rejects the key even if
+    /// another pattern in the list matches.
+    pub fn allows_peer(&self, peer_addr: Option<IpAddr>) -> bool {
+        let patterns = match &self.from {
+            Some(patterns) => patterns,
+            None => return true,
+        };
+        let peer = match peer_addr {
+            Some(peer) => peer.to_string(),
+            None => return false,
+        };
+        let mut allowed = false;
+        for pattern in patterns {
+            match pattern.strip_prefix('!') {
+                Some(negated) if wildcard_match(negated, &peer) => return false,
+                Some(_) => {},
+                None if wildcard_match(pattern, &peer) => allowed = true,
+                None => {},
+            }
+        }
+        allowed
+    }
+
+    /// Whether the key may open a forward to `host:port`, the allowlist of the server still
+    /// applies on top of it
+    pub fn allows_forward(&self, host: &str, port: u32) -> bool {
+        if self.no_port_forwarding {
+            return false;
+        }
+        let targets = match &self.permit_open {
+            Some(targets) => targets,
+            None => return true,
+        };
+        targets.iter().any(|target| match target.rsplit_once(':') {
+            Some((permitted_host, permitted_port)) => {
+                let permitted_host = permitted_host.trim_start_matches('[').trim_end_matches(']');
+                permitted_host.eq_ignore_ascii_case(host)
+                    && (permitted_port == "*" || permitted_port.parse() == Ok(port))
+            },
+            None => false,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorizedKey {
+    pub key: PublicKey,
+    pub options: KeyOptions,
+    pub comment: String,
+}
+
+impl AuthorizedKey {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (options, rest) = if starts_with_key_type(line) {
+            (KeyOptions::default(), line)
+        } else {
+            let end = options_end(line);
+            (KeyOptions::parse(&line[..end])?, line[end..].trim_start())
+        };
+        let mut fields = rest.splitn(3, char::is_whitespace);
+        let _key_type = fields.next().ok_or("missing key type")?;
+        let key_data = fields.next().ok_or("missing key data")?;
+        let comment = fields.next().unwrap_or_default().trim().to_string();
+        let key = russh_keys::parse_public_key_base64(key_data).map_err(|e| e.to_string())?;
+        Ok(AuthorizedKey {
+            key,
+            options,
+            comment,
+        })
+    }
+
+    pub fn matches(&self, public_key: &PublicKey) -> bool {
+        self.key.name() == public_key.name() && self.key.fingerprint() == public_key.fingerprint()
+    }
+}
+
+/// An OpenSSH-format authorized_keys file.
+///
+/// The file is re-read for every authentication attempt so edits are picked up without
+/// restarting the server.
+#[derive(Clone, Debug)]
+pub struct AuthorizedKeys {
+    path: PathBuf,
+}
+
+impl AuthorizedKeys {
+    pub fn new(path: PathBuf) -> Self {
+        AuthorizedKeys { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> io::Result<Vec<AuthorizedKey>> {
+        let raw = fs::read_to_string(&self.path)?;
+        Ok(parse_authorized_keys(&raw, &self.path))
+    }
+
+    /// Finds the entry authorizing `public_key` for a peer connecting from `peer_addr`
+    pub fn find(&self, public_key: &PublicKey, peer_addr: Option<IpAddr>) -> Option<AuthorizedKey> {
+        let keys = match self.load() {
+            Ok(keys) => keys,
+            Err(e) => {
+                log::warn!("Failed to read authorized keys {:?}: {}", self.path, e);
+                return None;
+            },
+        };
+        keys.into_iter()
+            .find(|entry| entry.matches(public_key) && entry.options.allows_peer(peer_addr))
+    }
+}
+
+fn parse_authorized_keys(raw: &str, path: &Path) -> Vec<AuthorizedKey> {
+    raw.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .filter_map(|(index, line)| match AuthorizedKey::parse(line) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                log::warn!("Skipping invalid entry at {:?}:{}: {}", path, index + 1, e);
+                None
+            },
+        })
+        .collect()
+}
+
+fn starts_with_key_type(line: &str) -> bool {
+    KEY_TYPE_PREFIXES
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+// the options end at the first whitespace outside of double quotes
+fn options_end(line: &str) -> usize {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => return index,
+            _ => {},
+        }
+    }
+    line.len()
+}
+
+// options are separated by commas outside of double quotes
+fn split_options(raw: &str) -> Result<Vec<String>, String> {
+    let mut options = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for c in raw.chars() {
+        match c {
+            _ if escaped => {
+                current.push(c);
+                escaped = false;
+            },
+            '\\' => {
+                current.push(c);
+                escaped = true;
+            },
+            '"' => {
+                current.push(c);
+                in_quotes = !in_quotes;
+            },
+            ',' if !in_quotes => options.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quote in options".to_string());
+    }
+    options.push(current);
+    Ok(options.into_iter().filter(|o| !o.is_empty()).collect())
+}
+
+fn unquote(value: &str) -> Result<String, String> {
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| format!("option value must be quoted: {}", value))?;
+    Ok(inner.replace("\\\"", "\""))
+}
+
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIJdD7y3aLq454yWBdwLWbieU1ebz9/cu7/QEXn9OIeZJ";
+
+    #[test]
+    fn parses_plain_entry() {
+        let entry = AuthorizedKey::parse(&format!("ssh-ed25519 {} alice@laptop", KEY)).unwrap();
+        assert_eq!(entry.comment, "alice@laptop");
+        assert_eq!(entry.options, KeyOptions::default());
+    }
+
+    #[test]
+    fn parses_entry_with_options() {
+        let entry = AuthorizedKey::parse(&format!(
+            r#"from="10.0.0.*,!10.0.0.13",command="attach \"pairing\"",no-pty,view-only ssh-ed25519 {}"#,
+            KEY
+        ))
+        .unwrap();
+        assert_eq!(
+            entry.options.from,
+            Some(vec!["10.0.0.*".to_string(), "!10.0.0.13".to_string()])
+        );
+        assert_eq!(entry.options.command.as_deref(), Some("attach \"pairing\""));
+        assert!(entry.options.no_pty);
+        assert!(entry.options.view_only);
+    }
+
+    #[test]
+    fn from_option_restricts_peers() {
+        let options = KeyOptions {
+            from: Some(vec!["10.0.0.*".to_string(), "!10.0.0.13".to_string()]),
+            ..Default::default()
+        };
+        assert!(options.allows_peer(Some("10.0.0.5".parse().unwrap())));
+        assert!(!options.allows_peer(Some("10.0.0.13".parse().unwrap())));
+        assert!(!options.allows_peer(Some("192.168.1.5".parse().unwrap())));
+        assert!(!options.allows_peer(None));
+        assert!(KeyOptions::default().allows_peer(None));
+    }
+
+    #[test]
+    fn forwarding_options_restrict_forwards() {
+        let entry = AuthorizedKey::parse(&format!(
+            r#"permitopen="localhost:3000",permitopen="[::1]:*" ssh-ed25519 {}"#,
+            KEY
+        ))
+        .unwrap();
+        assert!(entry.options.allows_forward("localhost", 3000));
+        assert!(entry.options.allows_forward("LOCALHOST", 3000));
+        assert!(!entry.options.allows_forward("localhost", 8080));
+        assert!(entry.options.allows_forward("::1", 8080));
+        assert!(KeyOptions::default().allows_forward("localhost", 8080));
+
+        for options in ["no-port-forwarding", "restrict"] {
+            let entry = AuthorizedKey::parse(&format!("{} ssh-ed25519 {}", options, KEY)).unwrap();
+            assert!(!entry.options.allows_forward("localhost", 3000));
+        }
+    }
+
+    #[test]
+    fn invalid_entries_are_skipped() {
+        let raw = format!(
+            "# comment\n\nssh-ed25519 not-base64\nssh-ed25519 {} bob\n",
+            KEY
+        );
+        let keys = parse_authorized_keys(&raw, Path::new("authorized_keys"));
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].comment, "bob");
+    }
+}
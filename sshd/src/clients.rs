@@ -0,0 +1,157 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use tokio::sync::mpsc::UnboundedSender;
+use zellij_utils::ssh::ShareRole;
+
+/// An authenticated ssh client, as shown to the other clients of its session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectedClient {
+    pub user: String,
+    pub peer_addr: Option<String>,
+    pub session_name: Option<String>,
+    pub role: ShareRole,
+    pub connected_at: SystemTime,
+}
+
+#[derive(Debug)]
+struct ClientEntry {
+    client: ConnectedClient,
+    // lines to show on the terminals of the client
+    notices: UnboundedSender<Vec<String>>,
+}
+
+#[derive(Debug, Default)]
+struct ClientsState {
+    next_id: u64,
+    clients: HashMap<u64, ClientEntry>,
+}
+
+/// The ssh clients connected to the server, so they can see and reach each other. Each client
+/// is listed while it holds its [`RegisteredClient`]
+#[derive(Clone, Debug, Default)]
+pub struct ClientRegistry {
+    state: Arc<Mutex<ClientsState>>,
+}
+
+impl ClientRegistry {
+    pub fn register(
+        &self,
+        client: ConnectedClient,
+        notices: UnboundedSender<Vec<String>>,
+    ) -> RegisteredClient {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.clients.insert(id, ClientEntry { client, notices });
+        RegisteredClient {
+            id,
+            registry: self.clone(),
+        }
+    }
+
+    /// The clients of `session_name`, in the order they connected
+    pub fn clients_of(&self, session_name: &str) -> Vec<ConnectedClient> {
+        let state = self.state.lock().unwrap();
+        let mut clients: Vec<ConnectedClient> = state
+            .clients
+            .values()
+            .filter(|entry| entry.client.session_name.as_deref() == Some(session_name))
+            .map(|entry| entry.client.clone())
+            .collect();
+        clients.sort_by_key(|client| client.connected_at);
+        clients
+    }
+
+    fn unregister(&self, id: u64) {
+        self.state.lock().unwrap().clients.remove(&id);
+    }
+}
+
+/// A client listed in the [`ClientRegistry`], it is taken off the list when this is dropped
+#[derive(Debug)]
+pub struct RegisteredClient {
+    id: u64,
+    registry: ClientRegistry,
+}
+
+impl RegisteredClient {
+    /// Shows `lines` to the other clients of the same session that have `role`, returns how
+    /// many were told
+    pub fn notify_others(&self, role: ShareRole, lines: Vec<String>) -> usize {
+        let state = self.registry.state.lock().unwrap();
+        let session_name = match state.clients.get(&self.id) {
+            Some(entry) => entry.client.session_name.clone(),
+            None => return 0,
+        };
+        state
+            .clients
+            .iter()
+            .filter(|(id, entry)| {
+                **id != self.id
+                    && entry.client.session_name == session_name
+                    && entry.client.role == role
+            })
+            .filter(|(_, entry)| entry.notices.send(lines.clone()).is_ok())
+            .count()
+    }
+}
+
+impl Drop for RegisteredClient {
+    fn drop(&mut self) {
+        self.registry.unregister(self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    fn client(user: &str, role: ShareRole, seconds: u64) -> ConnectedClient {
+        ConnectedClient {
+            user: user.to_string(),
+            peer_addr: None,
+            session_name: Some("pairing".to_string()),
+            role,
+            connected_at: SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+        }
+    }
+
+    #[test]
+    fn clients_see_the_others_of_their_session() {
+        let registry = ClientRegistry::default();
+        let (alice_tx, mut alice_rx) = unbounded_channel();
+        let (bob_tx, mut bob_rx) = unbounded_channel();
+        let (carol_tx, mut carol_rx) = unbounded_channel();
+        let alice = registry.register(client("alice", ShareRole::Collaborator, 1), alice_tx);
+        let bob = registry.register(client("bob", ShareRole::Viewer, 2), bob_tx);
+        let _carol = registry.register(client("carol", ShareRole::Collaborator, 3), carol_tx);
+        let users = |clients: Vec<ConnectedClient>| {
+            clients
+                .into_iter()
+                .map(|client| client.user)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            users(registry.clients_of("pairing")),
+            vec!["alice", "bob", "carol"]
+        );
+        assert!(registry.clients_of("other").is_empty());
+
+        assert_eq!(
+            bob.notify_others(ShareRole::Collaborator, vec!["hello".to_string()]),
+            2
+        );
+        assert_eq!(alice_rx.try_recv().unwrap(), vec!["hello".to_string()]);
+        assert_eq!(carol_rx.try_recv().unwrap(), vec!["hello".to_string()]);
+        assert!(bob_rx.try_recv().is_err(), "the sender isn't told");
+
+        drop(alice);
+        assert_eq!(users(registry.clients_of("pairing")), vec!["bob", "carol"]);
+    }
+}
@@ -0,0 +1,181 @@
+use std::mem;
+
+/// The escape character when none is configured, typed at the start of a line like in OpenSSH
+pub const DEFAULT_ESCAPE_CHAR: u8 = b'~';
+
+/// Parses the configured escape character: a single character, or `none` to turn escape
+/// sequences off
+pub fn parse_escape_char(escape_char: Option<&str>) -> Result<Option<u8>, String> {
+    match escape_char {
+        None => Ok(Some(DEFAULT_ESCAPE_CHAR)),
+        Some("none") => Ok(None),
+        Some(escape_char) => match escape_char.as_bytes() {
+            [byte] if byte.is_ascii_graphic() => Ok(Some(*byte)),
+            _ => Err(format!(
+                "{:?} is not a valid escape character, use a single printable character or none",
+                escape_char
+            )),
+        },
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscapeCommand {
+    Detach,
+    ConnectionInfo,
+    RequestControl,
+    ListClients,
+    Help,
+}
+
+impl EscapeCommand {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            b'.' => Some(EscapeCommand::Detach),
+            b'i' => Some(EscapeCommand::ConnectionInfo),
+            b'c' => Some(EscapeCommand::RequestControl),
+            b'v' => Some(EscapeCommand::ListClients),
+            b'?' => Some(EscapeCommand::Help),
+            _ => None,
+        }
+    }
+}
+
+/// The lines shown for [`EscapeCommand::Help`]
+pub fn help(escape_char: u8) -> Vec<String> {
+    let escape_char = char::from(escape_char);
+    vec![
+        "Supported escape sequences:".to_string(),
+        format!("{}.  detach from the session", escape_char),
+        format!("{}i  show the connection info", escape_char),
+        format!("{}c  ask for control of the session", escape_char),
+        format!("{}v  list who is connected", escape_char),
+        format!("{}?  this message", escape_char),
+        format!("{}{}  send the escape character", escape_char, escape_char),
+        "Escape sequences are only recognized after a newline".to_string(),
+    ]
+}
+
+/// What the input of a channel turned into
+#[derive(Debug, PartialEq, Eq)]
+pub enum Escaped {
+    Input(Vec<u8>),
+    Command(EscapeCommand),
+}
+
+/// Picks OpenSSH-style escape sequences out of the input of a channel: the escape character
+/// at the start of a line, followed by a command. The escape character typed twice sends it
+/// once, followed by anything else it is passed on as it was typed. A sequence may be split
+/// across reads.
+#[derive(Debug)]
+pub struct EscapeParser {
+    escape_char: Option<u8>,
+    at_line_start: bool,
+    escape_pending: bool,
+}
+
+impl EscapeParser {
+    pub fn new(escape_char: Option<u8>) -> Self {
+        EscapeParser {
+            escape_char,
+            at_line_start: true,
+            escape_pending: false,
+        }
+    }
+
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Escaped> {
+        let escape_char = match self.escape_char {
+            Some(escape_char) => escape_char,
+            None => return vec![Escaped::Input(data.to_vec())],
+        };
+        let mut escaped = vec![];
+        let mut input = vec![];
+        for &byte in data {
+            if self.escape_pending {
+                self.escape_pending = false;
+                if byte == escape_char {
+                    input.push(byte);
+                    self.at_line_start = false;
+                    continue;
+                }
+                if let Some(command) = EscapeCommand::from_byte(byte) {
+                    if !input.is_empty() {
+                        escaped.push(Escaped::Input(mem::take(&mut input)));
+                    }
+                    escaped.push(Escaped::Command(command));
+                    continue;
+                }
+                input.push(escape_char);
+            } else if self.at_line_start && byte == escape_char {
+                self.escape_pending = true;
+                continue;
+            }
+            input.push(byte);
+            self.at_line_start = byte == b'\r' || byte == b'\n';
+        }
+        if !input.is_empty() {
+            escaped.push(Escaped::Input(input));
+        }
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn input(data: &[u8]) -> Escaped {
+        Escaped::Input(data.to_vec())
+    }
+
+    #[test]
+    fn escape_sequences_are_recognized_at_the_start_of_a_line() {
+        let mut parser = EscapeParser::new(Some(b'~'));
+        assert_eq!(
+            parser.feed(b"~."),
+            vec![Escaped::Command(EscapeCommand::Detach)]
+        );
+        assert_eq!(
+            parser.feed(b"ls ~.\r~i"),
+            vec![
+                input(b"ls ~.\r"),
+                Escaped::Command(EscapeCommand::ConnectionInfo)
+            ]
+        );
+        assert_eq!(
+            parser.feed(b"~~x~x"),
+            vec![input(b"~x~x")],
+            "a doubled escape character is sent once"
+        );
+        assert_eq!(parser.feed(b"\n~q"), vec![input(b"\n~q")]);
+        assert_eq!(parser.feed(&[4]), vec![input(&[4])], "ctrl-d is passed on");
+    }
+
+    #[test]
+    fn escape_sequences_can_be_split_across_reads() {
+        let mut parser = EscapeParser::new(Some(b'~'));
+        assert_eq!(parser.feed(b"echo\r~"), vec![input(b"echo\r")]);
+        assert_eq!(
+            parser.feed(b"?"),
+            vec![Escaped::Command(EscapeCommand::Help)]
+        );
+    }
+
+    #[test]
+    fn escape_character_is_configurable() {
+        assert_eq!(parse_escape_char(None), Ok(Some(b'~')));
+        assert_eq!(parse_escape_char(Some("%")), Ok(Some(b'%')));
+        assert_eq!(parse_escape_char(Some("none")), Ok(None));
+        assert!(parse_escape_char(Some("ab")).is_err());
+        assert!(parse_escape_char(Some(" ")).is_err());
+
+        let mut parser = EscapeParser::new(None);
+        assert_eq!(parser.feed(b"~."), vec![input(b"~.")]);
+        let mut parser = EscapeParser::new(Some(b'%'));
+        assert_eq!(parser.feed(b"~.%."), vec![input(b"~.%.")]);
+        assert_eq!(
+            parser.feed(b"\r%."),
+            vec![input(b"\r"), Escaped::Command(EscapeCommand::Detach)]
+        );
+    }
+}
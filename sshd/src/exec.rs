@@ -0,0 +1,311 @@
+use std::{fmt::Display, path::PathBuf};
+
+use zellij_utils::{
+    cli::{CliAction, CliArgs, Command, Sessions},
+    consts::ZELLIJ_SOCK_DIR,
+    input::{actions::Action, config::Config},
+    interprocess::local_socket::LocalSocketStream,
+    ipc::{
+        ClientToServerMsg, ClientType, IpcReceiverWithContext, IpcSenderWithContext, Prompt,
+        RemoteClient, ServerToClientMsg,
+    },
+};
+
+use crate::{session_util::get_sessions, zellij::parse_zellij_command};
+
+/// Not a zellij cli command, short for `action list-clients`
+const LIST_CLIENTS: &str = "list-clients";
+
+/// What an exec request printed and how it exited, sent back over its channel
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_status: u32,
+}
+
+impl ExecOutput {
+    fn success(lines: Vec<String>) -> Self {
+        ExecOutput {
+            stdout: to_text(lines),
+            ..Default::default()
+        }
+    }
+
+    pub fn failure(message: impl Display, exit_status: u32) -> Self {
+        ExecOutput {
+            stderr: format!("{}\n", message.to_string().trim_end()),
+            exit_status,
+            ..Default::default()
+        }
+    }
+}
+
+/// Runs the zellij command line of an exec request (`action new-pane`, `run -- htop`,
+/// `list-sessions`, `list-clients`) against `session_name`, the way `zellij` would run it
+/// from within the session
+pub(crate) fn run_exec_command(
+    command_line: &str,
+    args: &CliArgs,
+    session_name: Option<&str>,
+    client_type: ClientType,
+    remote: RemoteClient,
+) -> ExecOutput {
+    let command_line = match command_line.trim() {
+        LIST_CLIENTS => "action list-clients",
+        command_line => command_line,
+    };
+    let command = match parse_zellij_command(command_line) {
+        Ok(command) => command,
+        Err(e) => return ExecOutput::failure(e, 2),
+    };
+    let cli_action = match command {
+        Command::Sessions(Sessions::ListSessions { .. }) => return list_sessions(),
+        Command::Sessions(Sessions::Action(cli_action)) => cli_action,
+        Command::Sessions(Sessions::Run {
+            command,
+            direction,
+            cwd,
+            floating,
+            in_place,
+            name,
+            close_on_exit,
+            start_suspended,
+        }) => CliAction::NewPane {
+            command,
+            plugin: None,
+            direction,
+            cwd,
+            floating,
+            in_place,
+            name,
+            close_on_exit,
+            start_suspended,
+            configuration: None,
+            size: None,
+        },
+        _ => {
+            return ExecOutput::failure(
+                format!("{:?} can't be run over ssh", command_line.trim()),
+                1,
+            )
+        },
+    };
+    // listing who is connected doesn't change the session
+    let is_listing_clients = matches!(cli_action, CliAction::ListClients { .. });
+    if client_type == ClientType::Reader && !is_listing_clients {
+        return ExecOutput::failure("Read-only clients can't send actions to the session", 1);
+    }
+    // the session drops these for anyone but its owners, refused here to say why
+    let is_owner_only = matches!(
+        cli_action,
+        CliAction::KickClient { .. } | CliAction::SetClientRole { .. }
+    );
+    if is_owner_only {
+        return ExecOutput::failure(
+            "Only the owners of the session can do this, from within the session",
+            1,
+        );
+    }
+    // the session would have its first client follow, these commands have no view to move
+    let is_following = matches!(
+        cli_action,
+        CliAction::FollowClient { .. } | CliAction::UnfollowClient
+    );
+    if is_following {
+        return ExecOutput::failure("Following only works from within the session", 1);
+    }
+    let session_name = match session_name {
+        Some(session_name) => session_name,
+        None => return ExecOutput::failure("There is no active session!", 1),
+    };
+    let config = match Config::try_from(args) {
+        Ok(config) => config,
+        Err(e) => return ExecOutput::failure(format!("Failed to load the config: {}", e), 1),
+    };
+    let get_current_dir = || std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    match Action::actions_from_cli(cli_action, Box::new(get_current_dir), Some(config)) {
+        Ok(actions) => send_actions(session_name, actions, remote, client_type),
+        Err(e) => ExecOutput::failure(e, 2),
+    }
+}
+
+fn connect(session_name: &str) -> Result<IpcSenderWithContext<ClientToServerMsg>, ExecOutput> {
+    LocalSocketStream::connect(ZELLIJ_SOCK_DIR.join(session_name))
+        .map(IpcSenderWithContext::new)
+        .map_err(|e| ExecOutput::failure(format!("Session '{}' not found: {}", session_name, e), 1))
+}
+
+// mirrors `zellij_client::cli_client::start_cli_client`, collecting the output instead of
+// printing it
+fn send_actions(
+    session_name: &str,
+    actions: Vec<Action>,
+    remote: RemoteClient,
+    client_type: ClientType,
+) -> ExecOutput {
+    let mut sender = match connect(session_name) {
+        Ok(sender) => sender,
+        Err(output) => return output,
+    };
+    let mut receiver: IpcReceiverWithContext<ServerToClientMsg> = sender.get_receiver();
+    // the session would otherwise take the actions for its owner's, as they come through its
+    // socket
+    let _ = sender.send(ClientToServerMsg::Remote(remote, client_type));
+    for action in actions {
+        let _ = sender.send(ClientToServerMsg::Action(action, None, None));
+    }
+    let output = loop {
+        match receiver.recv() {
+            Some((ServerToClientMsg::UnblockInputThread, _)) => break ExecOutput::default(),
+            Some((ServerToClientMsg::Log(log_lines), _)) => break ExecOutput::success(log_lines),
+            Some((ServerToClientMsg::LogError(log_lines), _)) => {
+                break ExecOutput::failure(to_text(log_lines), 2)
+            },
+            Some(_) => {},
+            None => break ExecOutput::failure("Lost the connection to the session", 1),
+        }
+    };
+    let _ = sender.send(ClientToServerMsg::ClientExited);
+    output
+}
+
+/// Shows `message` to everyone in the session, through the plugins listening for notifications
+pub(crate) fn notify_session(session_name: &str, message: String) {
+    match connect(session_name) {
+        Ok(mut sender) => {
+            let _ = sender.send(ClientToServerMsg::Notify(message));
+            let _ = sender.send(ClientToServerMsg::ClientExited);
+        },
+        Err(output) => log::warn!("Failed to notify session: {}", output.stderr.trim_end()),
+    }
+}
+
+/// Asks `prompt` in the dialog of the first client attached to `session_name`, returns the
+/// choice it answered with, if any
+pub(crate) fn ask_session(session_name: &str, prompt: Prompt) -> Option<String> {
+    let mut sender = match connect(session_name) {
+        Ok(sender) => sender,
+        Err(output) => {
+            log::warn!("Failed to ask session: {}", output.stderr.trim_end());
+            return None;
+        },
+    };
+    let mut receiver: IpcReceiverWithContext<ServerToClientMsg> = sender.get_receiver();
+    let _ = sender.send(ClientToServerMsg::Prompt(prompt));
+    let answer = loop {
+        match receiver.recv() {
+            Some((ServerToClientMsg::PromptAnswer(answer), _)) => break answer,
+            Some(_) => {},
+            None => break None,
+        }
+    };
+    let _ = sender.send(ClientToServerMsg::ClientExited);
+    answer
+}
+
+fn list_sessions() -> ExecOutput {
+    match get_sessions() {
+        Ok(sessions) => ExecOutput::success(sessions.into_iter().map(|(name, _)| name).collect()),
+        Err(e) => ExecOutput::failure(format!("Error occurred: {:?}", e), 1),
+    }
+}
+
+fn to_text(lines: Vec<String>) -> String {
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_only_clients_cannot_send_actions() {
+        let output = run_exec_command(
+            "action new-pane",
+            &CliArgs::default(),
+            Some("shared"),
+            ClientType::Reader,
+            RemoteClient::default(),
+        );
+        assert_eq!(output.exit_status, 1);
+        assert!(output.stdout.is_empty());
+    }
+
+    #[test]
+    fn owner_only_actions_are_refused() {
+        let output = run_exec_command(
+            "action set-client-role 2 owner",
+            &CliArgs::default(),
+            Some("shared"),
+            ClientType::Writer,
+            RemoteClient::default(),
+        );
+        assert_eq!(output.exit_status, 1);
+        assert_eq!(
+            output.stderr,
+            "Only the owners of the session can do this, from within the session\n"
+        );
+    }
+
+    #[test]
+    fn following_is_refused() {
+        let output = run_exec_command(
+            "action follow-client 1",
+            &CliArgs::default(),
+            Some("shared"),
+            ClientType::Writer,
+            RemoteClient::default(),
+        );
+        assert_eq!(output.exit_status, 1);
+        assert_eq!(
+            output.stderr,
+            "Following only works from within the session\n"
+        );
+    }
+
+    #[test]
+    fn interactive_commands_are_refused() {
+        let output = run_exec_command(
+            "attach shared",
+            &CliArgs::default(),
+            Some("shared"),
+            ClientType::Writer,
+            RemoteClient::default(),
+        );
+        assert_eq!(output.exit_status, 1);
+        assert_eq!(output.stderr, "\"attach shared\" can't be run over ssh\n");
+    }
+
+    #[test]
+    fn invalid_command_lines_exit_with_usage_error() {
+        let output = run_exec_command(
+            "action no-such-action",
+            &CliArgs::default(),
+            Some("shared"),
+            ClientType::Writer,
+            RemoteClient::default(),
+        );
+        assert_eq!(output.exit_status, 2);
+    }
+
+    #[test]
+    fn broken_config_refuses_actions() {
+        let config_dir = zellij_utils::tempfile::tempdir().unwrap();
+        let config = config_dir.path().join("config.kdl");
+        std::fs::write(&config, "keybinds {").unwrap();
+        let args = CliArgs {
+            config: Some(config),
+            ..Default::default()
+        };
+        let output = run_exec_command(
+            "action new-pane",
+            &args,
+            Some("shared"),
+            ClientType::Writer,
+            RemoteClient::default(),
+        );
+        assert_eq!(output.exit_status, 1);
+        assert!(output.stderr.starts_with("Failed to load the config"));
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::host_keys::{create_ssh_data_dir, ssh_data_dir};
+
+const ACTIVE_FORWARDS_FILE: &str = "forwards";
+
+/// The `host:port` end of a direct-tcpip channel, e.g. `localhost:3000` or `[::1]:8080`
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ForwardTarget {
+    pub host: String,
+    pub port: u16,
+}
+
+impl FromStr for ForwardTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, port) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("{:?} is not a host:port pair", s))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("{:?} is missing a host", s));
+        }
+        let port = port
+            .parse()
+            .map_err(|_| format!("{:?} has an invalid port", s))?;
+        Ok(ForwardTarget {
+            host: host.to_lowercase(),
+            port,
+        })
+    }
+}
+
+impl Display for ForwardTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// The targets clients may forward ports to, nothing is allowed when it is empty
+#[derive(Clone, Debug, Default)]
+pub struct ForwardAllowlist(Vec<ForwardTarget>);
+
+impl ForwardAllowlist {
+    pub fn parse(entries: &[String]) -> Self {
+        let targets = entries
+            .iter()
+            .filter_map(|entry| match entry.parse() {
+                Ok(target) => Some(target),
+                Err(e) => {
+                    log::warn!("Ignoring allowed forward: {}", e);
+                    None
+                },
+            })
+            .collect();
+        ForwardAllowlist(targets)
+    }
+
+    pub fn allows(&self, host: &str, port: u32) -> bool {
+        self.0
+            .iter()
+            .any(|target| target.host.eq_ignore_ascii_case(host) && u32::from(target.port) == port)
+    }
+}
+
+/// The forwards currently open through this server, by the client that opened them.
+///
+/// The list is mirrored to `ssh/forwards` in the data dir so `zellij ssh forwards` can show
+/// the host what is being tunnelled.
+#[derive(Clone, Debug)]
+pub struct ActiveForwards {
+    path: PathBuf,
+    forwards: Arc<Mutex<BTreeMap<u64, (String, ForwardTarget)>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl ActiveForwards {
+    pub fn new(data_dir: Option<&Path>) -> io::Result<Self> {
+        let active_forwards = ActiveForwards {
+            path: create_ssh_data_dir(data_dir)?.join(ACTIVE_FORWARDS_FILE),
+            forwards: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        };
+        // forwards left over by a previous run are long gone
+        active_forwards.save(&BTreeMap::new());
+        Ok(active_forwards)
+    }
+
+    /// Records a forward of `client`, it is removed again when the returned guard is dropped
+    pub fn open(&self, client: &str, target: &ForwardTarget) -> ActiveForward {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut forwards = self.forwards.lock().unwrap();
+        forwards.insert(id, (client.to_string(), target.clone()));
+        self.save(&forwards);
+        ActiveForward {
+            id,
+            active_forwards: self.clone(),
+        }
+    }
+
+    fn close(&self, id: u64) {
+        let mut forwards = self.forwards.lock().unwrap();
+        forwards.remove(&id);
+        self.save(&forwards);
+    }
+
+    fn save(&self, forwards: &BTreeMap<u64, (String, ForwardTarget)>) {
+        let mut by_client: BTreeMap<&str, Vec<&ForwardTarget>> = BTreeMap::new();
+        for (client, target) in forwards.values() {
+            by_client.entry(client).or_default().push(target);
+        }
+        let mut listing = String::new();
+        for (client, targets) in by_client {
+            listing.push_str(&format!("{}\n", client));
+            for target in targets {
+                listing.push_str(&format!("  -> {}\n", target));
+            }
+        }
+        if let Err(e) =
+            fs::File::create(&self.path).and_then(|mut f| f.write_all(listing.as_bytes()))
+        {
+            log::warn!("Failed to write active forwards to {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// A forward that is open, see [`ActiveForwards::open`]
+pub struct ActiveForward {
+    id: u64,
+    active_forwards: ActiveForwards,
+}
+
+impl Drop for ActiveForward {
+    fn drop(&mut self) {
+        self.active_forwards.close(self.id);
+    }
+}
+
+/// Prints the forwards open through the ssh server, grouped by client
+pub fn print_active_forwards(data_dir: Option<&Path>) {
+    let path = ssh_data_dir(data_dir).join(ACTIVE_FORWARDS_FILE);
+    match fs::read_to_string(path) {
+        Ok(listing) if !listing.is_empty() => print!("{}", listing),
+        _ => println!("No active forwards."),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zellij_utils::tempfile::tempdir;
+
+    #[test]
+    fn allowlist_matches_host_and_port() {
+        let allowlist = ForwardAllowlist::parse(&[
+            "localhost:3000".to_string(),
+            "[::1]:8080".to_string(),
+            "no-port".to_string(),
+        ]);
+        assert!(allowlist.allows("localhost", 3000));
+        assert!(allowlist.allows("LOCALHOST", 3000));
+        assert!(allowlist.allows("::1", 8080));
+        assert!(!allowlist.allows("localhost", 3001));
+        assert!(!allowlist.allows("example.com", 3000));
+        assert!(!ForwardAllowlist::default().allows("localhost", 3000));
+    }
+
+    #[test]
+    fn forwards_are_listed_while_open() {
+        let data_dir = tempdir().unwrap();
+        let active_forwards = ActiveForwards::new(Some(data_dir.path())).unwrap();
+        let target: ForwardTarget = "localhost:3000".parse().unwrap();
+        let forward = active_forwards.open("alice from 10.0.0.5:52000", &target);
+        let listing = fs::read_to_string(&active_forwards.path).unwrap();
+        assert_eq!(listing, "alice from 10.0.0.5:52000\n  -> localhost:3000\n");
+        drop(forward);
+        assert!(fs::read_to_string(&active_forwards.path)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use std::{borrow::Cow, collections::HashMap, net::SocketAddr};
+
 use async_trait::async_trait;
 use russh::{
     server::{Msg, Session},
@@ -6,33 +8,134 @@ use russh::{
 use russh_keys::*;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot::*;
+use zellij_utils::cli::Command;
+
+use crate::{
+    accept_env::AcceptedEnv,
+    audit::AuthMethod,
+    authorized_keys::{AuthorizedKeys, KeyOptions},
+    forward::{ForwardAllowlist, ForwardTarget},
+    limits::Connection,
+    passcode::{Passcode, PasscodeStore},
+    share::strip_read_only_suffix,
+    zellij::parse_zellij_command,
+    PtyRequest, ServerChannel, ServerChannelId, ServerHandle,
+};
 
-use crate::{PtyRequest, ServerChannelId, ServerHandle};
+/// Who authenticated on a connection, passed on to the session once authentication succeeds.
+/// The session answers whether it takes the connection
+#[derive(Debug)]
+pub struct AuthenticatedUser {
+    pub user: String,
+    pub peer_addr: Option<SocketAddr>,
+    pub forced_command: Option<Command>,
+    pub auth_method: AuthMethod,
+    /// the key is `view-only` or the username carries the read-only suffix
+    pub read_only: bool,
+    /// the one-time passcode the user authenticated with, it picks the session and access
+    pub passcode: Option<Passcode>,
+}
 
 #[derive(Debug)]
 pub enum HandlerEvent {
-    Authenticated(ServerHandle, Sender<()>),
+    Authenticated(ServerHandle, AuthenticatedUser, Sender<bool>),
     PtyRequest(ServerChannelId, PtyRequest),
-    ShellRequest(ServerChannelId),
+    EnvRequest(String, String),
+    ShellRequest(ServerChannelId, ServerChannel),
+    ExecRequest(ServerChannelId, String),
+    SftpRequest(ServerChannelId, ServerChannel),
+    DirectTcpip(ServerChannel, ForwardTarget),
     Data(ServerChannelId, Vec<u8>),
     Signal(ServerChannelId, Sig),
     WindowChangeRequest(ServerChannelId, libc::winsize),
+    ChannelClose(ServerChannelId),
+    ChannelEof(ServerChannelId),
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum HandlerError {
     #[error("maybe channel disconnected")]
     ChannelSend,
+    #[error("the session refused the connection")]
+    Refused,
 }
 
 #[derive(Debug)]
 pub struct Handler {
     pub tx: UnboundedSender<HandlerEvent>,
+    peer_addr: Option<SocketAddr>,
+    authorized_keys: AuthorizedKeys,
+    allow_auth_none: bool,
+    forward_allowlist: ForwardAllowlist,
+    accepted_env: AcceptedEnv,
+    passcodes: PasscodeStore,
+    // counts the connection against the limits of the server until the handler is dropped
+    connection: Connection,
+    user: String,
+    // options of the authorized_keys entry the client authenticated with
+    key_options: KeyOptions,
+    auth_method: AuthMethod,
+    passcode: Option<Passcode>,
+    // session channels that haven't asked for a shell, a command or a subsystem yet
+    pending_channels: HashMap<ChannelId, ServerChannel>,
 }
 
 impl Handler {
-    pub fn new(tx: UnboundedSender<HandlerEvent>) -> Self {
-        Handler { tx }
+    pub fn new(
+        tx: UnboundedSender<HandlerEvent>,
+        peer_addr: Option<SocketAddr>,
+        authorized_keys: AuthorizedKeys,
+        allow_auth_none: bool,
+        forward_allowlist: ForwardAllowlist,
+        accepted_env: AcceptedEnv,
+        passcodes: PasscodeStore,
+        connection: Connection,
+    ) -> Self {
+        Handler {
+            tx,
+            peer_addr,
+            authorized_keys,
+            allow_auth_none,
+            forward_allowlist,
+            accepted_env,
+            passcodes,
+            connection,
+            user: String::new(),
+            key_options: KeyOptions::default(),
+            auth_method: AuthMethod::None,
+            passcode: None,
+            pending_channels: HashMap::new(),
+        }
+    }
+
+    fn reject() -> server::Auth {
+        server::Auth::Reject {
+            proceed_with_methods: None,
+        }
+    }
+
+    fn redeem_passcode(&mut self, user: &str, code: &str) -> server::Auth {
+        match self.passcodes.redeem(code) {
+            Some(passcode) => {
+                // the passcode itself is never logged, it could still be in flight elsewhere
+                log::info!(
+                    "Accepted passcode for session {:?} ({:?}) for {} from {:?}",
+                    passcode.session_name,
+                    passcode.role,
+                    user,
+                    self.peer_addr
+                );
+                self.user = user.to_string();
+                self.auth_method = AuthMethod::Passcode;
+                self.passcode = Some(passcode);
+                server::Auth::Accept
+            },
+            None => {
+                log::info!("Rejected passcode for {} from {:?}", user, self.peer_addr);
+                self.connection.auth_failed();
+                Self::reject()
+            },
+        }
     }
 
     fn send_event(&self, event: HandlerEvent) -> Result<(), HandlerError> {
@@ -45,50 +148,212 @@ impl server::Handler for Handler {
     type Error = anyhow::Error;
 
     async fn channel_open_session(
-        self,
-        _channel: Channel<Msg>,
-        session: Session,
-    ) -> Result<(Self, bool, Session), Self::Error> {
-        Ok((self, true, session))
+        &mut self,
+        channel: Channel<Msg>,
+        _session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        self.pending_channels
+            .insert(channel.id(), ServerChannel(channel));
+        Ok(true)
     }
 
-    async fn auth_succeeded(self, session: Session) -> Result<(Self, Session), Self::Error> {
+    async fn channel_open_direct_tcpip(
+        &mut self,
+        channel: Channel<Msg>,
+        host_to_connect: &str,
+        port_to_connect: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        if !self
+            .forward_allowlist
+            .allows(host_to_connect, port_to_connect)
+        {
+            log::info!(
+                "Refused forward to {}:{} from {:?}",
+                host_to_connect,
+                port_to_connect,
+                self.peer_addr
+            );
+            return Ok(false);
+        }
+        // keys with a forced command are only meant to run that command, like with sshd
+        if self.key_options.command.is_some()
+            || !self
+                .key_options
+                .allows_forward(host_to_connect, port_to_connect)
+        {
+            log::info!(
+                "Refused forward to {}:{} from {:?}: not allowed for this key",
+                host_to_connect,
+                port_to_connect,
+                self.peer_addr
+            );
+            return Ok(false);
+        }
+        self.send_event(HandlerEvent::DirectTcpip(
+            ServerChannel(channel),
+            ForwardTarget {
+                host: host_to_connect.to_string(),
+                port: port_to_connect as u16,
+            },
+        ))?;
+        Ok(true)
+    }
+
+    async fn channel_close(
+        &mut self,
+        channel: ChannelId,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.pending_channels.remove(&channel);
+        self.send_event(HandlerEvent::ChannelClose(ServerChannelId(channel)))?;
+        Ok(())
+    }
+
+    async fn channel_eof(
+        &mut self,
+        channel: ChannelId,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.send_event(HandlerEvent::ChannelEof(ServerChannelId(channel)))?;
+        Ok(())
+    }
+
+    async fn auth_succeeded(&mut self, session: &mut Session) -> Result<(), Self::Error> {
         let handle = session.handle();
-        let (tx, rx) = channel::<()>();
-        self.send_event(HandlerEvent::Authenticated(ServerHandle(handle), tx))?;
-        let _ = rx.await;
-        Ok((self, session))
+        // validated when the key was accepted
+        let forced_command = self
+            .key_options
+            .command
+            .as_deref()
+            .and_then(|command| parse_zellij_command(command).ok());
+        let (user, read_only_suffix) = strip_read_only_suffix(&self.user);
+        self.connection.auth_succeeded();
+        let (tx, rx) = channel::<bool>();
+        self.send_event(HandlerEvent::Authenticated(
+            ServerHandle(handle),
+            AuthenticatedUser {
+                user: user.to_string(),
+                peer_addr: self.peer_addr,
+                forced_command,
+                auth_method: self.auth_method.clone(),
+                read_only: read_only_suffix || self.key_options.view_only,
+                passcode: self.passcode.clone(),
+            },
+            tx,
+        ))?;
+        if !rx.await.unwrap_or(false) {
+            return Err(HandlerError::Refused.into());
+        }
+        Ok(())
     }
 
-    async fn auth_none(self, _user: &str) -> Result<(Self, server::Auth), Self::Error> {
-        Ok((self, server::Auth::Accept))
+    async fn auth_none(&mut self, user: &str) -> Result<server::Auth, Self::Error> {
+        if self.allow_auth_none {
+            self.user = user.to_string();
+            log::warn!(
+                "Accepted unauthenticated connection for {} from {:?}",
+                user,
+                self.peer_addr
+            );
+            Ok(server::Auth::Accept)
+        } else {
+            Ok(Self::reject())
+        }
+    }
+
+    async fn auth_password(
+        &mut self,
+        user: &str,
+        password: &str,
+    ) -> Result<server::Auth, Self::Error> {
+        Ok(self.redeem_passcode(user, password))
+    }
+
+    async fn auth_keyboard_interactive(
+        &mut self,
+        user: &str,
+        _submethods: &str,
+        response: Option<server::Response<'async_trait>>,
+    ) -> Result<server::Auth, Self::Error> {
+        match response {
+            None => Ok(server::Auth::Partial {
+                name: Cow::Borrowed("zellij"),
+                instructions: Cow::Borrowed("Enter the passcode the host gave you."),
+                prompts: Cow::Owned(vec![(Cow::Borrowed("Passcode: "), false)]),
+            }),
+            Some(mut response) => match response.next() {
+                Some(code) => {
+                    let code = String::from_utf8_lossy(code).to_string();
+                    Ok(self.redeem_passcode(user, &code))
+                },
+                None => Ok(Self::reject()),
+            },
+        }
     }
 
     async fn auth_publickey(
-        self,
-        _: &str,
-        _: &key::PublicKey,
-    ) -> Result<(Self, server::Auth), Self::Error> {
-        Ok((self, server::Auth::Accept))
+        &mut self,
+        user: &str,
+        public_key: &key::PublicKey,
+    ) -> Result<server::Auth, Self::Error> {
+        let peer_ip = self.peer_addr.map(|addr| addr.ip());
+        match self.authorized_keys.find(public_key, peer_ip) {
+            Some(authorized_key) => {
+                if let Some(command) = &authorized_key.options.command {
+                    if let Err(e) = parse_zellij_command(command) {
+                        log::warn!(
+                            "Rejected {} key SHA256:{} for {}: invalid command option: {}",
+                            public_key.name(),
+                            public_key.fingerprint(),
+                            user,
+                            e
+                        );
+                        return Ok(Self::reject());
+                    }
+                }
+                log::info!(
+                    "Accepted {} key SHA256:{} ({}) for {} from {:?}",
+                    public_key.name(),
+                    public_key.fingerprint(),
+                    authorized_key.comment,
+                    user,
+                    self.peer_addr
+                );
+                self.user = user.to_string();
+                self.key_options = authorized_key.options;
+                self.auth_method =
+                    AuthMethod::PublicKey(format!("SHA256:{}", public_key.fingerprint()));
+                Ok(server::Auth::Accept)
+            },
+            None => {
+                log::info!(
+                    "Rejected {} key SHA256:{} for {} from {:?}",
+                    public_key.name(),
+                    public_key.fingerprint(),
+                    user,
+                    self.peer_addr
+                );
+                self.connection.auth_failed();
+                Ok(Self::reject())
+            },
+        }
     }
 
     async fn data(
-        mut self,
+        &mut self,
         channel: ChannelId,
         data: &[u8],
-        session: Session,
-    ) -> Result<(Self, Session), Self::Error> {
-        let mut data = data.to_vec();
-        if data[0] == 4 {
-            data = vec![17]
-        }
-
-        self.send_event(HandlerEvent::Data(ServerChannelId(channel), data))?;
-        Ok((self, session))
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.send_event(HandlerEvent::Data(ServerChannelId(channel), data.to_vec()))?;
+        Ok(())
     }
 
     async fn pty_request(
-        self,
+        &mut self,
         channel: ChannelId,
         term: &str,
         col_width: u32,
@@ -96,8 +361,12 @@ impl server::Handler for Handler {
         pix_width: u32,
         pix_height: u32,
         modes: &[(Pty, u32)],
-        mut session: Session,
-    ) -> Result<(Self, Session), Self::Error> {
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        if self.key_options.no_pty {
+            session.channel_failure(channel);
+            return Ok(());
+        }
         let term = term.to_string();
         let modes = modes.to_vec();
         self.send_event(HandlerEvent::PtyRequest(
@@ -113,40 +382,111 @@ impl server::Handler for Handler {
         ))?;
 
         session.channel_success(channel);
-        Ok((self, session))
+        Ok(())
+    }
+
+    async fn env_request(
+        &mut self,
+        _channel: ChannelId,
+        variable_name: &str,
+        variable_value: &str,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        if self.accepted_env.accepts(variable_name, variable_value) {
+            self.send_event(HandlerEvent::EnvRequest(
+                variable_name.to_string(),
+                variable_value.to_string(),
+            ))?;
+        } else {
+            log::debug!("Ignored env request for {:?}", variable_name);
+        }
+        Ok(())
     }
 
     async fn shell_request(
-        self,
+        &mut self,
+        channel: ChannelId,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        // the input of shells comes through the handler callbacks, the channel carries their
+        // output
+        match self.pending_channels.remove(&channel) {
+            Some(server_channel) => {
+                self.send_event(HandlerEvent::ShellRequest(
+                    ServerChannelId(channel),
+                    server_channel,
+                ))?;
+                session.channel_success(channel);
+            },
+            None => {
+                log::info!("Refused shell on channel {}, it is already in use", channel);
+                session.channel_failure(channel);
+            },
+        }
+
+        Ok(())
+    }
+
+    async fn exec_request(
+        &mut self,
         channel: ChannelId,
-        mut session: Session,
-    ) -> Result<(Self, Session), Self::Error> {
-        self.send_event(HandlerEvent::ShellRequest(ServerChannelId(channel)))?;
+        data: &[u8],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.pending_channels.remove(&channel);
+        let command_line = String::from_utf8_lossy(data).to_string();
+        self.send_event(HandlerEvent::ExecRequest(
+            ServerChannelId(channel),
+            command_line,
+        ))?;
 
         session.channel_success(channel);
 
-        Ok((self, session))
+        Ok(())
+    }
+
+    async fn subsystem_request(
+        &mut self,
+        channel: ChannelId,
+        name: &str,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        match (name, self.pending_channels.remove(&channel)) {
+            // like sshd, the command of the key replaces any subsystem
+            ("sftp", Some(server_channel)) if self.key_options.command.is_none() => {
+                self.send_event(HandlerEvent::SftpRequest(
+                    ServerChannelId(channel),
+                    server_channel,
+                ))?;
+                session.channel_success(channel);
+            },
+            _ => {
+                log::info!("Refused {:?} subsystem on channel {}", name, channel);
+                session.channel_failure(channel);
+            },
+        }
+        Ok(())
     }
 
     async fn signal(
-        self,
+        &mut self,
         channel: ChannelId,
         signal: Sig,
-        session: Session,
-    ) -> Result<(Self, Session), Self::Error> {
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
         self.send_event(HandlerEvent::Signal(ServerChannelId(channel), signal))?;
-        Ok((self, session))
+        Ok(())
     }
 
     async fn window_change_request(
-        self,
+        &mut self,
         channel: ChannelId,
         col_width: u32,
         row_height: u32,
         pix_width: u32,
         pix_height: u32,
-        session: Session,
-    ) -> Result<(Self, Session), Self::Error> {
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
         self.send_event(HandlerEvent::WindowChangeRequest(
             ServerChannelId(channel),
             libc::winsize {
@@ -156,7 +496,7 @@ impl server::Handler for Handler {
                 ws_ypixel: pix_height as u16,
             },
         ))?;
-        Ok((self, session))
+        Ok(())
     }
 }
 
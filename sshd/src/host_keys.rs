@@ -0,0 +1,247 @@
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use rand::RngCore;
+use russh_keys::{
+    ec,
+    key::{KeyPair, SignatureHash},
+};
+use zellij_utils::{home::get_default_data_dir, shared::set_permissions};
+
+const ED25519_HOST_KEY: &str = "ssh_host_ed25519_key";
+const ECDSA_HOST_KEY: &str = "ssh_host_ecdsa_key";
+const ECDSA_HOST_KEY_ALGORITHM: &[u8] = b"ecdsa-sha2-nistp256";
+const RSA_HOST_KEY: &str = "ssh_host_rsa_key";
+const RSA_HOST_KEY_BITS: usize = 3072;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HostKeyError {
+    #[error("failed to access host key {0:?}: {1}")]
+    Io(PathBuf, #[source] io::Error),
+    #[error("failed to decode host key {0:?}: {1}")]
+    Decode(PathBuf, #[source] russh_keys::Error),
+    #[error("failed to encode host key {0:?}: {1}")]
+    Encode(PathBuf, #[source] russh_keys::Error),
+    #[error(
+        "don't know how to generate host key {0:?}, only ed25519, ecdsa and rsa keys can be \
+         generated"
+    )]
+    UnknownKind(PathBuf),
+    #[error("failed to generate {0} host key")]
+    Generate(&'static str),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HostKeyKind {
+    Ed25519,
+    Ecdsa,
+    Rsa,
+}
+
+impl HostKeyKind {
+    // guess the key type from the file name, the way ssh-keygen names them
+    fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+        if file_name.contains("rsa") {
+            Some(HostKeyKind::Rsa)
+        } else if file_name.contains("ed25519") {
+            Some(HostKeyKind::Ed25519)
+        } else if file_name.contains("ecdsa") {
+            Some(HostKeyKind::Ecdsa)
+        } else {
+            None
+        }
+    }
+
+    fn generate(&self) -> Result<KeyPair, HostKeyError> {
+        match self {
+            HostKeyKind::Ed25519 => {
+                KeyPair::generate_ed25519().ok_or(HostKeyError::Generate("ed25519"))
+            },
+            HostKeyKind::Ecdsa => generate_ecdsa(),
+            HostKeyKind::Rsa => KeyPair::generate_rsa(RSA_HOST_KEY_BITS, SignatureHash::SHA2_256)
+                .ok_or(HostKeyError::Generate("rsa")),
+        }
+    }
+}
+
+// russh-keys has no generator for ecdsa keys, a nistp256 key is a random scalar below the
+// order of the curve, which almost every random 32 bytes are
+fn generate_ecdsa() -> Result<KeyPair, HostKeyError> {
+    let mut scalar = [0u8; 32];
+    for _ in 0..16 {
+        rand::thread_rng().fill_bytes(&mut scalar);
+        if let Ok(key) = ec::PrivateKey::new_from_secret_scalar(ECDSA_HOST_KEY_ALGORITHM, &scalar) {
+            return Ok(KeyPair::EC { key });
+        }
+    }
+    Err(HostKeyError::Generate("ecdsa"))
+}
+
+/// The directory holding the ssh server state, `<data dir>/ssh`
+pub fn ssh_data_dir(data_dir: Option<&Path>) -> PathBuf {
+    data_dir
+        .map(|d| d.to_path_buf())
+        .unwrap_or_else(get_default_data_dir)
+        .join("ssh")
+}
+
+/// Creates the ssh data dir if needed, making sure only the current user can read it
+pub fn create_ssh_data_dir(data_dir: Option<&Path>) -> io::Result<PathBuf> {
+    let dir = ssh_data_dir(data_dir);
+    fs::create_dir_all(&dir)?;
+    set_permissions(&dir, 0o700)?;
+    Ok(dir)
+}
+
+fn default_host_key_paths(data_dir: Option<&Path>) -> Result<Vec<PathBuf>, HostKeyError> {
+    let dir =
+        create_ssh_data_dir(data_dir).map_err(|e| HostKeyError::Io(ssh_data_dir(data_dir), e))?;
+    Ok(vec![
+        dir.join(ED25519_HOST_KEY),
+        dir.join(ECDSA_HOST_KEY),
+        dir.join(RSA_HOST_KEY),
+    ])
+}
+
+/// Loads the configured host keys, falling back to the default ed25519, ecdsa and rsa keys in
+/// the ssh data dir. Missing keys are generated and stored with 0600 permissions so the host
+/// identity survives restarts.
+pub fn load_host_keys(
+    paths: &[PathBuf],
+    data_dir: Option<&Path>,
+) -> Result<Vec<KeyPair>, HostKeyError> {
+    let paths = if paths.is_empty() {
+        default_host_key_paths(data_dir)?
+    } else {
+        paths.to_vec()
+    };
+    paths.iter().map(|path| load_or_generate(path)).collect()
+}
+
+fn load_or_generate(path: &Path) -> Result<KeyPair, HostKeyError> {
+    if path.exists() {
+        warn_if_accessible_by_others(path);
+        return russh_keys::load_secret_key(path, None)
+            .map_err(|e| HostKeyError::Decode(path.to_path_buf(), e));
+    }
+
+    let kind = HostKeyKind::from_path(path)
+        .ok_or_else(|| HostKeyError::UnknownKind(path.to_path_buf()))?;
+    let key = kind.generate()?;
+    write_host_key(path, &key)?;
+    log::info!("Generated new {:?} host key at {:?}", kind, path);
+    Ok(key)
+}
+
+// the key is written next to its final path and moved there once complete, so that an
+// interrupted write doesn't leave a truncated key behind that fails to load on the next start
+fn write_host_key(path: &Path, key: &KeyPair) -> Result<(), HostKeyError> {
+    let io_err = |e| HostKeyError::Io(path.to_path_buf(), e);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_err)?;
+    }
+    let mut temp_file_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_file_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_file_name);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&temp_path)
+        .map_err(io_err)?;
+    // a leftover of an earlier attempt keeps the permissions it was created with
+    set_permissions(&temp_path, 0o600).map_err(io_err)?;
+    russh_keys::encode_pkcs8_pem(key, &mut file)
+        .map_err(|e| HostKeyError::Encode(path.to_path_buf(), e))?;
+    file.sync_all().map_err(io_err)?;
+    fs::rename(&temp_path, path).map_err(io_err)
+}
+
+fn warn_if_accessible_by_others(path: &Path) {
+    if let Ok(metadata) = fs::metadata(path) {
+        if metadata.permissions().mode() & 0o077 != 0 {
+            log::warn!(
+                "Host key {:?} is accessible by other users, consider running `chmod 600` on it",
+                path
+            );
+        }
+    }
+}
+
+/// `SHA256:<base64>` fingerprint of the public half of `key`, as printed by `ssh-keygen -l`
+pub fn fingerprint(key: &KeyPair) -> Option<String> {
+    key.clone_public_key()
+        .ok()
+        .map(|public_key| format!("SHA256:{}", public_key.fingerprint()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zellij_utils::tempfile::tempdir;
+
+    #[test]
+    fn host_keys_are_generated_once_and_reused() {
+        let data_dir = tempdir().unwrap();
+        let first = load_host_keys(&[], Some(data_dir.path())).unwrap();
+        let second = load_host_keys(&[], Some(data_dir.path())).unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(
+            first.iter().map(fingerprint).collect::<Vec<_>>(),
+            second.iter().map(fingerprint).collect::<Vec<_>>(),
+        );
+
+        let key_file = ssh_data_dir(Some(data_dir.path())).join(ED25519_HOST_KEY);
+        let mode = fs::metadata(key_file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn host_keys_are_written_in_place_at_once() {
+        let data_dir = tempdir().unwrap();
+        let path = data_dir.path().join(ED25519_HOST_KEY);
+        let key = HostKeyKind::Ed25519.generate().unwrap();
+        write_host_key(&path, &key).unwrap();
+
+        assert!(!data_dir
+            .path()
+            .join(format!("{}.tmp", ED25519_HOST_KEY))
+            .exists());
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(
+            fingerprint(&russh_keys::load_secret_key(&path, None).unwrap()),
+            fingerprint(&key)
+        );
+    }
+
+    #[test]
+    fn ecdsa_host_keys_are_nistp256() {
+        let data_dir = tempdir().unwrap();
+        let path = data_dir.path().join(ECDSA_HOST_KEY);
+        let key = load_host_keys(std::slice::from_ref(&path), None)
+            .unwrap()
+            .remove(0);
+        assert_eq!(key.name(), "ecdsa-sha2-nistp256");
+        assert_eq!(
+            fingerprint(&russh_keys::load_secret_key(&path, None).unwrap()),
+            fingerprint(&key)
+        );
+    }
+
+    #[test]
+    fn unknown_key_kind_is_not_generated() {
+        let data_dir = tempdir().unwrap();
+        let path = data_dir.path().join("ssh_host_mystery_key");
+        assert!(matches!(
+            load_host_keys(&[path], None),
+            Err(HostKeyError::UnknownKind(_))
+        ));
+    }
+}
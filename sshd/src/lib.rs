@@ -1,18 +1,40 @@
 use std::fmt::{Debug, Display, Formatter};
 
-use russh::{server::Handle, ChannelId, Pty};
-use tokio::sync::mpsc::UnboundedSender;
+use russh::{
+    server::{Handle, Msg},
+    Channel, ChannelId, Pty, Sig,
+};
 
+mod accept_env;
+mod approval;
+pub mod audit;
+mod authorized_keys;
+mod clients;
+mod escape;
+mod exec;
+pub mod forward;
 mod handler;
+pub mod host_keys;
+mod limits;
+mod listener;
+mod lobby;
+mod output;
+pub mod passcode;
+pub mod relay;
 pub mod server;
 mod session;
 mod session_util;
+mod sftp;
+pub mod share;
 mod ssh_input_output;
 pub mod zellij;
 
-pub enum ZellijClientData {
-    Data(String),
-    Exit,
+/// Events of the ssh channel handled by the signal thread of its zellij client, the
+/// equivalent of the signals a local client gets from its terminal
+#[derive(Clone, Debug)]
+pub enum ClientSignal {
+    Signal(Sig),
+    WindowChange(libc::winsize),
 }
 
 #[derive(Clone)]
@@ -24,22 +46,26 @@ impl Debug for ServerHandle {
     }
 }
 
+/// A session channel kept by the handler until we know whether a subsystem will take it over
+pub struct ServerChannel(Channel<Msg>);
+
+impl Debug for ServerChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Channel({})", self.0.id())
+    }
+}
+
+/// The stdout of a zellij client, its bytes go to the ssh channel untouched
 #[derive(Clone)]
 pub struct ServerOutput {
-    sender: UnboundedSender<ZellijClientData>,
+    output: output::OutputWriter,
 }
 
 impl std::io::Write for ServerOutput {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let _ = self.sender.send(ZellijClientData::Data(
-            String::from_utf8_lossy(buf).to_string(),
-        ));
-
+        self.output.write(buf);
         Ok(buf.len())
     }
-    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
-        self.write(buf).map(|_| ())
-    }
 
     fn flush(&mut self) -> std::io::Result<()> {
         Ok(())
@@ -0,0 +1,365 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use zellij_utils::ssh::Ssh;
+
+const DEFAULT_MAX_CONNECTIONS: usize = 100;
+const DEFAULT_MAX_CONNECTIONS_PER_IP: usize = 10;
+const DEFAULT_MAX_AUTH_FAILURES: u32 = 5;
+const DEFAULT_BAN_MINUTES: u64 = 10;
+
+/// An address range like `10.0.0.0/8` or `2001:db8::/32`, a bare address matches only itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("{:?} is not an ip address or range", s))?;
+        let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse()
+                .ok()
+                .filter(|prefix_len| *prefix_len <= max_prefix_len)
+                .ok_or_else(|| format!("{:?} has an invalid prefix length", s))?,
+            None => max_prefix_len,
+        };
+        Ok(Cidr { addr, prefix_len })
+    }
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(
+                u32::from(net).into(),
+                u32::from(ip).into(),
+                32,
+                self.prefix_len,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(net), u128::from(ip), 128, self.prefix_len)
+            },
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, bits: u8, prefix_len: u8) -> bool {
+    let shift = u32::from(bits - prefix_len);
+    shift == u32::from(bits) || (net >> shift) == (ip >> shift)
+}
+
+// ipv4 clients of a dual stack listener show up as ::ffff:a.b.c.d
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// Why a connection was turned away, logged with its peer address
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Rejection {
+    #[error("the address is not allowed to connect")]
+    NotAllowed,
+    #[error("the address is banned for {0} more seconds after failing to authenticate")]
+    Banned(u64),
+    #[error("the server has reached its limit of {0} connections")]
+    TooManyConnections(usize),
+    #[error("the address has reached its limit of {0} connections")]
+    TooManyConnectionsFromIp(usize),
+    #[error("session {0:?} has reached its limit of {1} clients")]
+    SessionFull(String, usize),
+}
+
+#[derive(Debug, Default)]
+struct LimitsState {
+    connections: usize,
+    connections_per_ip: HashMap<IpAddr, usize>,
+    clients_per_session: HashMap<String, usize>,
+    auth_failures: HashMap<IpAddr, u32>,
+    bans: HashMap<IpAddr, Instant>,
+}
+
+/// Caps on the connections the server holds, and the bans of addresses that keep failing to
+/// authenticate. Shared by all connections, each one holds a [`Connection`] while it is open.
+#[derive(Clone, Debug)]
+pub struct ConnectionLimits {
+    allow_from: Vec<Cidr>,
+    deny_from: Vec<Cidr>,
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    max_clients_per_session: Option<usize>,
+    max_auth_failures: u32,
+    ban_duration: Duration,
+    state: Arc<Mutex<LimitsState>>,
+}
+
+impl ConnectionLimits {
+    pub fn new(ssh_opts: &Ssh) -> Self {
+        ConnectionLimits {
+            allow_from: parse_cidrs(&ssh_opts.allow_from),
+            deny_from: parse_cidrs(&ssh_opts.deny_from),
+            max_connections: ssh_opts.max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS),
+            max_connections_per_ip: ssh_opts
+                .max_connections_per_ip
+                .unwrap_or(DEFAULT_MAX_CONNECTIONS_PER_IP),
+            max_clients_per_session: ssh_opts.max_clients_per_session,
+            max_auth_failures: ssh_opts
+                .max_auth_failures
+                .unwrap_or(DEFAULT_MAX_AUTH_FAILURES),
+            ban_duration: Duration::from_secs(
+                ssh_opts.ban_minutes.unwrap_or(DEFAULT_BAN_MINUTES) * 60,
+            ),
+            state: Arc::new(Mutex::new(LimitsState::default())),
+        }
+    }
+
+    /// Lets a new connection from `ip` in if no list or limit stands in its way. Connections
+    /// without an address, over a Unix socket or stdio, are local and only count against the
+    /// overall limit
+    pub fn admit(&self, ip: Option<IpAddr>) -> Result<Connection, Rejection> {
+        let ip = ip.map(canonical);
+        let mut state = self.state.lock().unwrap();
+        if state.connections >= self.max_connections {
+            return Err(Rejection::TooManyConnections(self.max_connections));
+        }
+        if let Some(ip) = ip {
+            if self.deny_from.iter().any(|cidr| cidr.contains(ip))
+                || (!self.allow_from.is_empty()
+                    && !self.allow_from.iter().any(|cidr| cidr.contains(ip)))
+            {
+                return Err(Rejection::NotAllowed);
+            }
+            let now = Instant::now();
+            state.bans.retain(|_, until| *until > now);
+            if let Some(until) = state.bans.get(&ip) {
+                return Err(Rejection::Banned((*until - now).as_secs() + 1));
+            }
+            let connections_from_ip = state.connections_per_ip.entry(ip).or_default();
+            if *connections_from_ip >= self.max_connections_per_ip {
+                return Err(Rejection::TooManyConnectionsFromIp(
+                    self.max_connections_per_ip,
+                ));
+            }
+            *connections_from_ip += 1;
+        }
+        state.connections += 1;
+        Ok(Connection {
+            ip,
+            limits: self.clone(),
+            failed_auth: false,
+            authenticated: false,
+        })
+    }
+
+    /// Takes a client slot of `session_name`, it is given back when the returned guard drops
+    pub fn join_session(&self, session_name: &str) -> Result<SessionSlot, Rejection> {
+        let mut state = self.state.lock().unwrap();
+        let clients = state
+            .clients_per_session
+            .entry(session_name.to_string())
+            .or_default();
+        if let Some(max_clients) = self.max_clients_per_session {
+            if *clients >= max_clients {
+                return Err(Rejection::SessionFull(
+                    session_name.to_string(),
+                    max_clients,
+                ));
+            }
+        }
+        *clients += 1;
+        Ok(SessionSlot {
+            session_name: session_name.to_string(),
+            limits: self.clone(),
+        })
+    }
+
+    fn close(&self, connection: &Connection) {
+        let mut state = self.state.lock().unwrap();
+        state.connections = state.connections.saturating_sub(1);
+        let ip = match connection.ip {
+            Some(ip) => ip,
+            None => return,
+        };
+        if let Some(connections_from_ip) = state.connections_per_ip.get_mut(&ip) {
+            *connections_from_ip = connections_from_ip.saturating_sub(1);
+            if *connections_from_ip == 0 {
+                state.connections_per_ip.remove(&ip);
+            }
+        }
+        if connection.authenticated {
+            state.auth_failures.remove(&ip);
+        } else if connection.failed_auth {
+            let failures = state.auth_failures.entry(ip).or_default();
+            *failures += 1;
+            if *failures >= self.max_auth_failures {
+                log::warn!(
+                    "Banning {} for {} seconds after {} failed logins",
+                    ip,
+                    self.ban_duration.as_secs(),
+                    failures
+                );
+                state.auth_failures.remove(&ip);
+                state.bans.insert(ip, Instant::now() + self.ban_duration);
+            }
+        }
+    }
+
+    fn leave_session(&self, session_name: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(clients) = state.clients_per_session.get_mut(session_name) {
+            *clients = clients.saturating_sub(1);
+            if *clients == 0 {
+                state.clients_per_session.remove(session_name);
+            }
+        }
+    }
+}
+
+/// An admitted connection, counted against the limits until it is dropped. A connection that
+/// closes after failed login attempts without ever authenticating counts towards a ban.
+#[derive(Debug)]
+pub struct Connection {
+    ip: Option<IpAddr>,
+    limits: ConnectionLimits,
+    failed_auth: bool,
+    authenticated: bool,
+}
+
+impl Connection {
+    pub fn auth_failed(&mut self) {
+        self.failed_auth = true;
+    }
+
+    pub fn auth_succeeded(&mut self) {
+        self.authenticated = true;
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.limits.close(self);
+    }
+}
+
+/// A client slot of a session, see [`ConnectionLimits::join_session`]
+#[derive(Debug)]
+pub struct SessionSlot {
+    session_name: String,
+    limits: ConnectionLimits,
+}
+
+impl Drop for SessionSlot {
+    fn drop(&mut self) {
+        self.limits.leave_session(&self.session_name);
+    }
+}
+
+fn parse_cidrs(entries: &[String]) -> Vec<Cidr> {
+    entries
+        .iter()
+        .filter_map(|entry| match entry.parse() {
+            Ok(cidr) => Some(cidr),
+            Err(e) => {
+                log::warn!("Ignoring address range: {}", e);
+                None
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn cidrs_match_their_range() {
+        let private: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert!(private.contains(ip("10.1.2.3")));
+        assert!(private.contains(ip("::ffff:10.1.2.3")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        let single: Cidr = "2001:db8::1".parse().unwrap();
+        assert!(single.contains(ip("2001:db8::1")));
+        assert!(!single.contains(ip("2001:db8::2")));
+        let everything: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains(ip("192.168.1.1")));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("example.com".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn connections_are_capped_per_ip_and_session() {
+        let limits = ConnectionLimits::new(&Ssh {
+            max_connections_per_ip: Some(2),
+            max_clients_per_session: Some(1),
+            deny_from: vec!["192.168.0.0/16".to_string()],
+            ..Default::default()
+        });
+        let first = limits.admit(Some(ip("10.0.0.1"))).unwrap();
+        let _second = limits.admit(Some(ip("10.0.0.1"))).unwrap();
+        assert_eq!(
+            limits.admit(Some(ip("10.0.0.1"))).unwrap_err(),
+            Rejection::TooManyConnectionsFromIp(2)
+        );
+        assert!(limits.admit(Some(ip("10.0.0.2"))).is_ok());
+        drop(first);
+        assert!(limits.admit(Some(ip("10.0.0.1"))).is_ok());
+        assert_eq!(
+            limits.admit(Some(ip("192.168.1.1"))).unwrap_err(),
+            Rejection::NotAllowed
+        );
+        assert!(
+            limits.admit(None).is_ok(),
+            "local connections have no address"
+        );
+
+        let slot = limits.join_session("pairing").unwrap();
+        assert!(limits.join_session("pairing").is_err());
+        assert!(limits.join_session("other").is_ok());
+        drop(slot);
+        assert!(limits.join_session("pairing").is_ok());
+    }
+
+    #[test]
+    fn repeated_failed_logins_ban_the_address() {
+        let limits = ConnectionLimits::new(&Ssh {
+            max_auth_failures: Some(2),
+            ..Default::default()
+        });
+        for _ in 0..2 {
+            let mut connection = limits.admit(Some(ip("10.0.0.1"))).unwrap();
+            connection.auth_failed();
+        }
+        assert!(matches!(
+            limits.admit(Some(ip("10.0.0.1"))),
+            Err(Rejection::Banned(_))
+        ));
+        let mut connection = limits.admit(Some(ip("10.0.0.2"))).unwrap();
+        connection.auth_failed();
+        connection.auth_succeeded();
+        drop(connection);
+        assert!(limits.admit(Some(ip("10.0.0.2"))).is_ok());
+    }
+}
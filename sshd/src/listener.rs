@@ -0,0 +1,209 @@
+use std::{
+    fs, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf, Stdin, Stdout},
+    net::{TcpListener, UnixListener},
+};
+use zellij_utils::shared::set_permissions;
+
+/// Where the server accepts connections: an ip address, optionally with a port, or the path of
+/// a Unix socket
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl ListenAddress {
+    /// Parses `0.0.0.0`, `[::1]:2222` or `/run/zmate.sock`, addresses without a port listen on
+    /// `default_port`
+    pub fn parse(address: &str, default_port: u16) -> Result<Self, String> {
+        if address.contains('/') {
+            return Ok(ListenAddress::Unix(PathBuf::from(address)));
+        }
+        if let Ok(socket_addr) = address.parse::<SocketAddr>() {
+            return Ok(ListenAddress::Tcp(socket_addr));
+        }
+        address
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .map(|ip| ListenAddress::Tcp(SocketAddr::new(ip, default_port)))
+            .map_err(|_| {
+                format!(
+                    "{:?} is neither an ip address nor the path of a socket",
+                    address
+                )
+            })
+    }
+}
+
+/// Parses the configured addresses, see [`ListenAddress::parse`]
+pub fn parse_addresses(addresses: &[String], default_port: u16) -> io::Result<Vec<ListenAddress>> {
+    addresses
+        .iter()
+        .map(|address| ListenAddress::parse(address, default_port))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// Binds every address of `addresses`, or the unspecified IPv6 address when there are none,
+/// which also takes IPv4 connections where the system allows it
+pub async fn bind_all(addresses: &[ListenAddress], port: u16) -> io::Result<Vec<Listener>> {
+    if addresses.is_empty() {
+        let listener = match TcpListener::bind((IpAddr::V6(Ipv6Addr::UNSPECIFIED), port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::info!("Listening on IPv4 only, no IPv6: {}", e);
+                TcpListener::bind((IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)).await?
+            },
+        };
+        return Ok(vec![Listener::Tcp(listener)]);
+    }
+    // IPv6 first, so an IPv4 address that is already served by a dual stack IPv6 listener can
+    // be told apart from one that is taken
+    let mut addresses = addresses.to_vec();
+    addresses.sort_by_key(|address| !matches!(address, ListenAddress::Tcp(SocketAddr::V6(_))));
+    let mut listeners = vec![];
+    let mut dual_stack_ports = vec![];
+    for address in &addresses {
+        match address {
+            ListenAddress::Tcp(socket_addr) => match TcpListener::bind(socket_addr).await {
+                Ok(listener) => {
+                    if socket_addr.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED) {
+                        dual_stack_ports.push(socket_addr.port());
+                    }
+                    listeners.push(Listener::Tcp(listener));
+                },
+                Err(e)
+                    if e.kind() == io::ErrorKind::AddrInUse
+                        && socket_addr.is_ipv4()
+                        && dual_stack_ports.contains(&socket_addr.port()) =>
+                {
+                    log::info!("{} is served by the IPv6 listener", socket_addr);
+                },
+                Err(e) => {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("failed to listen on {}: {}", socket_addr, e),
+                    ))
+                },
+            },
+            ListenAddress::Unix(path) => listeners.push(Listener::Unix(bind_unix(path)?)),
+        }
+    }
+    Ok(listeners)
+}
+
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    // a socket left behind by a server that didn't shut down cleanly
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            fs::remove_file(path)?;
+        }
+    }
+    let listener = UnixListener::bind(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("failed to listen on {}: {}", path.display(), e),
+        )
+    })?;
+    // only the user running the server may connect, they still have to authenticate
+    set_permissions(path, 0o600)?;
+    Ok(listener)
+}
+
+/// stdin and stdout as a single stream, to serve a connection over them
+pub struct Stdio {
+    stdin: Stdin,
+    stdout: Stdout,
+}
+
+impl Default for Stdio {
+    fn default() -> Self {
+        Stdio {
+            stdin: tokio::io::stdin(),
+            stdout: tokio::io::stdout(),
+        }
+    }
+}
+
+impl AsyncRead for Stdio {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdin).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Stdio {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stdout).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdout).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdout).poll_shutdown(cx)
+    }
+}
+
+/// The client of a connection served over stdio, as told by OpenSSH when it runs the server
+/// as a `ForceCommand`
+pub fn stdio_peer_addr() -> Option<SocketAddr> {
+    let ssh_client = std::env::var("SSH_CLIENT").ok()?;
+    let mut fields = ssh_client.split_whitespace();
+    let ip = fields.next()?.parse().ok()?;
+    let port = fields.next()?.parse().ok()?;
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn listen_addresses_are_parsed() {
+        assert_eq!(
+            ListenAddress::parse("0.0.0.0", 6222),
+            Ok(ListenAddress::Tcp("0.0.0.0:6222".parse().unwrap()))
+        );
+        assert_eq!(
+            ListenAddress::parse("::", 6222),
+            Ok(ListenAddress::Tcp("[::]:6222".parse().unwrap()))
+        );
+        assert_eq!(
+            ListenAddress::parse("[::1]", 6222),
+            Ok(ListenAddress::Tcp("[::1]:6222".parse().unwrap()))
+        );
+        assert_eq!(
+            ListenAddress::parse("127.0.0.1:2222", 6222),
+            Ok(ListenAddress::Tcp("127.0.0.1:2222".parse().unwrap()))
+        );
+        assert_eq!(
+            ListenAddress::parse("/run/zmate.sock", 6222),
+            Ok(ListenAddress::Unix(PathBuf::from("/run/zmate.sock")))
+        );
+        assert!(ListenAddress::parse("localhost", 6222).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use zellij_utils::setup::find_default_config_dir;
+
+/// The layouts zellij ships with, they can be picked without any layout files
+const BUILT_IN_LAYOUTS: &[&str] = &["default", "compact", "strider", "disable-status-bar"];
+const MAX_DISPLAY_NAME_LEN: usize = 32;
+
+/// Resets the terminal the lobby drew on, before the zellij client takes it over
+pub const LEAVE_LOBBY: &[u8] = b"\x1b[0m\x1b[H\x1b[2J\x1b[?25h";
+
+/// A running session as listed in the lobby
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LobbySession {
+    pub name: String,
+    pub collaborators: usize,
+    pub viewers: usize,
+}
+
+impl LobbySession {
+    fn describe(&self) -> String {
+        let count = |n: usize, what: &str| match n {
+            1 => format!("1 {}", what),
+            n => format!("{} {}s", n, what),
+        };
+        match (self.collaborators, self.viewers) {
+            (0, 0) => "nobody connected over ssh".to_string(),
+            (collaborators, 0) => count(collaborators, "collaborator"),
+            (0, viewers) => count(viewers, "viewer"),
+            (collaborators, viewers) => format!(
+                "{}, {}",
+                count(collaborators, "collaborator"),
+                count(viewers, "viewer")
+            ),
+        }
+    }
+}
+
+/// What the user picked in the lobby
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LobbyAction {
+    /// Attach to the running session of that name
+    Attach(String),
+    /// Start a new session with the layout of that name
+    New(String),
+    /// List the running sessions again
+    Refresh,
+    /// Leave without attaching to anything
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Key {
+    Up,
+    Down,
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+    CtrlC,
+    Char(char),
+}
+
+/// Splits the input of a terminal into keys. Escape sequences other than the arrow keys are
+/// skipped, and like in most terminal apps they are expected to arrive in one read
+fn parse_keys(data: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            0x1b => match data.get(i + 1) {
+                Some(b'[') | Some(b'O') => {
+                    let end = data[i + 2..]
+                        .iter()
+                        .position(|byte| (0x40..=0x7e).contains(byte))
+                        .map(|end| i + 2 + end)
+                        .unwrap_or(data.len() - 1);
+                    match data.get(end) {
+                        Some(b'A') => keys.push(Key::Up),
+                        Some(b'B') => keys.push(Key::Down),
+                        _ => {},
+                    }
+                    i = end + 1;
+                    continue;
+                },
+                _ => keys.push(Key::Esc),
+            },
+            b'\r' | b'\n' => keys.push(Key::Enter),
+            b'\t' => keys.push(Key::Tab),
+            0x7f | 0x08 => keys.push(Key::Backspace),
+            3 => keys.push(Key::CtrlC),
+            byte if byte < 0x20 => {},
+            _ => {
+                let end = data[i..]
+                    .iter()
+                    .position(|byte| *byte < 0x20 || *byte == 0x7f)
+                    .map(|end| i + end)
+                    .unwrap_or(data.len());
+                keys.extend(
+                    String::from_utf8_lossy(&data[i..end])
+                        .chars()
+                        .map(Key::Char),
+                );
+                i = end;
+                continue;
+            },
+        }
+        i += 1;
+    }
+    keys
+}
+
+/// The screen ssh users get before attaching when their login doesn't pin them to a session:
+/// the running sessions to join, the layouts to start a new one with, and the name the others
+/// will know them by
+#[derive(Debug)]
+pub struct Lobby {
+    sessions: Vec<LobbySession>,
+    // empty when the user may not start sessions
+    layouts: Vec<String>,
+    display_name: String,
+    default_display_name: String,
+    // the row of the sessions followed by the layouts
+    selected: usize,
+    editing_name: bool,
+    message: Option<String>,
+}
+
+impl Lobby {
+    pub fn new(sessions: Vec<LobbySession>, layouts: Vec<String>, user: &str) -> Self {
+        let default_display_name: String = user.chars().take(MAX_DISPLAY_NAME_LEN).collect();
+        Lobby {
+            sessions,
+            layouts,
+            display_name: default_display_name.clone(),
+            default_display_name,
+            selected: 0,
+            editing_name: false,
+            message: None,
+        }
+    }
+
+    /// The name the user typed, or their ssh user when they cleared it
+    pub fn display_name(&self) -> String {
+        match self.display_name.trim() {
+            "" => self.default_display_name.clone(),
+            display_name => display_name.to_string(),
+        }
+    }
+
+    pub fn set_sessions(&mut self, sessions: Vec<LobbySession>) {
+        self.sessions = sessions;
+        self.selected = self.selected.min(self.rows().saturating_sub(1));
+    }
+
+    /// Shows `message` above the key help until the next key
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    fn rows(&self) -> usize {
+        self.sessions.len() + self.layouts.len()
+    }
+
+    /// Handles what the user typed, returns what they picked if they did. Whatever follows the
+    /// pick in `data` is dropped
+    pub fn feed(&mut self, data: &[u8]) -> Option<LobbyAction> {
+        for key in parse_keys(data) {
+            self.message = None;
+            if key == Key::CtrlC {
+                return Some(LobbyAction::Quit);
+            }
+            if self.editing_name {
+                match key {
+                    Key::Char(c) if !c.is_control() => {
+                        if self.display_name.chars().count() < MAX_DISPLAY_NAME_LEN {
+                            self.display_name.push(c);
+                        }
+                    },
+                    Key::Backspace => {
+                        self.display_name.pop();
+                    },
+                    Key::Enter | Key::Tab | Key::Esc => self.editing_name = false,
+                    _ => {},
+                }
+                continue;
+            }
+            match key {
+                Key::Up | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+                Key::Down | Key::Char('j') => {
+                    self.selected = (self.selected + 1).min(self.rows().saturating_sub(1))
+                },
+                Key::Tab | Key::Char('n') => self.editing_name = true,
+                Key::Char('r') => return Some(LobbyAction::Refresh),
+                Key::Char('q') | Key::Esc => return Some(LobbyAction::Quit),
+                Key::Enter => {
+                    if let Some(session) = self.sessions.get(self.selected) {
+                        return Some(LobbyAction::Attach(session.name.clone()));
+                    }
+                    if let Some(layout) = self.layouts.get(self.selected - self.sessions.len()) {
+                        return Some(LobbyAction::New(layout.clone()));
+                    }
+                },
+                _ => {},
+            }
+        }
+        None
+    }
+
+    /// Draws the lobby on a terminal of `cols` by `rows`, from scratch
+    pub fn render(&self, cols: u32, rows: u32) -> Vec<u8> {
+        let cols = cols as usize;
+        let rows = rows as usize;
+        let mut body = vec![String::from(" Sessions")];
+        let mut selected_line = 0;
+        if self.sessions.is_empty() {
+            body.push("   no sessions running".to_string());
+        }
+        for (i, session) in self.sessions.iter().enumerate() {
+            if i == self.selected {
+                selected_line = body.len();
+            }
+            body.push(format!(
+                " {} {:<24} {}",
+                if i == self.selected { ">" } else { " " },
+                session.name,
+                session.describe()
+            ));
+        }
+        if !self.layouts.is_empty() {
+            body.push(String::new());
+            body.push(" New session with layout".to_string());
+            for (i, layout) in self.layouts.iter().enumerate() {
+                let row = self.sessions.len() + i;
+                if row == self.selected {
+                    selected_line = body.len();
+                }
+                body.push(format!(
+                    " {} {}",
+                    if row == self.selected { ">" } else { " " },
+                    layout
+                ));
+            }
+        }
+
+        let mut footer = vec![
+            String::new(),
+            format!(
+                " Name: {}{}",
+                self.display_name,
+                if self.editing_name { "_" } else { "" }
+            ),
+        ];
+        if let Some(message) = &self.message {
+            footer.push(format!(" {}", message));
+        }
+        footer.push(String::new());
+        footer.push(if self.editing_name {
+            " Type your name, Enter to save".to_string()
+        } else {
+            " ↑/↓ select  Enter join  Tab change name  r refresh  q quit".to_string()
+        });
+
+        // the title and the footer stay put, the body scrolls to keep the selection in view
+        let body_rows = rows.saturating_sub(2 + footer.len()).max(1);
+        let first_line = (selected_line + 1).saturating_sub(body_rows);
+        let mut screen = String::from("\x1b[?25l\x1b[H\x1b[2J");
+        screen.push_str(&format!(
+            "\x1b[1m{}\x1b[0m\r\n\r\n",
+            truncate(" zmate, pick a session to join", cols)
+        ));
+        for (i, line) in body.iter().enumerate().skip(first_line).take(body_rows) {
+            let line = truncate(line, cols);
+            if i == selected_line && !self.editing_name && self.rows() > 0 {
+                screen.push_str(&format!("\x1b[7m{}\x1b[0m\r\n", line));
+            } else {
+                screen.push_str(&format!("{}\r\n", line));
+            }
+        }
+        for (i, line) in footer.iter().enumerate() {
+            let line = truncate(line, cols);
+            if i == 1 && self.editing_name {
+                screen.push_str(&format!("\x1b[1m{}\x1b[0m", line));
+            } else {
+                screen.push_str(&line);
+            }
+            if i + 1 < footer.len() {
+                screen.push_str("\r\n");
+            }
+        }
+        screen.into_bytes()
+    }
+}
+
+fn truncate(line: &str, cols: usize) -> String {
+    line.chars().take(cols).collect()
+}
+
+/// The layouts a new session can be started with: the built-in ones and the `.kdl` files of
+/// the layout dir of `config_dir`, or of the default config dir
+pub fn available_layouts(config_dir: Option<PathBuf>) -> Vec<String> {
+    let mut layouts: Vec<String> = BUILT_IN_LAYOUTS.iter().map(|l| l.to_string()).collect();
+    let layout_dir = config_dir
+        .or_else(find_default_config_dir)
+        .map(|config_dir| config_dir.join("layouts"));
+    if let Some(layout_dir) = layout_dir {
+        layouts.extend(layout_files(&layout_dir));
+    }
+    layouts
+}
+
+fn layout_files(layout_dir: &Path) -> Vec<String> {
+    let mut layouts: Vec<String> = fs::read_dir(layout_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|file_name| !file_name.ends_with(".swap.kdl"))
+                .filter_map(|file_name| file_name.strip_suffix(".kdl").map(String::from))
+                .filter(|layout| !BUILT_IN_LAYOUTS.contains(&layout.as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+    layouts.sort();
+    layouts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sessions() -> Vec<LobbySession> {
+        vec![
+            LobbySession {
+                name: "pairing".to_string(),
+                collaborators: 2,
+                viewers: 1,
+            },
+            LobbySession {
+                name: "demo".to_string(),
+                collaborators: 0,
+                viewers: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn sessions_and_layouts_can_be_picked() {
+        let layouts = vec!["default".to_string(), "compact".to_string()];
+        let mut lobby = Lobby::new(sessions(), layouts, "alice");
+        assert_eq!(
+            lobby.feed(b"\r"),
+            Some(LobbyAction::Attach("pairing".to_string()))
+        );
+        assert_eq!(
+            lobby.feed(b"\x1b[B\r"),
+            Some(LobbyAction::Attach("demo".to_string()))
+        );
+        assert_eq!(
+            lobby.feed(b"jjjj\r"),
+            Some(LobbyAction::New("compact".to_string())),
+            "the selection stops at the last layout"
+        );
+        assert_eq!(
+            lobby.feed(b"\x1bOA\r"),
+            Some(LobbyAction::New("default".to_string()))
+        );
+        assert_eq!(lobby.feed(b"r"), Some(LobbyAction::Refresh));
+        assert_eq!(lobby.feed(b"q"), Some(LobbyAction::Quit));
+        assert_eq!(lobby.feed(&[3]), Some(LobbyAction::Quit));
+    }
+
+    #[test]
+    fn viewers_without_layouts_can_only_join() {
+        let mut lobby = Lobby::new(vec![], vec![], "bob");
+        assert_eq!(lobby.feed(b"j\r"), None, "nothing to pick");
+        lobby.set_sessions(sessions());
+        assert_eq!(
+            lobby.feed(b"jj\r"),
+            Some(LobbyAction::Attach("demo".to_string()))
+        );
+        let screen = String::from_utf8(lobby.render(80, 24)).unwrap();
+        assert!(screen.contains("2 collaborators, 1 viewer"));
+        assert!(screen.contains("nobody connected"));
+        assert!(!screen.contains("New session"));
+    }
+
+    #[test]
+    fn display_name_can_be_changed() {
+        let mut lobby = Lobby::new(sessions(), vec![], "alice");
+        assert_eq!(lobby.display_name(), "alice");
+        assert_eq!(lobby.feed("\t\x7f\x7f\x7f\x7f\x7fÅsa q\r".as_bytes()), None);
+        assert_eq!(lobby.display_name(), "Åsa q", "typed keys go to the name");
+        assert_eq!(lobby.feed(b"\t\x7f\x7f\x7f\x7f\x7f\r"), None);
+        assert_eq!(lobby.display_name(), "alice", "an empty name falls back");
+        assert_eq!(
+            lobby.feed(b"\r"),
+            Some(LobbyAction::Attach("pairing".to_string()))
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    mem,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+};
+
+use tokio::sync::Notify;
+
+/// Bytes a zellij client may have written ahead of its ssh channel before its writes block
+pub const OUTPUT_BUFFER_CAPACITY: usize = 256 * 1024;
+
+#[derive(Debug, Default)]
+struct OutputState {
+    data: Vec<u8>,
+    // the zellij client is done, what is left is still sent
+    closed: bool,
+    // the channel is gone, writes are dropped
+    receiver_gone: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<OutputState>,
+    capacity: usize,
+    // wakes the writers when the receiver took what was written
+    drained: Condvar,
+    // wakes the receiver when there is something to take
+    written: Notify,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, OutputState> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Creates the output path of a zellij client: its thread writes raw bytes into the
+/// [`OutputWriter`], and the task of its channel takes them from the [`OutputReceiver`] as fast
+/// as the ssh peer lets it. Writes block once `capacity` bytes are waiting, so a slow peer
+/// slows the client down instead of piling up its output
+pub fn output_buffer(capacity: usize) -> (OutputWriter, OutputReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(OutputState::default()),
+        capacity,
+        drained: Condvar::new(),
+        written: Notify::new(),
+    });
+    (
+        OutputWriter {
+            shared: shared.clone(),
+        },
+        OutputReceiver { shared },
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct OutputWriter {
+    shared: Arc<Shared>,
+}
+
+impl OutputWriter {
+    /// Appends `buf` once the buffer has room. A single write is never split, so it may take
+    /// the buffer over its capacity
+    pub fn write(&self, buf: &[u8]) {
+        let mut state = self.shared.lock();
+        while !state.receiver_gone && state.data.len() >= self.shared.capacity {
+            state = self.shared.drained.wait(state).unwrap();
+        }
+        if state.receiver_gone {
+            return;
+        }
+        state.data.extend_from_slice(buf);
+        drop(state);
+        self.shared.written.notify_one();
+    }
+
+    /// Appends `buf` without waiting for room, for short notices of the server itself. Like
+    /// any write, it lands between the writes of the client rather than in the middle of one
+    pub fn push(&self, buf: &[u8]) {
+        let mut state = self.shared.lock();
+        if state.receiver_gone {
+            return;
+        }
+        state.data.extend_from_slice(buf);
+        drop(state);
+        self.shared.written.notify_one();
+    }
+
+    /// The client is done, the channel is closed once what it wrote is sent
+    pub fn close(&self) {
+        self.shared.lock().closed = true;
+        self.shared.written.notify_one();
+    }
+}
+
+#[derive(Debug)]
+pub struct OutputReceiver {
+    shared: Arc<Shared>,
+}
+
+impl OutputReceiver {
+    /// Takes everything written since the last call, waiting for a write if there is nothing.
+    /// Returns `None` once the writer is closed and everything it wrote was taken
+    pub async fn next_chunk(&mut self) -> Option<Vec<u8>> {
+        loop {
+            {
+                let mut state = self.shared.lock();
+                if !state.data.is_empty() {
+                    let chunk = mem::take(&mut state.data);
+                    drop(state);
+                    self.shared.drained.notify_all();
+                    return Some(chunk);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.shared.written.notified().await;
+        }
+    }
+}
+
+impl Drop for OutputReceiver {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.receiver_gone = true;
+        state.data = Vec::new();
+        drop(state);
+        self.shared.drained.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{sync::mpsc, thread, time::Duration};
+    use tokio::runtime::{Builder, Runtime};
+
+    fn runtime() -> Runtime {
+        Builder::new_current_thread().build().unwrap()
+    }
+
+    #[test]
+    fn characters_split_across_writes_arrive_intact() {
+        let (writer, mut receiver) = output_buffer(1024);
+        let text = "héllo wörld ✓".as_bytes();
+        // the first write ends in the middle of é
+        let (first, second) = text.split_at(2);
+        writer.write(first);
+        writer.write(second);
+        writer.close();
+        runtime().block_on(async {
+            assert_eq!(receiver.next_chunk().await, Some(text.to_vec()));
+            assert_eq!(receiver.next_chunk().await, None);
+        });
+    }
+
+    #[test]
+    fn writes_wait_for_room_in_a_full_buffer() {
+        let (writer, mut receiver) = output_buffer(4);
+        writer.write(b"abcd");
+        let (written_tx, written_rx) = mpsc::channel();
+        let blocked_writer = writer.clone();
+        let writing = thread::spawn(move || {
+            blocked_writer.write(b"efgh");
+            written_tx.send(()).unwrap();
+        });
+        assert!(
+            written_rx.recv_timeout(Duration::from_millis(100)).is_err(),
+            "the write waits while the buffer is full"
+        );
+        let runtime = runtime();
+        assert_eq!(
+            runtime.block_on(receiver.next_chunk()),
+            Some(b"abcd".to_vec())
+        );
+        written_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        writing.join().unwrap();
+        writer.push(b"!");
+        assert_eq!(
+            runtime.block_on(receiver.next_chunk()),
+            Some(b"efgh!".to_vec())
+        );
+    }
+
+    #[test]
+    fn writes_are_dropped_once_the_channel_is_gone() {
+        let (writer, receiver) = output_buffer(4);
+        writer.write(b"abcd");
+        drop(receiver);
+        // would block forever if the buffer still counted
+        writer.write(b"efgh");
+        writer.close();
+    }
+}
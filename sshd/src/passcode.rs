@@ -0,0 +1,265 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rand::Rng;
+use zellij_utils::{shared::set_permissions, ssh::ShareRole};
+
+use crate::{
+    host_keys::create_ssh_data_dir,
+    share::{hostname, ShareAccess},
+};
+
+// no 0/o or 1/l, so passcodes read out over a call come across right
+const PASSCODE_ALPHABET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+const PASSCODE_GROUPS: usize = 3;
+const PASSCODE_GROUP_LENGTH: usize = 4;
+
+/// A one-time passcode that lets a guest without a registered key join a session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Passcode {
+    pub code: String,
+    pub session_name: String,
+    pub role: ShareRole,
+    /// seconds since the unix epoch
+    pub expires_at: u64,
+}
+
+impl Passcode {
+    pub fn access(&self) -> ShareAccess {
+        match self.role {
+            ShareRole::Viewer => ShareAccess::ReadOnly,
+            ShareRole::Collaborator => ShareAccess::ReadWrite,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        now() >= self.expires_at
+    }
+
+    fn decode(code: &str, raw: &str) -> Option<Self> {
+        // the session name goes last, it may contain spaces
+        let mut fields = raw.trim_end_matches('\n').splitn(3, ' ');
+        let role = match fields.next()? {
+            "viewer" => ShareRole::Viewer,
+            "collaborator" => ShareRole::Collaborator,
+            _ => return None,
+        };
+        let expires_at = fields.next()?.parse().ok()?;
+        let session_name = fields.next()?.to_string();
+        Some(Passcode {
+            code: code.to_string(),
+            session_name,
+            role,
+            expires_at,
+        })
+    }
+
+    fn encode(&self) -> String {
+        format!(
+            "{} {} {}\n",
+            role_name(self.role),
+            self.expires_at,
+            self.session_name
+        )
+    }
+}
+
+/// Unused passcodes, one file per passcode under `ssh/passcodes` in the data dir.
+///
+/// Passcodes are generated by `zellij ssh passcode new` and redeemed by the running server,
+/// removing the file is what marks a passcode as used.
+#[derive(Clone, Debug)]
+pub struct PasscodeStore {
+    dir: PathBuf,
+}
+
+impl PasscodeStore {
+    pub fn new(data_dir: Option<&Path>) -> io::Result<Self> {
+        let dir = create_ssh_data_dir(data_dir)?.join("passcodes");
+        fs::create_dir_all(&dir)?;
+        set_permissions(&dir, 0o700)?;
+        Ok(PasscodeStore { dir })
+    }
+
+    pub fn generate(
+        &self,
+        session_name: &str,
+        role: ShareRole,
+        valid_for: Duration,
+    ) -> io::Result<Passcode> {
+        let passcode = Passcode {
+            code: generate_code(),
+            session_name: session_name.to_string(),
+            role,
+            expires_at: now() + valid_for.as_secs(),
+        };
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(self.dir.join(&passcode.code))?;
+        file.write_all(passcode.encode().as_bytes())?;
+        Ok(passcode)
+    }
+
+    /// Uses up `code`, returning its passcode if it was valid. A passcode can only be
+    /// redeemed once, even by concurrent connections
+    pub fn redeem(&self, code: &str) -> Option<Passcode> {
+        let code = normalize(code)?;
+        let path = self.dir.join(&code);
+        let passcode = Passcode::decode(&code, &fs::read_to_string(&path).ok()?)?;
+        // whoever manages to remove the file is the one who used the passcode
+        fs::remove_file(&path).ok()?;
+        if passcode.is_expired() {
+            return None;
+        }
+        Some(passcode)
+    }
+
+    /// Returns whether there was an unused passcode to revoke
+    pub fn revoke(&self, code: &str) -> io::Result<bool> {
+        let code = match normalize(code) {
+            Some(code) => code,
+            None => return Ok(false),
+        };
+        match fs::remove_file(self.dir.join(code)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The passcodes that can still be used, expired ones are cleaned up on the way
+    pub fn list(&self) -> io::Result<Vec<Passcode>> {
+        let mut passcodes = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let passcode = path
+                .file_name()
+                .and_then(|code| code.to_str())
+                .and_then(|code| Passcode::decode(code, &fs::read_to_string(&path).ok()?));
+            match passcode {
+                Some(passcode) if !passcode.is_expired() => passcodes.push(passcode),
+                _ => {
+                    let _ = fs::remove_file(&path);
+                },
+            }
+        }
+        passcodes.sort_by_key(|passcode| passcode.expires_at);
+        Ok(passcodes)
+    }
+}
+
+/// Prints `passcode` and how guests use it to join over the server listening on `port`
+pub fn print_passcode(passcode: &Passcode, port: u16) {
+    let host = hostname().unwrap_or_else(|| "localhost".to_string());
+    println!(
+        "Passcode for session \u{1b}[32;1m{}\u{1b}[m ({}), valid for {} minutes:",
+        passcode.session_name,
+        role_name(passcode.role),
+        passcode.expires_at.saturating_sub(now()) / 60
+    );
+    println!("  {}", passcode.code);
+    println!(
+        "Join with ssh -p {} <your name>@{}, then enter the passcode when asked.",
+        port, host
+    );
+}
+
+/// Prints the passcodes that haven't been used yet
+pub fn print_passcodes(passcodes: &[Passcode]) {
+    if passcodes.is_empty() {
+        println!("No unused passcodes.");
+    }
+    for passcode in passcodes {
+        println!(
+            "{}  {} ({}), expires in {} minutes",
+            passcode.code,
+            passcode.session_name,
+            role_name(passcode.role),
+            passcode.expires_at.saturating_sub(now()) / 60
+        );
+    }
+}
+
+fn role_name(role: ShareRole) -> &'static str {
+    match role {
+        ShareRole::Viewer => "viewer",
+        ShareRole::Collaborator => "collaborator",
+    }
+}
+
+fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..PASSCODE_GROUPS)
+        .map(|_| {
+            (0..PASSCODE_GROUP_LENGTH)
+                .map(|_| char::from(PASSCODE_ALPHABET[rng.gen_range(0..PASSCODE_ALPHABET.len())]))
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// passcodes are typed in by hand, and end up in a path, so anything but the exact format of a
+// generated code is refused
+fn normalize(code: &str) -> Option<String> {
+    let code = code.trim().to_lowercase();
+    let groups: Vec<&str> = code.split('-').collect();
+    let well_formed = groups.len() == PASSCODE_GROUPS
+        && groups.iter().all(|group| {
+            group.len() == PASSCODE_GROUP_LENGTH
+                && group.bytes().all(|b| PASSCODE_ALPHABET.contains(&b))
+        });
+    well_formed.then_some(code)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zellij_utils::tempfile::tempdir;
+
+    #[test]
+    fn passcodes_can_only_be_used_once() {
+        let data_dir = tempdir().unwrap();
+        let store = PasscodeStore::new(Some(data_dir.path())).unwrap();
+        let passcode = store
+            .generate("shared", ShareRole::Collaborator, Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(store.list().unwrap(), vec![passcode.clone()]);
+        let redeemed = store.redeem(&passcode.code.to_uppercase()).unwrap();
+        assert_eq!(redeemed.session_name, "shared");
+        assert_eq!(redeemed.access(), ShareAccess::ReadWrite);
+        assert_eq!(store.redeem(&passcode.code), None);
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn expired_and_revoked_passcodes_are_refused() {
+        let data_dir = tempdir().unwrap();
+        let store = PasscodeStore::new(Some(data_dir.path())).unwrap();
+        let expired = store
+            .generate("shared", ShareRole::Viewer, Duration::from_secs(0))
+            .unwrap();
+        assert_eq!(store.redeem(&expired.code), None);
+        let revoked = store
+            .generate("shared", ShareRole::Viewer, Duration::from_secs(60))
+            .unwrap();
+        assert!(store.revoke(&revoked.code).unwrap());
+        assert!(!store.revoke(&revoked.code).unwrap());
+        assert_eq!(store.redeem(&revoked.code), None);
+        assert_eq!(store.redeem("../../shares/shared"), None);
+    }
+}
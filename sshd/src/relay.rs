@@ -0,0 +1,843 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    net::{IpAddr, SocketAddr},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use russh::{
+    client,
+    server::{self, Handle, Msg, Session},
+    Channel, MethodSet,
+};
+use russh_keys::key::{KeyPair, PublicKey};
+use tokio::{
+    io::{copy_bidirectional, AsyncRead, AsyncWrite},
+    sync::oneshot,
+    task::JoinSet,
+};
+use zellij_utils::ssh::{Ssh, DEFAULT_SSH_PORT};
+
+use crate::{
+    host_keys::{create_ssh_data_dir, fingerprint, load_host_keys},
+    limits::{Connection, ConnectionLimits},
+    listener::{bind_all, parse_addresses, Listener},
+    server::{Server, DEFAULT_INACTIVITY_TIMEOUT_SECS},
+    share::generate_token,
+};
+
+const RELAY_ID_FILE: &str = "relay_id";
+// the keys the relay ids were registered with, on the relay
+const RELAY_HOSTS_FILE: &str = "relay_hosts";
+// hosts log in to the relay under this name with their host key, guests under the relay id of
+// their host without authenticating
+const HOST_USER: &str = "zmate-host";
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum RelayError {
+    #[error(transparent)]
+    Ssh(#[from] russh::Error),
+    #[error("the relay refused to let this host log in")]
+    Rejected,
+    #[error("the relay id is taken by another connection or belongs to another host key")]
+    Taken,
+}
+
+/// Where a relay listens: `relay.example.com`, `relay.example.com:2222` or `[2001:db8::1]:2222`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelayAddress {
+    host: String,
+    port: u16,
+}
+
+impl FromStr for RelayAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(socket_addr) = s.parse::<SocketAddr>() {
+            return Ok(RelayAddress {
+                host: socket_addr.ip().to_string(),
+                port: socket_addr.port(),
+            });
+        }
+        if let Ok(ip) = s
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            return Ok(RelayAddress {
+                host: ip.to_string(),
+                port: DEFAULT_SSH_PORT,
+            });
+        }
+        let (host, port) = match s.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("{:?} has an invalid port", s))?,
+            ),
+            None => (s, DEFAULT_SSH_PORT),
+        };
+        if host.is_empty() {
+            return Err(format!("{:?} has no host name", s));
+        }
+        Ok(RelayAddress {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl Display for RelayAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// The id this machine registers with relays under, kept in the data dir so the commands
+/// guests were given keep working when the server restarts
+pub fn relay_id(data_dir: Option<&Path>) -> io::Result<String> {
+    let path = create_ssh_data_dir(data_dir)?.join(RELAY_ID_FILE);
+    if let Ok(relay_id) = fs::read_to_string(&path) {
+        let relay_id = relay_id.trim();
+        if !relay_id.is_empty() {
+            return Ok(relay_id.to_string());
+        }
+    }
+    let relay_id = generate_token();
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)?;
+    file.write_all(relay_id.as_bytes())?;
+    Ok(relay_id)
+}
+
+/// The hosts registered with a relay by relay id, along with the connection that registered
+/// them. An id stays with its connection until that connection is gone, so a guest who knows
+/// the id can't take it over
+#[derive(Debug)]
+struct Routes<T> {
+    hosts: HashMap<String, (u64, T)>,
+}
+
+impl<T> Default for Routes<T> {
+    fn default() -> Self {
+        Routes {
+            hosts: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone> Routes<T> {
+    fn register(&mut self, relay_id: &str, connection_id: u64, host: T) -> bool {
+        match self.hosts.get(relay_id) {
+            Some((registered_by, _)) if *registered_by != connection_id => false,
+            _ => {
+                self.hosts
+                    .insert(relay_id.to_string(), (connection_id, host));
+                true
+            },
+        }
+    }
+
+    fn unregister(&mut self, relay_id: &str, connection_id: u64) -> bool {
+        match self.hosts.get(relay_id) {
+            Some((registered_by, _)) if *registered_by == connection_id => {
+                self.hosts.remove(relay_id);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn connection_closed(&mut self, connection_id: u64) {
+        self.hosts
+            .retain(|_, (registered_by, _)| *registered_by != connection_id);
+    }
+
+    fn find(&self, relay_id: &str) -> Option<T> {
+        self.hosts.get(relay_id).map(|(_, host)| host.clone())
+    }
+}
+
+/// The key each relay id was first registered with, kept in the data dir of the relay. Only
+/// that key can register the id again, so nobody takes over the id of a host, not even while
+/// the host is away
+#[derive(Debug, Default)]
+struct HostBindings {
+    path: Option<PathBuf>,
+    fingerprints: HashMap<String, String>,
+}
+
+impl HostBindings {
+    fn load(path: PathBuf) -> io::Result<Self> {
+        let fingerprints = match fs::read_to_string(&path) {
+            Ok(raw) => raw
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(relay_id, fingerprint)| {
+                    (relay_id.to_string(), fingerprint.trim().to_string())
+                })
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(HostBindings {
+            path: Some(path),
+            fingerprints,
+        })
+    }
+
+    /// Whether the key with `fingerprint` may register `relay_id`, binding the id to it if
+    /// nobody registered it yet
+    fn claim(&mut self, relay_id: &str, fingerprint: &str) -> io::Result<bool> {
+        match self.fingerprints.get(relay_id) {
+            Some(bound) => Ok(bound == fingerprint),
+            None => {
+                if let Some(path) = &self.path {
+                    let mut file = OpenOptions::new()
+                        .append(true)
+                        .create(true)
+                        .mode(0o600)
+                        .open(path)?;
+                    file.write_all(format!("{} {}\n", relay_id, fingerprint).as_bytes())?;
+                }
+                self.fingerprints
+                    .insert(relay_id.to_string(), fingerprint.to_string());
+                Ok(true)
+            },
+        }
+    }
+}
+
+/// A relay for hosts that guests can't reach directly. Hosts dial out to it and register under
+/// their relay id, guests jump through it (`ssh -J <relay id>@relay`) and are handed to their
+/// host over a channel of its connection. The guest's ssh connection runs through that channel
+/// end to end, so the relay never sees what is typed or shown.
+pub struct Relay {
+    data_dir: Option<PathBuf>,
+    ssh_opts: Ssh,
+    limits: ConnectionLimits,
+    routes: Arc<Mutex<Routes<Handle>>>,
+    host_bindings: Arc<Mutex<HostBindings>>,
+    next_connection_id: AtomicU64,
+}
+
+impl Relay {
+    pub fn new(data_dir: Option<PathBuf>, ssh_opts: Ssh) -> io::Result<Self> {
+        let limits = ConnectionLimits::new(&ssh_opts);
+        let host_bindings =
+            HostBindings::load(create_ssh_data_dir(data_dir.as_deref())?.join(RELAY_HOSTS_FILE))?;
+        Ok(Relay {
+            data_dir,
+            ssh_opts,
+            limits,
+            routes: Arc::new(Mutex::new(Routes::default())),
+            host_bindings: Arc::new(Mutex::new(host_bindings)),
+            next_connection_id: AtomicU64::new(0),
+        })
+    }
+
+    fn config(&self, keys: Vec<KeyPair>) -> server::Config {
+        // idle host connections are closed too, which clears the registrations of hosts that
+        // went away without the relay noticing. Hosts that are still there dial in again
+        let inactivity_timeout = self
+            .ssh_opts
+            .inactivity_timeout
+            .unwrap_or(DEFAULT_INACTIVITY_TIMEOUT_SECS);
+        server::Config {
+            inactivity_timeout: (inactivity_timeout > 0)
+                .then(|| Duration::from_secs(inactivity_timeout)),
+            auth_rejection_time: Duration::from_secs(0),
+            keys,
+            // guests authenticate with their host, through the relay, hosts with their host key
+            methods: MethodSet::NONE | MethodSet::PUBLICKEY,
+            ..Default::default()
+        }
+    }
+
+    pub async fn listen(self) -> io::Result<()> {
+        let keys = load_host_keys(&self.ssh_opts.host_keys, self.data_dir.as_deref())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        for key in &keys {
+            let fingerprint = fingerprint(key).unwrap_or_else(|| "unknown".to_string());
+            println!("Relay host key fingerprint: {} {}", fingerprint, key.name());
+        }
+        let config = Arc::new(self.config(keys));
+        let addresses = parse_addresses(&self.ssh_opts.bind_addresses, self.ssh_opts.port())?;
+        let listeners = bind_all(&addresses, self.ssh_opts.port()).await?;
+
+        let relay = Arc::new(self);
+        let mut accept_loops = JoinSet::new();
+        for listener in listeners {
+            accept_loops.spawn(relay.clone().accept(listener, config.clone()));
+        }
+        // the accept loops only end when their listener fails
+        match accept_loops.join_next().await {
+            Some(Ok(result)) => result,
+            Some(Err(e)) => Err(io::Error::new(io::ErrorKind::Other, e)),
+            None => Ok(()),
+        }
+    }
+
+    async fn accept(
+        self: Arc<Self>,
+        listener: Listener,
+        config: Arc<server::Config>,
+    ) -> io::Result<()> {
+        match listener {
+            Listener::Tcp(listener) => {
+                log::info!("Relay listening on {}", listener.local_addr()?);
+                loop {
+                    let (stream, peer_addr) = listener.accept().await?;
+                    self.serve(stream, Some(peer_addr), config.clone());
+                }
+            },
+            Listener::Unix(listener) => {
+                log::info!("Relay listening on {:?}", listener.local_addr()?);
+                loop {
+                    let (stream, _) = listener.accept().await?;
+                    self.serve(stream, None, config.clone());
+                }
+            },
+        }
+    }
+
+    fn serve<S>(&self, stream: S, peer_addr: Option<SocketAddr>, config: Arc<server::Config>)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let connection = match self.limits.admit(peer_addr.map(|peer_addr| peer_addr.ip())) {
+            Ok(connection) => connection,
+            Err(rejection) => {
+                log::warn!("Rejected connection from {:?}: {}", peer_addr, rejection);
+                return;
+            },
+        };
+        let handler = RelayHandler {
+            connection_id: self.next_connection_id.fetch_add(1, Ordering::Relaxed),
+            peer_addr,
+            user: None,
+            host_key: None,
+            routes: self.routes.clone(),
+            host_bindings: self.host_bindings.clone(),
+            _connection: connection,
+        };
+        tokio::spawn(async move {
+            if let Err(e) = server::run_stream(config, stream, handler).await {
+                log::debug!("Relay connection from {:?} ended: {}", peer_addr, e);
+            }
+        });
+    }
+}
+
+/// A connection to the relay, from a host registering itself or from a guest on their way to
+/// a host
+struct RelayHandler {
+    connection_id: u64,
+    peer_addr: Option<SocketAddr>,
+    user: Option<String>,
+    // the fingerprint of the key a host logged in with
+    host_key: Option<String>,
+    routes: Arc<Mutex<Routes<Handle>>>,
+    host_bindings: Arc<Mutex<HostBindings>>,
+    _connection: Connection,
+}
+
+#[async_trait]
+impl server::Handler for RelayHandler {
+    type Error = anyhow::Error;
+
+    async fn auth_none(&mut self, user: &str) -> Result<server::Auth, Self::Error> {
+        if user == HOST_USER {
+            return Ok(server::Auth::Reject {
+                proceed_with_methods: Some(MethodSet::PUBLICKEY),
+            });
+        }
+        self.user = Some(user.to_string());
+        Ok(server::Auth::Accept)
+    }
+
+    async fn auth_publickey(
+        &mut self,
+        user: &str,
+        public_key: &PublicKey,
+    ) -> Result<server::Auth, Self::Error> {
+        // only hosts log in with a key, which tells them apart when they register
+        if user != HOST_USER {
+            return Ok(server::Auth::Reject {
+                proceed_with_methods: None,
+            });
+        }
+        self.user = Some(user.to_string());
+        self.host_key = Some(format!("SHA256:{}", public_key.fingerprint()));
+        Ok(server::Auth::Accept)
+    }
+
+    async fn tcpip_forward(
+        &mut self,
+        address: &str,
+        _port: &mut u32,
+        session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        let host_key = match &self.host_key {
+            Some(host_key) => host_key.clone(),
+            None => {
+                log::warn!(
+                    "{:?} tried to register as {} without logging in as a host",
+                    self.peer_addr,
+                    address
+                );
+                return Ok(false);
+            },
+        };
+        if !self
+            .host_bindings
+            .lock()
+            .unwrap()
+            .claim(address, &host_key)?
+        {
+            log::warn!(
+                "Host {:?} tried to register as {} with key {}, which belongs to another key",
+                self.peer_addr,
+                address,
+                host_key
+            );
+            return Ok(false);
+        }
+        let registered =
+            self.routes
+                .lock()
+                .unwrap()
+                .register(address, self.connection_id, session.handle());
+        if registered {
+            log::info!("Host {:?} registered as {}", self.peer_addr, address);
+        } else {
+            log::warn!(
+                "Host {:?} tried to register as {}, which is taken",
+                self.peer_addr,
+                address
+            );
+        }
+        Ok(registered)
+    }
+
+    async fn cancel_tcpip_forward(
+        &mut self,
+        address: &str,
+        _port: u32,
+        _session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        let unregistered = self
+            .routes
+            .lock()
+            .unwrap()
+            .unregister(address, self.connection_id);
+        Ok(unregistered)
+    }
+
+    async fn channel_open_direct_tcpip(
+        &mut self,
+        channel: Channel<Msg>,
+        _host_to_connect: &str,
+        _port_to_connect: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        // the guest names the host as its login, the host to connect to is whatever the guest
+        // typed after -J and means nothing here. Hosts don't open channels
+        if self.host_key.is_some() {
+            return Ok(false);
+        }
+        let relay_id = self.user.clone().unwrap_or_default();
+        let host = self.routes.lock().unwrap().find(&relay_id);
+        let host = match host {
+            Some(host) => host,
+            None => {
+                log::info!(
+                    "Guest {:?} asked for {:?}, which isn't registered",
+                    self.peer_addr,
+                    relay_id
+                );
+                return Ok(false);
+            },
+        };
+        // the host sees the guest's address, to apply its own limits and log it
+        let (originator_address, originator_port) = match self.peer_addr {
+            Some(peer_addr) => (peer_addr.ip().to_string(), u32::from(peer_addr.port())),
+            None => (String::new(), 0),
+        };
+        let peer_addr = self.peer_addr;
+        tokio::spawn(async move {
+            let host_channel = match host
+                .channel_open_forwarded_tcpip(
+                    relay_id.clone(),
+                    0,
+                    originator_address,
+                    originator_port,
+                )
+                .await
+            {
+                Ok(host_channel) => host_channel,
+                Err(e) => {
+                    log::warn!(
+                        "Failed to reach host {} for {:?}: {}",
+                        relay_id,
+                        peer_addr,
+                        e
+                    );
+                    return;
+                },
+            };
+            log::info!("Routing guest {:?} to host {}", peer_addr, relay_id);
+            let mut guest_stream = channel.into_stream();
+            let mut host_stream = host_channel.into_stream();
+            if let Err(e) = copy_bidirectional(&mut guest_stream, &mut host_stream).await {
+                log::debug!("Relayed connection of {:?} ended: {}", peer_addr, e);
+            }
+        });
+        Ok(true)
+    }
+}
+
+impl Drop for RelayHandler {
+    fn drop(&mut self) {
+        self.routes
+            .lock()
+            .unwrap()
+            .connection_closed(self.connection_id);
+    }
+}
+
+/// Keeps `server` registered with the relay at `relay` under `relay_id`, dialing in again
+/// whenever the connection to the relay drops. Guests the relay hands over are served like
+/// any other connection.
+pub(crate) async fn dial(
+    server: Arc<Server>,
+    config: Arc<server::Config>,
+    host_key: Arc<KeyPair>,
+    relay: RelayAddress,
+    relay_id: String,
+) {
+    let mut retry_delay = MIN_RETRY_DELAY;
+    let mut announced = false;
+    loop {
+        let (closed_tx, closed_rx) = oneshot::channel();
+        let tunnel = Tunnel {
+            server: server.clone(),
+            config: config.clone(),
+            _closed: closed_tx,
+        };
+        match register(tunnel, host_key.clone(), &relay, &relay_id).await {
+            Ok(_tunnel) => {
+                log::info!("Registered with relay {} as {}", relay, relay_id);
+                if !announced {
+                    server.print_relay_connect_strings(&relay, &relay_id);
+                    announced = true;
+                }
+                retry_delay = MIN_RETRY_DELAY;
+                let _ = closed_rx.await;
+                log::warn!("Lost the connection to relay {}", relay);
+            },
+            Err(e) => {
+                log::warn!("Failed to register with relay {}: {}", relay, e);
+                if !announced && retry_delay == MIN_RETRY_DELAY {
+                    eprintln!(
+                        "Failed to register with relay {}: {}, retrying in the background",
+                        relay, e
+                    );
+                }
+            },
+        }
+        tokio::time::sleep(retry_delay).await;
+        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+/// Logs in to the relay at `relay` with `host_key` and registers as `relay_id`, the relay then
+/// opens a channel on `tunnel` for every guest it hands over
+async fn register<H>(
+    tunnel: H,
+    host_key: Arc<KeyPair>,
+    relay: &RelayAddress,
+    relay_id: &str,
+) -> Result<client::Handle<H>, RelayError>
+where
+    H: client::Handler<Error = RelayError> + 'static,
+{
+    let mut handle = client::connect(
+        Arc::new(client::Config::default()),
+        (relay.host.as_str(), relay.port),
+        tunnel,
+    )
+    .await?;
+    if !handle.authenticate_publickey(HOST_USER, host_key).await? {
+        return Err(RelayError::Rejected);
+    }
+    match handle.tcpip_forward(relay_id, 0).await {
+        Ok(_) => {},
+        Err(russh::Error::RequestDenied) => return Err(RelayError::Taken),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(handle)
+}
+
+/// The host's end of its connection to the relay, every channel the relay opens on it carries
+/// the ssh connection of a guest
+struct Tunnel {
+    server: Arc<Server>,
+    config: Arc<server::Config>,
+    // dropped with the connection, which tells `dial` to dial in again
+    _closed: oneshot::Sender<()>,
+}
+
+#[async_trait]
+impl client::Handler for Tunnel {
+    type Error = RelayError;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKey,
+    ) -> Result<bool, Self::Error> {
+        // guests check the host key of this machine themselves, through the relay, so a rogue
+        // relay can turn guests away but not pose as this machine
+        log::info!(
+            "Relay host key fingerprint: SHA256:{}",
+            server_public_key.fingerprint()
+        );
+        Ok(true)
+    }
+
+    async fn server_channel_open_forwarded_tcpip(
+        &mut self,
+        channel: Channel<client::Msg>,
+        _connected_address: &str,
+        _connected_port: u32,
+        originator_address: &str,
+        originator_port: u32,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        let peer_addr = originator_address
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, originator_port as u16));
+        self.server
+            .serve(channel.into_stream(), peer_addr, self.config.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use zellij_utils::tempfile::tempdir;
+
+    /// Echoes what the guests the relay hands over send, in place of the ssh server of a host
+    struct EchoTunnel;
+
+    #[async_trait]
+    impl client::Handler for EchoTunnel {
+        type Error = RelayError;
+
+        async fn check_server_key(
+            &mut self,
+            _server_public_key: &PublicKey,
+        ) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+
+        async fn server_channel_open_forwarded_tcpip(
+            &mut self,
+            channel: Channel<client::Msg>,
+            _connected_address: &str,
+            _connected_port: u32,
+            _originator_address: &str,
+            _originator_port: u32,
+            _session: &mut client::Session,
+        ) -> Result<(), Self::Error> {
+            tokio::spawn(async move {
+                let (mut reader, mut writer) = tokio::io::split(channel.into_stream());
+                let _ = tokio::io::copy(&mut reader, &mut writer).await;
+            });
+            Ok(())
+        }
+    }
+
+    /// A guest jumping through the relay
+    struct Guest;
+
+    #[async_trait]
+    impl client::Handler for Guest {
+        type Error = RelayError;
+
+        async fn check_server_key(
+            &mut self,
+            _server_public_key: &PublicKey,
+        ) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+    }
+
+    async fn start_relay(data_dir: &Path) -> RelayAddress {
+        let relay = Arc::new(Relay::new(Some(data_dir.to_path_buf()), Ssh::default()).unwrap());
+        let config = Arc::new(relay.config(vec![KeyPair::generate_ed25519().unwrap()]));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, peer_addr)) = listener.accept().await {
+                relay.serve(stream, Some(peer_addr), config.clone());
+            }
+        });
+        RelayAddress {
+            host: address.ip().to_string(),
+            port: address.port(),
+        }
+    }
+
+    async fn connect_guest(relay: &RelayAddress) -> Result<client::Handle<Guest>, RelayError> {
+        client::connect(
+            Arc::new(client::Config::default()),
+            (relay.host.as_str(), relay.port),
+            Guest,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn guests_reach_their_host_through_the_relay() {
+        let data_dir = tempdir().unwrap();
+        let relay = start_relay(data_dir.path()).await;
+        let host_key = Arc::new(KeyPair::generate_ed25519().unwrap());
+        let _host = register(EchoTunnel, host_key, &relay, "alice")
+            .await
+            .unwrap();
+
+        let mut guest = connect_guest(&relay).await.unwrap();
+        assert!(guest.authenticate_none("alice").await.unwrap());
+        let channel = guest
+            .channel_open_direct_tcpip("host", 22, "127.0.0.1", 0)
+            .await
+            .unwrap();
+        let mut stream = channel.into_stream();
+        stream.write_all(b"ping").await.unwrap();
+        let mut echoed = [0; 4];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"ping");
+
+        let mut lost_guest = connect_guest(&relay).await.unwrap();
+        assert!(lost_guest.authenticate_none("bob").await.unwrap());
+        assert!(
+            lost_guest
+                .channel_open_direct_tcpip("host", 22, "127.0.0.1", 0)
+                .await
+                .is_err(),
+            "nobody registered as bob"
+        );
+    }
+
+    #[tokio::test]
+    async fn relay_ids_belong_to_the_key_that_registered_them_first() {
+        let data_dir = tempdir().unwrap();
+        let relay = start_relay(data_dir.path()).await;
+        let host_key = Arc::new(KeyPair::generate_ed25519().unwrap());
+        let impostor_key = Arc::new(KeyPair::generate_ed25519().unwrap());
+        let host = register(EchoTunnel, host_key.clone(), &relay, "alice")
+            .await
+            .unwrap();
+        assert!(matches!(
+            register(EchoTunnel, impostor_key.clone(), &relay, "alice").await,
+            Err(RelayError::Taken)
+        ));
+
+        let mut unauthenticated = connect_guest(&relay).await.unwrap();
+        assert!(
+            !unauthenticated.authenticate_none(HOST_USER).await.unwrap(),
+            "hosts log in with a key"
+        );
+
+        // the id stays with the key while its host is away, even across restarts of the relay
+        drop(host);
+        let restarted = start_relay(data_dir.path()).await;
+        assert!(matches!(
+            register(EchoTunnel, impostor_key, &restarted, "alice").await,
+            Err(RelayError::Taken)
+        ));
+        assert!(register(EchoTunnel, host_key, &restarted, "alice")
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn relay_addresses_are_parsed() {
+        let parse = |s: &str| s.parse::<RelayAddress>().map(|relay| relay.to_string());
+        assert_eq!(
+            parse("relay.example.com"),
+            Ok("relay.example.com:6222".to_string())
+        );
+        assert_eq!(
+            parse("relay.example.com:2222"),
+            Ok("relay.example.com:2222".to_string())
+        );
+        assert_eq!(parse("10.0.0.1"), Ok("10.0.0.1:6222".to_string()));
+        assert_eq!(parse("[::1]:2222"), Ok("[::1]:2222".to_string()));
+        assert_eq!(parse("::1"), Ok("[::1]:6222".to_string()));
+        assert!(parse("relay.example.com:ssh").is_err());
+        assert!(parse(":2222").is_err());
+    }
+
+    #[test]
+    fn relay_ids_stay_with_the_connection_that_registered_them() {
+        let mut routes = Routes::default();
+        assert!(routes.register("alice", 1, "first host"));
+        assert!(
+            !routes.register("alice", 2, "impostor"),
+            "taken by another connection"
+        );
+        assert_eq!(routes.find("alice"), Some("first host"));
+        assert!(!routes.unregister("alice", 2));
+        assert!(routes.register("bob", 1, "first host"));
+        routes.connection_closed(1);
+        assert_eq!(routes.find("alice"), None);
+        assert_eq!(routes.find("bob"), None);
+        assert!(routes.register("alice", 2, "second host"));
+        assert!(routes.unregister("alice", 2));
+        assert_eq!(routes.find("alice"), None);
+    }
+
+    #[test]
+    fn relay_ids_stay_bound_to_the_first_key() {
+        let data_dir = tempdir().unwrap();
+        let path = data_dir.path().join(RELAY_HOSTS_FILE);
+        let mut bindings = HostBindings::load(path.clone()).unwrap();
+        assert!(bindings.claim("alice", "SHA256:first").unwrap());
+        assert!(bindings.claim("alice", "SHA256:first").unwrap());
+        assert!(!bindings.claim("alice", "SHA256:second").unwrap());
+        assert!(bindings.claim("bob", "SHA256:second").unwrap());
+
+        let mut reloaded = HostBindings::load(path).unwrap();
+        assert!(!reloaded.claim("alice", "SHA256:second").unwrap());
+        assert!(reloaded.claim("alice", "SHA256:first").unwrap());
+        assert!(reloaded.claim("bob", "SHA256:second").unwrap());
+    }
+}
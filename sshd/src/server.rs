@@ -1,46 +1,406 @@
-use std::sync::Arc;
+use std::{fs, net::SocketAddr, sync::Arc, time::Duration};
 
-use russh::{server, MethodSet};
-use tokio::sync::mpsc::unbounded_channel;
-use zellij_utils::{cli::CliArgs, ssh::Ssh};
+use russh::MethodSet;
+use russh_keys::key::KeyPair;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    signal::unix::{signal, Signal, SignalKind},
+    sync::{mpsc::unbounded_channel, watch},
+    task::JoinSet,
+};
+use zellij_utils::{
+    cli::CliArgs,
+    envs,
+    ssh::{Ssh, SshAuthMethod},
+};
 
 use crate::{
+    accept_env::AcceptedEnv,
+    approval::DEFAULT_APPROVAL_TIMEOUT_SECS,
+    audit::AuditLog,
+    authorized_keys::AuthorizedKeys,
+    clients::ClientRegistry,
+    escape::parse_escape_char,
+    forward::{ActiveForwards, ForwardAllowlist},
     handler::{Handler, HandlerEvent},
+    host_keys::{fingerprint, load_host_keys, ssh_data_dir},
+    limits::{Connection, ConnectionLimits},
+    listener::{bind_all, parse_addresses, stdio_peer_addr, ListenAddress, Listener, Stdio},
+    passcode::PasscodeStore,
+    relay::{dial, relay_id, RelayAddress},
     session::Session,
+    share::{print_connect_strings, print_relay_connect_strings, ShareRegistry},
 };
 
+pub(crate) const DEFAULT_INACTIVITY_TIMEOUT_SECS: u64 = 3600;
+const DEFAULT_AUTH_REJECTION_TIME_SECS: u64 = 3;
+const DEFAULT_SHUTDOWN_GRACE_PERIOD_SECS: u64 = 10;
+// how long connections get to close after the grace period, before the server exits anyway
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_AUTH_REJECTION_TIME_INITIAL_SECS: u64 = 0;
+
 pub struct Server {
     args: CliArgs,
     ssh_opts: Ssh,
+    authorized_keys: AuthorizedKeys,
+    share_registry: ShareRegistry,
+    forward_allowlist: ForwardAllowlist,
+    active_forwards: ActiveForwards,
+    accepted_env: AcceptedEnv,
+    passcodes: PasscodeStore,
+    audit_log: AuditLog,
+    limits: ConnectionLimits,
+    clients: ClientRegistry,
+    escape_char: Option<u8>,
+    // the grace period once the server is shutting down, every session watches it and the
+    // server waits for them to let go of it
+    shutdown: watch::Sender<Option<Duration>>,
 }
 
 impl Server {
-    pub fn new(args: CliArgs, ssh_opts: Ssh) -> Self {
-        Self { args, ssh_opts }
+    pub fn new(args: CliArgs, ssh_opts: Ssh) -> Result<Self, std::io::Error> {
+        let authorized_keys_path = ssh_opts
+            .authorized_keys
+            .clone()
+            .unwrap_or_else(|| ssh_data_dir(args.data_dir.as_deref()).join("authorized_keys"));
+        let share_registry = ShareRegistry::new(args.data_dir.as_deref())?;
+        let forward_allowlist = ForwardAllowlist::parse(&ssh_opts.allowed_forwards);
+        let active_forwards = ActiveForwards::new(args.data_dir.as_deref())?;
+        let accepted_env = AcceptedEnv::new(&ssh_opts.accepted_env);
+        let passcodes = PasscodeStore::new(args.data_dir.as_deref())?;
+        let audit_log = AuditLog::new(args.data_dir.as_deref())?;
+        let limits = ConnectionLimits::new(&ssh_opts);
+        let escape_char = parse_escape_char(ssh_opts.escape_char.as_deref())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        Ok(Self {
+            args,
+            ssh_opts,
+            authorized_keys: AuthorizedKeys::new(authorized_keys_path),
+            share_registry,
+            forward_allowlist,
+            active_forwards,
+            accepted_env,
+            passcodes,
+            audit_log,
+            limits,
+            clients: ClientRegistry::default(),
+            escape_char,
+            shutdown: watch::channel(None).0,
+        })
     }
 
     pub async fn listen(self) -> Result<(), std::io::Error> {
+        let config = self.config()?;
+        for fingerprint in host_key_fingerprints(&config.keys) {
+            log::info!("Host key {}", fingerprint);
+            println!("Host key fingerprint: {}", fingerprint);
+        }
+        if !self.authorized_keys.path().exists() {
+            eprintln!(
+                "No authorized keys found at {:?}, all public key authentication will be rejected",
+                self.authorized_keys.path()
+            );
+        }
+        self.share_registry.remove_stale_tokens();
+        if let Ok(session_name) = envs::get_session_name() {
+            print_connect_strings(&self.share_registry, &[session_name], self.ssh_opts.port());
+        }
+        let addresses = parse_addresses(&self.ssh_opts.bind_addresses, self.ssh_opts.port())?;
+        let relay = match &self.ssh_opts.relay {
+            Some(relay) => Some((
+                relay
+                    .parse::<RelayAddress>()
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+                relay_id(self.args.data_dir.as_deref())?,
+                // the relay id belongs to the key it was first registered with
+                Arc::new(relay_key(&config.keys)?),
+            )),
+            None => None,
+        };
+        let listeners = bind_all(&addresses, self.ssh_opts.port()).await?;
+        let mut shutdown_signals = ShutdownSignals::new()?;
+
+        let server = Arc::new(self);
+        let mut accept_loops = JoinSet::new();
+        for listener in listeners {
+            accept_loops.spawn(server.clone().accept(listener, config.clone()));
+        }
+        // kept apart from the accept loops, guests that came through the relay need it until
+        // they are detached
+        let relay_tunnel = relay.map(|(relay, relay_id, relay_key)| {
+            tokio::spawn(dial(
+                server.clone(),
+                config.clone(),
+                relay_key,
+                relay,
+                relay_id,
+            ))
+        });
+        tokio::select! {
+            // the accept loops only end when their listener fails
+            accepted = accept_loops.join_next() => {
+                return match accepted {
+                    Some(Ok(result)) => result,
+                    Some(Err(e)) => Err(std::io::Error::new(std::io::ErrorKind::Other, e)),
+                    None => Ok(()),
+                };
+            },
+            signal = shutdown_signals.recv() => log::info!("Received {}", signal),
+        }
+        accept_loops.abort_all();
+        server.shut_down(&mut shutdown_signals).await;
+        if let Some(relay_tunnel) = relay_tunnel {
+            relay_tunnel.abort();
+        }
+        for address in &addresses {
+            if let ListenAddress::Unix(path) = address {
+                let _ = fs::remove_file(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Warns the connected clients, then detaches them and closes their connections once the
+    /// grace period is over
+    async fn shut_down(&self, shutdown_signals: &mut ShutdownSignals) {
+        let grace_period = Duration::from_secs(
+            self.ssh_opts
+                .shutdown_grace_period
+                .unwrap_or(DEFAULT_SHUTDOWN_GRACE_PERIOD_SECS),
+        );
+        log::info!(
+            "Shutting down, closing connections in {} seconds",
+            grace_period.as_secs()
+        );
+        eprintln!(
+            "Shutting down, remote clients are detached in {} seconds. Press Ctrl+C again to \
+             exit now",
+            grace_period.as_secs()
+        );
+        let _ = self.shutdown.send(Some(grace_period));
+        tokio::select! {
+            _ = self.shutdown.closed() => log::info!("All connections closed"),
+            _ = tokio::time::sleep(grace_period + SHUTDOWN_TIMEOUT) => {
+                log::warn!("Connections still open after the grace period, exiting anyway");
+            },
+            signal = shutdown_signals.recv() => {
+                log::warn!("Received {} again, exiting without waiting", signal);
+            },
+        }
+    }
+
+    /// Serves a single connection over stdin and stdout, until it closes
+    pub async fn serve_stdio(self) -> Result<(), std::io::Error> {
+        let config = self.config()?;
+        // stdout carries the connection, the fingerprints only go to the log
+        for fingerprint in host_key_fingerprints(&config.keys) {
+            log::info!("Host key {}", fingerprint);
+        }
+        let peer_addr = stdio_peer_addr();
+        let connection = self
+            .limits
+            .admit(peer_addr.map(|peer_addr| peer_addr.ip()))
+            .map_err(|rejection| {
+                log::warn!("Rejected connection from {:?}: {}", peer_addr, rejection);
+                std::io::Error::new(std::io::ErrorKind::PermissionDenied, rejection)
+            })?;
+        let handler = self.new_client(peer_addr, connection);
+        russh::server::run_stream(config, Stdio::default(), handler)
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        Ok(())
+    }
+
+    fn config(&self) -> Result<Arc<russh::server::Config>, std::io::Error> {
+        let keys = load_host_keys(&self.ssh_opts.host_keys, self.args.data_dir.as_deref())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        if !self.authorized_keys.path().exists() {
+            log::warn!(
+                "Authorized keys file {:?} not found",
+                self.authorized_keys.path()
+            );
+        }
+        let inactivity_timeout = self
+            .ssh_opts
+            .inactivity_timeout
+            .unwrap_or(DEFAULT_INACTIVITY_TIMEOUT_SECS);
         let config = russh::server::Config {
-            inactivity_timeout: Some(std::time::Duration::from_secs(3600)),
-            auth_rejection_time: std::time::Duration::from_secs(3),
-            auth_rejection_time_initial: Some(std::time::Duration::from_secs(0)),
-            keys: vec![russh_keys::key::KeyPair::generate_ed25519().unwrap()],
-            methods: MethodSet::PUBLICKEY,
+            inactivity_timeout: (inactivity_timeout > 0)
+                .then(|| Duration::from_secs(inactivity_timeout)),
+            auth_rejection_time: Duration::from_secs(
+                self.ssh_opts
+                    .auth_rejection_time
+                    .unwrap_or(DEFAULT_AUTH_REJECTION_TIME_SECS),
+            ),
+            auth_rejection_time_initial: Some(Duration::from_secs(
+                self.ssh_opts
+                    .auth_rejection_time_initial
+                    .unwrap_or(DEFAULT_AUTH_REJECTION_TIME_INITIAL_SECS),
+            )),
+            keys,
+            methods: auth_methods(&self.ssh_opts),
             ..Default::default()
         };
-        let config = Arc::new(config);
-        russh::server::run(config, ("0.0.0.0", self.ssh_opts.port), self).await
+        Ok(Arc::new(config))
+    }
+
+    async fn accept(
+        self: Arc<Self>,
+        listener: Listener,
+        config: Arc<russh::server::Config>,
+    ) -> Result<(), std::io::Error> {
+        match listener {
+            Listener::Tcp(listener) => {
+                log::info!("Listening on {}", listener.local_addr()?);
+                loop {
+                    let (stream, peer_addr) = listener.accept().await?;
+                    self.serve(stream, Some(peer_addr), config.clone());
+                }
+            },
+            Listener::Unix(listener) => {
+                log::info!("Listening on {:?}", listener.local_addr()?);
+                loop {
+                    let (stream, _) = listener.accept().await?;
+                    self.serve(stream, None, config.clone());
+                }
+            },
+        }
+    }
+
+    pub(crate) fn serve<S>(
+        &self,
+        stream: S,
+        peer_addr: Option<SocketAddr>,
+        config: Arc<russh::server::Config>,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        // turned away before any ssh traffic, so a flood of connections costs next to nothing
+        let connection = match self.limits.admit(peer_addr.map(|peer_addr| peer_addr.ip())) {
+            Ok(connection) => connection,
+            Err(rejection) => {
+                log::warn!("Rejected connection from {:?}: {}", peer_addr, rejection);
+                return;
+            },
+        };
+        let handler = self.new_client(peer_addr, connection);
+        tokio::spawn(async move {
+            if let Err(e) = russh::server::run_stream(config, stream, handler).await {
+                log::debug!("Connection from {:?} ended: {}", peer_addr, e);
+            }
+        });
     }
-}
 
-impl server::Server for Server {
-    type Handler = Handler;
+    /// Prints how to join the session of this server through the relay it registered with
+    pub(crate) fn print_relay_connect_strings(&self, relay: &RelayAddress, relay_id: &str) {
+        if let Ok(session_name) = envs::get_session_name() {
+            print_relay_connect_strings(
+                &self.share_registry,
+                &[session_name],
+                &relay.to_string(),
+                relay_id,
+            );
+        }
+    }
 
-    fn new_client(&mut self, _peer_addr: Option<std::net::SocketAddr>) -> Self::Handler {
+    fn new_client(&self, peer_addr: Option<SocketAddr>, connection: Connection) -> Handler {
+        log::info!("New connection from {:?}", peer_addr);
         let (event_tx, event_rx) = unbounded_channel::<HandlerEvent>();
-        let mut sess = Session::new(self.args.clone(), event_rx);
+        let mut sess = Session::new(
+            self.args.clone(),
+            self.share_registry.clone(),
+            self.ssh_opts.sftp_root.clone(),
+            self.active_forwards.clone(),
+            peer_addr,
+            self.escape_char,
+            self.ssh_opts.skip_lobby,
+            self.ssh_opts.approve_guests.then(|| {
+                Duration::from_secs(
+                    self.ssh_opts
+                        .approval_timeout
+                        .unwrap_or(DEFAULT_APPROVAL_TIMEOUT_SECS),
+                )
+            }),
+            self.limits.clone(),
+            self.clients.clone(),
+            self.audit_log.clone(),
+            self.shutdown.subscribe(),
+            event_rx,
+        );
         tokio::spawn(async move { sess.run().await });
 
-        Handler::new(event_tx)
+        Handler::new(
+            event_tx,
+            peer_addr,
+            self.authorized_keys.clone(),
+            self.ssh_opts.allow_auth_none,
+            self.forward_allowlist.clone(),
+            self.accepted_env.clone(),
+            self.passcodes.clone(),
+            connection,
+        )
+    }
+}
+
+/// The signals that shut the server down
+struct ShutdownSignals {
+    sigterm: Signal,
+    sigint: Signal,
+}
+
+impl ShutdownSignals {
+    fn new() -> Result<Self, std::io::Error> {
+        Ok(ShutdownSignals {
+            sigterm: signal(SignalKind::terminate())?,
+            sigint: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.sigterm.recv() => "SIGTERM",
+            _ = self.sigint.recv() => "SIGINT",
+        }
+    }
+}
+
+// `<fingerprint> <key type>` of each host key, what clients are asked to check the first time
+fn host_key_fingerprints(keys: &[KeyPair]) -> Vec<String> {
+    keys.iter()
+        .map(|key| {
+            let fingerprint = fingerprint(key).unwrap_or_else(|| "unknown".to_string());
+            format!("{} {}", fingerprint, key.name())
+        })
+        .collect()
+}
+
+// the host key this machine logs in to relays with
+fn relay_key(keys: &[KeyPair]) -> Result<KeyPair, std::io::Error> {
+    keys.first().cloned().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no host key to log in to the relay with",
+        )
+    })
+}
+
+fn auth_methods(ssh_opts: &Ssh) -> MethodSet {
+    // passcodes can be generated while the server runs, so their methods are on unless
+    // turned off
+    let mut methods = if ssh_opts.auth_methods.is_empty() {
+        MethodSet::PUBLICKEY | MethodSet::PASSWORD | MethodSet::KEYBOARD_INTERACTIVE
+    } else {
+        MethodSet::empty()
+    };
+    for method in &ssh_opts.auth_methods {
+        methods |= match method {
+            SshAuthMethod::Publickey => MethodSet::PUBLICKEY,
+            SshAuthMethod::Password => MethodSet::PASSWORD,
+            SshAuthMethod::KeyboardInteractive => MethodSet::KEYBOARD_INTERACTIVE,
+        };
+    }
+    if ssh_opts.allow_auth_none {
+        methods |= MethodSet::NONE;
     }
+    methods
 }
@@ -1,118 +1,1194 @@
-use russh::{server::Handle, CryptoVec, Sig};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
-use zellij_utils::{cli::CliArgs, cli::Command, cli::Sessions, envs};
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use russh::{server::Handle, CryptoVec, Disconnect, Sig};
+use tokio::{
+    io::copy_bidirectional,
+    net::TcpStream,
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        watch,
+    },
+    time::{sleep, sleep_until, Instant},
+};
+use zellij_utils::{
+    cli::CliArgs,
+    cli::Command,
+    cli::Sessions,
+    envs, humantime,
+    ipc::{ClientType, RemoteClient},
+    ssh::ShareRole,
+};
 
 use crate::{
-    handler::HandlerEvent, zellij::start_client, PtyRequest, ServerChannelId, ServerHandle,
-    ZellijClientData,
+    approval::{ask_host, peer_ip, Approval, Guest},
+    audit::{AuditLog, AuditRecord},
+    clients::{ClientRegistry, ConnectedClient, RegisteredClient},
+    escape::{help, EscapeCommand, EscapeParser, Escaped},
+    exec::{run_exec_command, ExecOutput},
+    forward::ActiveForwards,
+    handler::HandlerEvent,
+    limits::{ConnectionLimits, SessionSlot},
+    lobby::{available_layouts, Lobby, LobbyAction, LobbySession, LEAVE_LOBBY},
+    output::{output_buffer, OutputWriter, OUTPUT_BUFFER_CAPACITY},
+    session_util::{get_sessions, session_exists},
+    sftp::{sftp_root, SftpSession},
+    share::{ShareAccess, ShareRegistry},
+    zellij::{start_client, unique_session_name},
+    ClientSignal, PtyRequest, ServerChannel, ServerChannelId, ServerHandle,
 };
 
+// how long the zellij clients get to detach before the connection is closed on shutdown
+const DETACH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The session a login or a shell channel joined and what it joined as, held while it stays
+struct Attachment {
+    session_name: Option<String>,
+    client_type: ClientType,
+    // the name it goes by in the session, the ssh user unless it picked another one in the lobby
+    display_name: String,
+    // counts against the client limit of the session
+    _session_slot: Option<SessionSlot>,
+    // lists it to the other clients of the session
+    registered_client: RegisteredClient,
+}
+
+/// State of one session channel of the connection, each shell channel runs its own zellij
+/// client with its own size and input
+struct ChannelState {
+    pty_request: Option<PtyRequest>,
+    input_sender: crossbeam_channel::Sender<Vec<u8>>,
+    input_receiver: crossbeam_channel::Receiver<Vec<u8>>,
+    signal_sender: crossbeam_channel::Sender<ClientSignal>,
+    signal_receiver: crossbeam_channel::Receiver<ClientSignal>,
+    // where the zellij client of a shell channel writes
+    output: Option<OutputWriter>,
+    escape: EscapeParser,
+    // set while the shell of the channel shows the lobby instead of a zellij client
+    lobby: Option<Lobby>,
+    // set while the host of the session picked in the lobby decides whether to let it in
+    awaiting_approval: bool,
+    // set once the client sent EOF, its zellij client keeps running until the channel closes
+    input_closed: bool,
+    // set once the shell picked a session in the lobby, each shell of a connection picks its own
+    attachment: Option<Attachment>,
+}
+
+impl ChannelState {
+    fn new(escape_char: Option<u8>) -> Self {
+        let (input_sender, input_receiver) = crossbeam_channel::unbounded::<Vec<u8>>();
+        let (signal_sender, signal_receiver) = crossbeam_channel::unbounded::<ClientSignal>();
+        ChannelState {
+            pty_request: None,
+            input_sender,
+            input_receiver,
+            signal_sender,
+            signal_receiver,
+            output: None,
+            escape: EscapeParser::new(escape_char),
+            lobby: None,
+            awaiting_approval: false,
+            input_closed: false,
+            attachment: None,
+        }
+    }
+
+    fn render_lobby(&self) {
+        if let (Some(lobby), Some(output), Some(pty_request)) =
+            (&self.lobby, &self.output, &self.pty_request)
+        {
+            output.push(&lobby.render(pty_request.col_width, pty_request.row_height));
+        }
+    }
+}
+
+impl Drop for ChannelState {
+    fn drop(&mut self) {
+        // makes the zellij client of the channel, if any, detach from its session
+        let _ = self.signal_sender.send(ClientSignal::Signal(Sig::HUP));
+        // a channel in the lobby has no zellij client to close it
+        if let (Some(_), Some(output)) = (&self.lobby, &self.output) {
+            output.close();
+        }
+    }
+}
+
 pub struct Session {
     handle: Option<Handle>,
     zellij_cli_args: CliArgs,
-    pty_request: Option<PtyRequest>,
-    channel_id: Option<ServerChannelId>,
+    share_registry: ShareRegistry,
+    sftp_root: Option<PathBuf>,
+    active_forwards: ActiveForwards,
+    // who is on the other end of the connection, as shown in the list of forwards
+    client: String,
+    // the address of the connection as shown to the host asked to let it in
+    peer_ip: Option<String>,
+    // what the login may do, shells picking a session in the lobby start out as it
+    client_type: ClientType,
+    // the session the login picked, unless its shells pick one in the lobby
+    attachment: Option<Attachment>,
+    has_forced_command: bool,
+    // accepted env requests of the connection, set in the panes its clients open
+    env: BTreeMap<String, String>,
+    channels: HashMap<ServerChannelId, ChannelState>,
+    escape_char: Option<u8>,
+    skip_lobby: bool,
+    // how long the host gets to let the connection into a session, if they are asked
+    approval_timeout: Option<Duration>,
+    // answers of the hosts asked to let a shell in the lobby into their session
+    approvals_tx: UnboundedSender<(ServerChannelId, String, Approval)>,
+    approvals_rx: UnboundedReceiver<(ServerChannelId, String, Approval)>,
+    // set when the login didn't pick a session, the shells then pick one in the lobby
+    choose_session: bool,
+    limits: ConnectionLimits,
+    clients: ClientRegistry,
+    // lines other connections want shown on the terminals of this one
+    notices_tx: UnboundedSender<Vec<String>>,
+    notices_rx: UnboundedReceiver<Vec<String>>,
+    audit_log: AuditLog,
+    audit_record: AuditRecord,
+    // counted by the tasks of the channels, copied into the audit record at the end
+    bytes_in: Arc<AtomicU64>,
+    bytes_out: Arc<AtomicU64>,
+    // set to the grace period when the server shuts down
+    shutdown: watch::Receiver<Option<Duration>>,
     rx: UnboundedReceiver<HandlerEvent>,
-    server_sender: crossbeam_channel::Sender<Vec<u8>>,
-    server_receiver: crossbeam_channel::Receiver<Vec<u8>>,
-    server_signal_sender: crossbeam_channel::Sender<Sig>,
-    server_signal_receiver: crossbeam_channel::Receiver<Sig>,
 }
 
 impl Session {
-    pub fn new(args: CliArgs, rx: UnboundedReceiver<HandlerEvent>) -> Self {
-        let (server_sender, server_receiver) = crossbeam_channel::unbounded::<Vec<u8>>();
-        let (server_signal_sender, server_signal_receiver) = crossbeam_channel::unbounded::<Sig>();
-
+    pub fn new(
+        args: CliArgs,
+        share_registry: ShareRegistry,
+        sftp_root: Option<PathBuf>,
+        active_forwards: ActiveForwards,
+        peer_addr: Option<SocketAddr>,
+        escape_char: Option<u8>,
+        skip_lobby: bool,
+        approval_timeout: Option<Duration>,
+        limits: ConnectionLimits,
+        clients: ClientRegistry,
+        audit_log: AuditLog,
+        shutdown: watch::Receiver<Option<Duration>>,
+        rx: UnboundedReceiver<HandlerEvent>,
+    ) -> Self {
+        let (notices_tx, notices_rx) = unbounded_channel();
+        let (approvals_tx, approvals_rx) = unbounded_channel();
         Self {
             zellij_cli_args: args,
+            share_registry,
+            sftp_root,
+            active_forwards,
+            client: String::new(),
+            peer_ip: peer_ip(peer_addr),
+            client_type: ClientType::Writer,
+            attachment: None,
+            has_forced_command: false,
+            env: BTreeMap::new(),
+            channels: HashMap::new(),
+            escape_char,
+            skip_lobby,
+            approval_timeout,
+            approvals_tx,
+            approvals_rx,
+            choose_session: false,
+            limits,
+            clients,
+            notices_tx,
+            notices_rx,
+            audit_log,
+            audit_record: AuditRecord::new(peer_addr.map(|peer_addr| peer_addr.to_string())),
+            bytes_in: Arc::new(AtomicU64::new(0)),
+            bytes_out: Arc::new(AtomicU64::new(0)),
+            shutdown,
             rx,
             handle: None,
-            channel_id: None,
-            server_receiver,
-            server_sender,
-            pty_request: None,
-            server_signal_sender,
-            server_signal_receiver,
         }
     }
 
+    /// Handles the events of the connection until it is gone, or the server shut down
     pub async fn run(&mut self) {
+        let mut shutdown_at: Option<Instant> = None;
         loop {
-            if let Some(event) = self.rx.recv().await {
-                self.handle_handler_event(event, self.zellij_cli_args.clone())
-                    .await
+            tokio::select! {
+                event = self.rx.recv() => match event {
+                    Some(event) => {
+                        self.handle_handler_event(event, self.zellij_cli_args.clone())
+                            .await
+                    },
+                    None => break,
+                },
+                Some(lines) = self.notices_rx.recv() => self.show_notice(&lines),
+                Some((channel_id, session_name, approval)) = self.approvals_rx.recv() => {
+                    self.handle_approval(channel_id, session_name, approval)
+                },
+                Ok(()) = self.shutdown.changed(), if shutdown_at.is_none() => {
+                    let grace_period = *self.shutdown.borrow();
+                    if let Some(grace_period) = grace_period {
+                        self.announce_shutdown(grace_period);
+                        shutdown_at = Some(Instant::now() + grace_period);
+                    }
+                },
+                _ = sleep_until(shutdown_at.unwrap_or_else(Instant::now)),
+                    if shutdown_at.is_some() =>
+                {
+                    self.disconnect().await;
+                    break;
+                },
+            }
+        }
+        // the connection is gone, tear down the clients of all its channels
+        self.channels.clear();
+        log::info!("Connection from {:?} closed", self.audit_record.peer_addr);
+        self.audit_record.disconnected_at =
+            Some(humantime::format_rfc3339_seconds(SystemTime::now()).to_string());
+        self.audit_record.bytes_in = self.bytes_in.load(Ordering::Relaxed);
+        self.audit_record.bytes_out = self.bytes_out.load(Ordering::Relaxed);
+        self.audit_log.append(&self.audit_record);
+    }
+
+    fn announce_shutdown(&self, grace_period: Duration) {
+        self.show_notice(&[format!(
+            "The server is shutting down, you will be detached in {} seconds",
+            grace_period.as_secs()
+        )]);
+    }
+
+    /// Draws `lines` over the bottom of the terminals of the connection, the next render of
+    /// zellij clears them
+    fn show_notice(&self, lines: &[String]) {
+        for channel in self.channels.values() {
+            if let (Some(output), Some(pty_request)) = (&channel.output, &channel.pty_request) {
+                output.push(&render_notice(lines, pty_request.row_height));
+            }
+        }
+    }
+
+    fn run_escape_command(&mut self, channel_id: ServerChannelId, command: EscapeCommand) {
+        let lines = match command {
+            EscapeCommand::Detach => {
+                log::info!("{} detached from channel {}", self.client, channel_id);
+                // the zellij client detaches and closes the channel on its way out
+                self.channels.remove(&channel_id);
+                return;
+            },
+            EscapeCommand::ConnectionInfo => {
+                let mut lines = vec![format!(
+                    "{} from {}",
+                    self.audit_record.user,
+                    self.audit_record
+                        .peer_addr
+                        .as_deref()
+                        .unwrap_or("unknown address")
+                )];
+                if let Some(auth_method) = &self.audit_record.auth_method {
+                    lines.push(format!("Authenticated with {}", auth_method));
+                }
+                if let Some(Attachment {
+                    session_name: Some(session_name),
+                    client_type,
+                    ..
+                }) = self.attachment(channel_id)
+                {
+                    lines.push(format!(
+                        "Session {} as {}",
+                        session_name,
+                        role(*client_type)
+                    ));
+                }
+                lines.push(format!(
+                    "Connected since {}, {} B in, {} B out",
+                    self.audit_record.connected_at,
+                    self.bytes_in.load(Ordering::Relaxed),
+                    self.bytes_out.load(Ordering::Relaxed)
+                ));
+                lines
+            },
+            EscapeCommand::RequestControl => match self.attachment(channel_id) {
+                Some(Attachment {
+                    client_type: ClientType::Writer,
+                    ..
+                }) => vec!["You already have control".to_string()],
+                Some(attachment) => {
+                    let asked = attachment.registered_client.notify_others(
+                        ShareRole::Collaborator,
+                        vec![format!("{} asks for control of the session", self.client)],
+                    );
+                    log::info!("{} asked {} collaborators for control", self.client, asked);
+                    if asked == 0 {
+                        vec!["Nobody who could hand over control is connected".to_string()]
+                    } else {
+                        vec![format!("Asked {} collaborators for control", asked)]
+                    }
+                },
+                None => vec!["Not connected to a session".to_string()],
+            },
+            EscapeCommand::ListClients => match self
+                .attachment(channel_id)
+                .and_then(|attachment| attachment.session_name.as_ref())
+            {
+                Some(session_name) => {
+                    let mut lines = vec![format!("Connected to {}:", session_name)];
+                    lines.extend(self.clients.clients_of(session_name).iter().map(|client| {
+                        format!(
+                            "{} from {} as {}",
+                            client.user,
+                            client.peer_addr.as_deref().unwrap_or("unknown address"),
+                            client.role
+                        )
+                    }));
+                    lines
+                },
+                None => vec!["Not connected to a session".to_string()],
+            },
+            EscapeCommand::Help => match self.escape_char {
+                Some(escape_char) => help(escape_char),
+                None => return,
+            },
+        };
+        if let Some(channel) = self.channels.get(&channel_id) {
+            if let (Some(output), Some(pty_request)) = (&channel.output, &channel.pty_request) {
+                output.push(&render_notice(&lines, pty_request.row_height));
+            }
+        }
+    }
+
+    fn role(&self) -> ShareRole {
+        role(self.client_type)
+    }
+
+    /// Where the shell of `channel_id` is attached, the session of the login unless it picked
+    /// one in the lobby
+    fn attachment(&self, channel_id: ServerChannelId) -> Option<&Attachment> {
+        self.channels
+            .get(&channel_id)
+            .and_then(|channel| channel.attachment.as_ref())
+            .or(self.attachment.as_ref())
+    }
+
+    /// Where the exec, sftp and forwarding requests of the connection act, the session of the
+    /// login or one its shells picked in the lobby. They are refused while the host still has
+    /// to let a login that picks its session in the lobby in
+    fn request_attachment(&self) -> Result<Option<&Attachment>, &'static str> {
+        let attachment = self.attachment.as_ref().or_else(|| {
+            self.channels
+                .values()
+                .find_map(|channel| channel.attachment.as_ref())
+        });
+        if attachment.is_none() && self.choose_session && self.approval_timeout.is_some() {
+            return Err("The host hasn't let you into a session yet");
+        }
+        Ok(attachment)
+    }
+
+    /// Lists the connection to the other clients of `session_name` under `display_name`, for
+    /// as long as the returned attachment is held
+    fn attach(
+        &self,
+        session_name: Option<String>,
+        client_type: ClientType,
+        display_name: String,
+        session_slot: Option<SessionSlot>,
+    ) -> Attachment {
+        let registered_client = self.clients.register(
+            ConnectedClient {
+                user: display_name.clone(),
+                peer_addr: self.audit_record.peer_addr.clone(),
+                session_name: session_name.clone(),
+                role: role(client_type),
+                connected_at: SystemTime::now(),
+            },
+            self.notices_tx.clone(),
+        );
+        Attachment {
+            session_name,
+            client_type,
+            display_name,
+            _session_slot: session_slot,
+            registered_client,
+        }
+    }
+
+    /// Runs a zellij client on the shell channel `channel_id`, attached to the session
+    /// `args` pick under the name the shell goes by
+    fn start_zellij_client(&self, channel_id: ServerChannelId, mut args: CliArgs) {
+        let (handle, channel) = match (self.handle.clone(), self.channels.get(&channel_id)) {
+            (Some(handle), Some(channel)) => (handle, channel),
+            _ => return,
+        };
+        let (pty_request, output) = match (&channel.pty_request, &channel.output) {
+            (Some(pty_request), Some(output)) => (pty_request, output.clone()),
+            _ => return,
+        };
+        let win_size = libc::winsize {
+            ws_row: pty_request.row_height as u16,
+            ws_col: pty_request.col_width as u16,
+            ws_xpixel: pty_request.pix_width as u16,
+            ws_ypixel: pty_request.pix_height as u16,
+        };
+        // like sshd, the terminal type comes from the pty request unless the client sent its
+        // own
+        let mut env = BTreeMap::new();
+        if !pty_request.term.is_empty() {
+            env.insert("TERM".to_string(), pty_request.term.clone());
+        }
+        env.extend(self.env.clone());
+        let input_receiver = channel.input_receiver.clone();
+        let signal_receiver = channel.signal_receiver.clone();
+        let client_type = self
+            .attachment(channel_id)
+            .map_or(self.client_type, |attachment| attachment.client_type);
+        if args.name.is_none() {
+            args.name = self
+                .attachment(channel_id)
+                .map(|attachment| attachment.display_name.clone());
+        }
+        let remote = self.remote_client();
+        std::thread::spawn(move || {
+            start_client(
+                args,
+                output,
+                input_receiver,
+                signal_receiver,
+                ServerHandle(handle),
+                channel_id.0,
+                win_size,
+                client_type,
+                env,
+                remote,
+            );
+        });
+    }
+
+    /// The running sessions, which any client whose login didn't pick a session may join
+    fn lobby_sessions(&self) -> Vec<LobbySession> {
+        let mut session_names: Vec<String> = get_sessions()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(session_name, _)| session_name)
+            .collect();
+        session_names.sort();
+        session_names
+            .into_iter()
+            .map(|session_name| {
+                let clients = self.clients.clients_of(&session_name);
+                let count = |role| clients.iter().filter(|c| c.role == role).count();
+                LobbySession {
+                    collaborators: count(ShareRole::Collaborator),
+                    viewers: count(ShareRole::Viewer),
+                    name: session_name,
+                }
+            })
+            .collect()
+    }
+
+    fn open_lobby(&mut self, channel_id: ServerChannelId) {
+        // viewers can't type into a session of their own either
+        let layouts = match self.client_type {
+            ClientType::Writer => available_layouts(self.zellij_cli_args.config_dir.clone()),
+            ClientType::Reader => vec![],
+        };
+        let lobby = Lobby::new(self.lobby_sessions(), layouts, &self.audit_record.user);
+        if let Some(channel) = self.channels.get_mut(&channel_id) {
+            channel.lobby = Some(lobby);
+            channel.render_lobby();
+        }
+    }
+
+    fn show_lobby_message(&mut self, channel_id: ServerChannelId, message: String) {
+        if let Some(channel) = self.channels.get_mut(&channel_id) {
+            if let Some(lobby) = channel.lobby.as_mut() {
+                lobby.set_message(message);
             }
+            channel.render_lobby();
+        }
+    }
+
+    fn run_lobby_action(&mut self, channel_id: ServerChannelId, action: LobbyAction) {
+        // the channel may have left the lobby on an earlier action of the same input
+        let (in_lobby, awaiting_approval) = match self.channels.get(&channel_id) {
+            Some(channel) => (channel.lobby.is_some(), channel.awaiting_approval),
+            None => return,
+        };
+        if !in_lobby {
+            return;
+        }
+        if awaiting_approval && matches!(action, LobbyAction::Attach(_) | LobbyAction::New(_)) {
+            self.show_lobby_message(
+                channel_id,
+                "Still waiting for the host to let you in".to_string(),
+            );
+            return;
+        }
+        match action {
+            LobbyAction::Quit => {
+                self.channels.remove(&channel_id);
+            },
+            LobbyAction::Refresh => {
+                let sessions = self.lobby_sessions();
+                if let Some(channel) = self.channels.get_mut(&channel_id) {
+                    if let Some(lobby) = channel.lobby.as_mut() {
+                        lobby.set_sessions(sessions);
+                    }
+                    channel.render_lobby();
+                }
+            },
+            LobbyAction::Attach(session_name) => {
+                if !session_exists(&session_name).unwrap_or(false) {
+                    let sessions = self.lobby_sessions();
+                    if let Some(lobby) = self
+                        .channels
+                        .get_mut(&channel_id)
+                        .and_then(|channel| channel.lobby.as_mut())
+                    {
+                        lobby.set_sessions(sessions);
+                    }
+                    let message = format!("Session {} is gone", session_name);
+                    self.show_lobby_message(channel_id, message);
+                    return;
+                }
+                match self.approval_timeout {
+                    Some(timeout) => self.ask_to_join(channel_id, session_name, timeout),
+                    None => self.leave_lobby(channel_id, session_name, None, self.client_type),
+                }
+            },
+            LobbyAction::New(layout) => match unique_session_name() {
+                Ok(session_name) => {
+                    self.leave_lobby(channel_id, session_name, Some(layout), self.client_type)
+                },
+                Err(e) => self.show_lobby_message(channel_id, e),
+            },
+        }
+    }
+
+    fn guest(&self) -> Guest {
+        Guest {
+            user: self.audit_record.user.clone(),
+            peer_addr: self.peer_ip.clone(),
+            auth_method: self.audit_record.auth_method.clone(),
+            role: self.role(),
+        }
+    }
+
+    /// Asks the host of `session_name` to let the shell of `channel_id` in, it stays in the
+    /// lobby until they answer
+    fn ask_to_join(
+        &mut self,
+        channel_id: ServerChannelId,
+        session_name: String,
+        timeout: Duration,
+    ) {
+        if let Some(channel) = self.channels.get_mut(&channel_id) {
+            channel.awaiting_approval = true;
+        }
+        let message = format!("Waiting for the host of {} to let you in", session_name);
+        self.show_lobby_message(channel_id, message);
+        log::info!(
+            "Asking the host of {} to let {} in",
+            session_name,
+            self.client
+        );
+        let guest = self.guest();
+        let approvals_tx = self.approvals_tx.clone();
+        tokio::spawn(async move {
+            let approval = ask_host(&session_name, &guest, timeout).await;
+            let _ = approvals_tx.send((channel_id, session_name, approval));
+        });
+    }
+
+    fn handle_approval(
+        &mut self,
+        channel_id: ServerChannelId,
+        session_name: String,
+        approval: Approval,
+    ) {
+        match self.channels.get_mut(&channel_id) {
+            Some(channel) if channel.lobby.is_some() => channel.awaiting_approval = false,
+            // the shell left while its host was deciding
+            _ => return,
+        }
+        log::info!(
+            "The host of {} answered {:?} to {}",
+            session_name,
+            approval,
+            self.client
+        );
+        // only this shell joins as what the host let it in as, the other ones ask on their own
+        match approval {
+            Approval::Allow => self.leave_lobby(channel_id, session_name, None, self.client_type),
+            Approval::ViewOnly => {
+                self.leave_lobby(channel_id, session_name, None, ClientType::Reader)
+            },
+            Approval::Deny => {
+                let message = format!("The host of {} didn't let you in", session_name);
+                self.show_lobby_message(channel_id, message);
+            },
+        }
+    }
+
+    /// The ssh login the session knows the clients of this connection by
+    fn remote_client(&self) -> RemoteClient {
+        RemoteClient {
+            user: self.audit_record.user.clone(),
+            peer_addr: self.audit_record.peer_addr.clone(),
+        }
+    }
+
+    /// Attaches the shell of `channel_id` to `session_name` as `client_type`, a new session
+    /// started with `layout` when it is given
+    fn leave_lobby(
+        &mut self,
+        channel_id: ServerChannelId,
+        session_name: String,
+        layout: Option<String>,
+        client_type: ClientType,
+    ) {
+        // the slot is only taken once the channel is known to still be in the lobby
+        match self.channels.get(&channel_id) {
+            Some(channel) if channel.lobby.is_some() && channel.output.is_some() => {},
+            _ => return,
+        }
+        let session_slot = match self.limits.join_session(&session_name) {
+            Ok(session_slot) => session_slot,
+            Err(rejection) => {
+                log::warn!("Rejected {}: {}", self.client, rejection);
+                self.show_lobby_message(channel_id, rejection.to_string());
+                return;
+            },
+        };
+        let display_name = match self.channels.get_mut(&channel_id) {
+            Some(channel) => {
+                if let (Some(lobby), Some(output)) = (channel.lobby.take(), &channel.output) {
+                    output.push(LEAVE_LOBBY);
+                    lobby.display_name()
+                } else {
+                    return;
+                }
+            },
+            None => return,
+        };
+        log::info!(
+            "{} picked session {} ({:?}) as {}",
+            self.client,
+            session_name,
+            layout,
+            display_name
+        );
+        self.audit_record.session = Some(session_name.clone());
+        self.audit_record.role = Some(role(client_type));
+        let attachment = self.attach(
+            Some(session_name.clone()),
+            client_type,
+            display_name,
+            Some(session_slot),
+        );
+        if let Some(channel) = self.channels.get_mut(&channel_id) {
+            channel.attachment = Some(attachment);
+        }
+
+        // a session that doesn't exist is created with the layout, like `zellij attach -c`
+        let mut args = self.zellij_cli_args.clone();
+        let create = layout.is_some();
+        if let Some(layout) = layout {
+            args.layout = Some(PathBuf::from(layout));
         }
+        args.command = Some(Command::Sessions(Sessions::Attach {
+            session_name: Some(session_name),
+            create,
+            index: None,
+            options: None,
+            force_run_commands: false,
+        }));
+        self.start_zellij_client(channel_id, args);
+    }
+
+    async fn disconnect(&mut self) {
+        // detaches the zellij clients, which close their channels on the way out
+        self.channels.clear();
+        if let Some(handle) = &self.handle {
+            sleep(DETACH_TIMEOUT).await;
+            let _ = handle
+                .disconnect(
+                    Disconnect::ByApplication,
+                    "The server is shutting down".to_string(),
+                    String::new(),
+                )
+                .await;
+        }
+        log::info!(
+            "Closed connection from {:?} on shutdown",
+            self.audit_record.peer_addr
+        );
     }
 
     async fn handle_handler_event(&mut self, event: HandlerEvent, args: CliArgs) {
         match event {
-            HandlerEvent::Authenticated(handle, tx) => {
+            HandlerEvent::Authenticated(handle, authenticated_user, tx) => {
                 self.handle = Some(handle.0);
 
-                self.zellij_cli_args.command = Some(Command::Sessions(Sessions::Attach {
-                    session_name: envs::get_session_name().ok(),
-                    create: false,
-                    index: None,
-                    options: None,
-                    force_run_commands: false,
-                }));
+                let peer = authenticated_user
+                    .peer_addr
+                    .map(|peer_addr| peer_addr.to_string())
+                    .unwrap_or_else(|| "unknown address".to_string());
+                // a passcode picks the session it was generated for, and the username picks the
+                // session when it is one of its share tokens. Anyone else picks a session in the
+                // lobby, or joins the session this server was started with
+                let (session_name, access, client) = match &authenticated_user.passcode {
+                    Some(passcode) => (
+                        Some(passcode.session_name.clone()),
+                        passcode.access(),
+                        format!("{} (passcode) from {}", authenticated_user.user, peer),
+                    ),
+                    None => match self.share_registry.find(&authenticated_user.user) {
+                        Some((session_name, access)) => (
+                            Some(session_name),
+                            access,
+                            format!("share link from {}", peer),
+                        ),
+                        None => (
+                            None,
+                            ShareAccess::ReadWrite,
+                            format!("{} from {}", authenticated_user.user, peer),
+                        ),
+                    },
+                };
+                self.client = client;
+                if authenticated_user.read_only || access == ShareAccess::ReadOnly {
+                    self.client_type = ClientType::Reader;
+                }
+                self.choose_session = session_name.is_none()
+                    && !self.skip_lobby
+                    && authenticated_user.forced_command.is_none();
+                // clients in the lobby count against the limit of the session they pick once
+                // they picked it
+                let session_name = if self.choose_session {
+                    log::info!(
+                        "{} is picking a session ({:?})",
+                        self.client,
+                        self.client_type
+                    );
+                    None
+                } else {
+                    let session_name = session_name.or_else(|| envs::get_session_name().ok());
+                    log::info!(
+                        "Joining session {:?} ({:?})",
+                        session_name,
+                        self.client_type
+                    );
+                    session_name
+                };
+                self.audit_record.user = authenticated_user.user.clone();
+                self.audit_record.auth_method = Some(authenticated_user.auth_method.clone());
+                self.audit_record.role = Some(self.role());
+                self.audit_record.session = session_name.clone();
+                let mut session_slot = None;
+                if let Some(session_name) = &session_name {
+                    match self.limits.join_session(session_name) {
+                        Ok(slot) => session_slot = Some(slot),
+                        Err(rejection) => {
+                            log::warn!("Rejected {}: {}", self.client, rejection);
+                            let _ = tx.send(false);
+                            return;
+                        },
+                    }
+                }
+                // the client waits at authentication while the host decides
+                if let (Some(session_name), Some(timeout)) = (&session_name, self.approval_timeout)
+                {
+                    log::info!(
+                        "Asking the host of {} to let {} in",
+                        session_name,
+                        self.client
+                    );
+                    let approval = ask_host(session_name, &self.guest(), timeout).await;
+                    log::info!(
+                        "The host of {} answered {:?} to {}",
+                        session_name,
+                        approval,
+                        self.client
+                    );
+                    match approval {
+                        Approval::Allow => {},
+                        Approval::ViewOnly => {
+                            self.client_type = ClientType::Reader;
+                            self.audit_record.role = Some(self.role());
+                        },
+                        Approval::Deny => {
+                            let _ = tx.send(false);
+                            return;
+                        },
+                    }
+                }
+                if !self.choose_session {
+                    self.attachment = Some(self.attach(
+                        session_name.clone(),
+                        self.client_type,
+                        authenticated_user.user.clone(),
+                        session_slot,
+                    ));
+                }
+                self.has_forced_command = authenticated_user.forced_command.is_some();
+
+                self.zellij_cli_args.command =
+                    Some(authenticated_user.forced_command.unwrap_or_else(|| {
+                        Command::Sessions(Sessions::Attach {
+                            session_name,
+                            create: false,
+                            index: None,
+                            options: None,
+                            force_run_commands: false,
+                        })
+                    }));
 
-                let _ = tx.send(());
+                let _ = tx.send(true);
             },
             HandlerEvent::PtyRequest(channel_id, pty_request) => {
-                self.pty_request = Some(pty_request);
-                self.channel_id = Some(channel_id);
-            },
-            HandlerEvent::ShellRequest(channel_id) => {
-                let (sender, mut recv) = unbounded_channel::<ZellijClientData>();
-                let pty_request = self.pty_request.as_ref().unwrap();
-                let win_size = libc::winsize {
-                    ws_row: pty_request.row_height as u16,
-                    ws_col: pty_request.col_width as u16,
-                    ws_xpixel: pty_request.pix_width as u16,
-                    ws_ypixel: pty_request.pix_height as u16,
-                };
-                let handle = self.handle.clone().unwrap();
-                let server_receiver = self.server_receiver.clone();
-                let server_signal_receiver = self.server_signal_receiver.clone();
-                std::thread::spawn(move || {
-                    start_client(
-                        args,
-                        sender,
-                        server_receiver,
-                        server_signal_receiver,
-                        ServerHandle(handle),
-                        channel_id.0,
-                        win_size,
-                    );
-                });
+                self.channels
+                    .entry(channel_id)
+                    .or_insert_with(|| ChannelState::new(self.escape_char))
+                    .pty_request = Some(pty_request);
+            },
+            HandlerEvent::EnvRequest(name, value) => {
+                self.env.insert(name, value);
+            },
+            HandlerEvent::ShellRequest(channel_id, ServerChannel(mut server_channel)) => {
+                if self.handle.is_none() {
+                    return;
+                }
+                let channel = self
+                    .channels
+                    .entry(channel_id)
+                    .or_insert_with(|| ChannelState::new(self.escape_char));
+                if channel.pty_request.is_none() {
+                    log::warn!("Shell requested without a pty on channel {}", channel_id);
+                    let _ = server_channel.close().await;
+                    return;
+                }
+                let (output, mut output_receiver) = output_buffer(OUTPUT_BUFFER_CAPACITY);
+                channel.output = Some(output);
 
-                let handle = self.handle.clone().unwrap();
-                let channel_id = self.channel_id.unwrap().0;
+                let bytes_out = self.bytes_out.clone();
                 tokio::spawn(async move {
+                    // sending waits for the peer to open the window of the channel, meanwhile
+                    // the output piles up in the buffer until the client has to wait too
                     loop {
-                        if let Some(event) = recv.recv().await {
-                            match event {
-                                ZellijClientData::Data(data) => {
-                                    let _ = handle.data(channel_id, CryptoVec::from(data)).await;
+                        tokio::select! {
+                            chunk = output_receiver.next_chunk() => match chunk {
+                                Some(chunk) => {
+                                    bytes_out.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                                    if server_channel.data(&chunk[..]).await.is_err() {
+                                        break;
+                                    }
                                 },
-                                ZellijClientData::Exit => {
-                                    let _ = handle.close(channel_id).await;
+                                None => {
+                                    let _ = server_channel.close().await;
+                                    break;
                                 },
-                            }
+                            },
+                            // input is handled through the handler, waiting here keeps track
+                            // of the window and notices when the channel is gone
+                            msg = server_channel.wait() => if msg.is_none() {
+                                break;
+                            },
                         }
                     }
                 });
+
+                if self.choose_session {
+                    self.open_lobby(channel_id);
+                } else {
+                    self.start_zellij_client(channel_id, args);
+                }
+            },
+            HandlerEvent::ExecRequest(channel_id, command_line) => {
+                let handle = match self.handle.clone() {
+                    Some(handle) => handle,
+                    None => return,
+                };
+                log::info!("Exec request on channel {}: {:?}", channel_id, command_line);
+                let has_forced_command = self.has_forced_command;
+                // clients that are still picking a session run commands against the session
+                // of the server, unless its host has to let them in
+                let target = self.request_attachment().map(|attachment| {
+                    let session_name = attachment
+                        .and_then(|attachment| attachment.session_name.clone())
+                        .or_else(|| envs::get_session_name().ok());
+                    let client_type =
+                        attachment.map_or(self.client_type, |attachment| attachment.client_type);
+                    (session_name, client_type)
+                });
+                let remote = self.remote_client();
+                let bytes_out = self.bytes_out.clone();
+                tokio::spawn(async move {
+                    let output = match target {
+                        // like sshd, the command of the key replaces the requested one, and
+                        // the commands we force are interactive ones
+                        _ if has_forced_command => {
+                            ExecOutput::failure("This key is not allowed to run commands", 1)
+                        },
+                        Err(message) => ExecOutput::failure(message, 1),
+                        Ok((session_name, client_type)) => tokio::task::spawn_blocking(move || {
+                            run_exec_command(
+                                &command_line,
+                                &args,
+                                session_name.as_deref(),
+                                client_type,
+                                remote,
+                            )
+                        })
+                        .await
+                        .unwrap_or_else(|e| ExecOutput::failure(e, 1)),
+                    };
+                    let channel_id = channel_id.0;
+                    bytes_out.fetch_add(
+                        (output.stdout.len() + output.stderr.len()) as u64,
+                        Ordering::Relaxed,
+                    );
+                    if !output.stdout.is_empty() {
+                        let _ = handle
+                            .data(channel_id, CryptoVec::from(output.stdout))
+                            .await;
+                    }
+                    if !output.stderr.is_empty() {
+                        // extended data type 1 is stderr
+                        let _ = handle
+                            .extended_data(channel_id, 1, CryptoVec::from(output.stderr))
+                            .await;
+                    }
+                    let _ = handle
+                        .exit_status_request(channel_id, output.exit_status)
+                        .await;
+                    let _ = handle.eof(channel_id).await;
+                    let _ = handle.close(channel_id).await;
+                });
+            },
+            HandlerEvent::SftpRequest(channel_id, ServerChannel(channel)) => {
+                let attachment = match self.request_attachment() {
+                    Ok(attachment) => attachment,
+                    Err(message) => {
+                        log::warn!("Refused sftp to {}: {}", self.client, message);
+                        let _ = channel.close().await;
+                        return;
+                    },
+                };
+                // the config error isn't Send, only its message is kept past the await
+                let root = sftp_root(self.sftp_root.as_deref(), &args).map_err(|e| e.to_string());
+                let root = match root {
+                    Ok(root) => root,
+                    Err(e) => {
+                        log::error!("Failed to find the directory to serve over sftp: {}", e);
+                        let _ = channel.close().await;
+                        return;
+                    },
+                };
+                let read_only = attachment
+                    .map_or(self.client_type, |attachment| attachment.client_type)
+                    == ClientType::Reader;
+                let session_name =
+                    attachment.and_then(|attachment| attachment.session_name.clone());
+                match SftpSession::new(&root, read_only, session_name) {
+                    Ok(sftp_session) => {
+                        log::info!(
+                            "Serving {:?} over sftp on channel {} (read-only: {})",
+                            root,
+                            channel_id,
+                            read_only
+                        );
+                        russh_sftp::server::run(channel.into_stream(), sftp_session).await;
+                    },
+                    Err(e) => {
+                        log::error!("Failed to serve {:?} over sftp: {}", root, e);
+                        let _ = channel.close().await;
+                    },
+                }
+            },
+            HandlerEvent::DirectTcpip(ServerChannel(channel), target) => {
+                let client_type = self.request_attachment().and_then(|attachment| {
+                    match attachment.map_or(self.client_type, |attachment| attachment.client_type) {
+                        // viewers only watch, they don't get to reach into the host's network
+                        ClientType::Reader => Err("Viewers can't forward ports"),
+                        client_type => Ok(client_type),
+                    }
+                });
+                if let Err(message) = client_type {
+                    log::warn!(
+                        "Refused a forward to {} for {}: {}",
+                        target,
+                        self.client,
+                        message
+                    );
+                    let _ = channel.close().await;
+                    return;
+                }
+                log::info!("{} opened a forward to {}", self.client, target);
+                let forward = self.active_forwards.open(&self.client, &target);
+                let bytes_in = self.bytes_in.clone();
+                let bytes_out = self.bytes_out.clone();
+                tokio::spawn(async move {
+                    match TcpStream::connect((target.host.as_str(), target.port)).await {
+                        Ok(mut stream) => {
+                            let mut channel_stream = channel.into_stream();
+                            if let Ok((sent, received)) =
+                                copy_bidirectional(&mut channel_stream, &mut stream).await
+                            {
+                                bytes_in.fetch_add(sent, Ordering::Relaxed);
+                                bytes_out.fetch_add(received, Ordering::Relaxed);
+                            }
+                        },
+                        Err(e) => {
+                            log::warn!("Failed to connect to {}: {}", target, e);
+                            let _ = channel.close().await;
+                        },
+                    }
+                    drop(forward);
+                });
+            },
+            HandlerEvent::Data(channel_id, data) => {
+                let channel = match self.channels.get_mut(&channel_id) {
+                    Some(channel) if !channel.input_closed => channel,
+                    _ => {
+                        log::debug!("Dropping data for closed or unknown channel {}", channel_id);
+                        return;
+                    },
+                };
+                self.bytes_in
+                    .fetch_add(data.len() as u64, Ordering::Relaxed);
+                let mut commands = vec![];
+                let mut lobby_actions = vec![];
+                for escaped in channel.escape.feed(&data) {
+                    match escaped {
+                        Escaped::Input(input) => match channel.lobby.as_mut() {
+                            Some(lobby) => lobby_actions.extend(lobby.feed(&input)),
+                            None => {
+                                let _ = channel.input_sender.send(input);
+                            },
+                        },
+                        Escaped::Command(command) => commands.push(command),
+                    }
+                }
+                channel.render_lobby();
+                for action in lobby_actions {
+                    self.run_lobby_action(channel_id, action);
+                }
+                for command in commands {
+                    self.run_escape_command(channel_id, command);
+                }
+            },
+            HandlerEvent::WindowChangeRequest(channel_id, win_size) => {
+                if let Some(channel) = self.channels.get_mut(&channel_id) {
+                    if let Some(pty_request) = channel.pty_request.as_mut() {
+                        pty_request.col_width = win_size.ws_col as u32;
+                        pty_request.row_height = win_size.ws_row as u32;
+                        pty_request.pix_width = win_size.ws_xpixel as u32;
+                        pty_request.pix_height = win_size.ws_ypixel as u32;
+                    }
+                    channel.render_lobby();
+                    let _ = channel
+                        .signal_sender
+                        .send(ClientSignal::WindowChange(win_size));
+                }
+            },
+            HandlerEvent::Signal(channel_id, signal) => {
+                if let Some(channel) = self.channels.get(&channel_id) {
+                    let _ = channel.signal_sender.send(ClientSignal::Signal(signal));
+                }
             },
-            HandlerEvent::Data(_channel_id, data) => {
-                let _ = self.server_sender.send(data);
+            HandlerEvent::ChannelClose(channel_id) => {
+                if self.channels.remove(&channel_id).is_some() {
+                    log::debug!("Channel {} closed", channel_id);
+                }
             },
-            HandlerEvent::WindowChangeRequest(_, _winsize) => {},
-            HandlerEvent::Signal(_, signal) => {
-                let _ = self.server_signal_sender.send(signal);
+            HandlerEvent::ChannelEof(channel_id) => {
+                // EOF only half-closes the channel: output keeps flowing, e.g. for
+                // `ssh host < /dev/null`, until the client closes it
+                if let Some(channel) = self.channels.get_mut(&channel_id) {
+                    channel.input_closed = true;
+                    log::debug!("Channel {} reached its end of input", channel_id);
+                }
             },
         }
     }
 }
+
+fn role(client_type: ClientType) -> ShareRole {
+    match client_type {
+        ClientType::Reader => ShareRole::Viewer,
+        ClientType::Writer => ShareRole::Collaborator,
+    }
+}
+
+/// Draws `lines` in reverse video over the bottom rows of a terminal `rows` high, leaving the
+/// cursor where it was
+fn render_notice(lines: &[String], rows: u32) -> Vec<u8> {
+    let first_row = (rows as usize).saturating_sub(lines.len()) + 1;
+    let mut notice = String::from("\u{1b}7");
+    for (i, line) in lines.iter().enumerate() {
+        notice.push_str(&format!(
+            "\u{1b}[{};1H\u{1b}[0;1;7m {} \u{1b}[0m\u{1b}[K",
+            first_row + i,
+            line
+        ));
+    }
+    notice.push_str("\u{1b}8");
+    notice.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+    use zellij_utils::{ssh::Ssh, tempfile::tempdir};
+
+    fn session(data_dir: &Path, approval_timeout: Option<Duration>) -> Session {
+        let (_shutdown_tx, shutdown) = watch::channel(None);
+        let (_tx, rx) = unbounded_channel();
+        Session::new(
+            CliArgs::default(),
+            ShareRegistry::new(Some(data_dir)).unwrap(),
+            None,
+            ActiveForwards::new(Some(data_dir)).unwrap(),
+            None,
+            None,
+            false,
+            approval_timeout,
+            ConnectionLimits::new(&Ssh::default()),
+            ClientRegistry::default(),
+            AuditLog::new(Some(data_dir)).unwrap(),
+            shutdown,
+            rx,
+        )
+    }
+
+    #[test]
+    fn unapproved_lobby_guests_cannot_exec_use_sftp_or_forward_ports() {
+        let data_dir = tempdir().unwrap();
+        // exec, sftp and direct-tcpip requests all go through the same check
+        let mut guest = session(data_dir.path(), Some(Duration::from_secs(60)));
+        guest.choose_session = true;
+        assert!(guest.request_attachment().is_err());
+
+        guest.attachment = Some(guest.attach(
+            Some("pairing".to_string()),
+            ClientType::Reader,
+            "alice".to_string(),
+            None,
+        ));
+        let attachment = guest.request_attachment().unwrap().unwrap();
+        assert_eq!(attachment.session_name.as_deref(), Some("pairing"));
+        assert_eq!(attachment.client_type, ClientType::Reader);
+
+        let mut unchecked_guest = session(data_dir.path(), None);
+        unchecked_guest.choose_session = true;
+        assert!(
+            matches!(unchecked_guest.request_attachment(), Ok(None)),
+            "without approvals the requests act on the session of the server"
+        );
+    }
+}
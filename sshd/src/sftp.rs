@@ -0,0 +1,485 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions, Permissions},
+    io,
+    os::unix::fs::{FileExt, OpenOptionsExt, PermissionsExt},
+    path::{Component, Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use russh_sftp::protocol::{
+    Attrs, Data, File, FileAttributes, Handle, Name, OpenFlags, Status, StatusCode, Version,
+};
+use zellij_utils::{
+    cli::CliArgs,
+    input::config::{Config, ConfigError},
+};
+
+use crate::exec::notify_session;
+
+/// Largest chunk sent back for a single read, clients usually ask for 32KiB
+const MAX_READ_LENGTH: u32 = 256 * 1024;
+
+enum OpenHandle {
+    File {
+        file: fs::File,
+        path: PathBuf,
+        // the path as the client sees it, used in notifications
+        name: String,
+        writable: bool,
+        uploaded: u64,
+        downloaded: u64,
+    },
+    Dir {
+        path: PathBuf,
+        listed: bool,
+    },
+}
+
+/// The directory served over sftp: the `sftp_root` ssh option, falling back to the
+/// `default_cwd` option and then to the directory zellij was started in. A broken config file
+/// is an error rather than a reason to serve another directory
+pub(crate) fn sftp_root(configured: Option<&Path>, args: &CliArgs) -> Result<PathBuf, ConfigError> {
+    if let Some(root) = configured {
+        return Ok(root.to_path_buf());
+    }
+    let default_cwd = Config::try_from(args)?.options.default_cwd;
+    Ok(default_cwd
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_else(|| PathBuf::from(".")))
+}
+
+/// An sftp server confined to `root`, which the client sees as `/`.
+///
+/// Read-only viewers can list and download files but not change anything. Finished transfers
+/// are announced to everyone in the session.
+pub(crate) struct SftpSession {
+    root: PathBuf,
+    read_only: bool,
+    session_name: Option<String>,
+    handles: HashMap<String, OpenHandle>,
+    next_handle: u64,
+}
+
+impl SftpSession {
+    pub fn new(root: &Path, read_only: bool, session_name: Option<String>) -> io::Result<Self> {
+        Ok(SftpSession {
+            root: root.canonicalize()?,
+            read_only,
+            session_name,
+            handles: HashMap::new(),
+            next_handle: 0,
+        })
+    }
+
+    /// Maps a path sent by the client to the file it names under the root, refusing paths
+    /// that lead out of it through symlinks
+    fn resolve(&self, path: &str) -> Result<PathBuf, StatusCode> {
+        let relative = virtual_path(path);
+        let path = self
+            .root
+            .join(relative.strip_prefix("/").unwrap_or(&relative));
+        // new files don't exist yet, their closest existing parent is checked instead
+        let existing = path
+            .ancestors()
+            .find(|ancestor| ancestor.symlink_metadata().is_ok())
+            .unwrap_or(&self.root);
+        match existing.canonicalize() {
+            Ok(canonical) if canonical.starts_with(&self.root) => Ok(path),
+            Ok(_) => Err(StatusCode::PermissionDenied),
+            Err(e) => Err(io_status(e)),
+        }
+    }
+
+    fn ensure_writable(&self) -> Result<(), StatusCode> {
+        if self.read_only {
+            Err(StatusCode::PermissionDenied)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn open_file(&self, filename: &str, pflags: OpenFlags) -> Result<OpenHandle, StatusCode> {
+        let writable = pflags.intersects(
+            OpenFlags::WRITE | OpenFlags::APPEND | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+        );
+        if writable {
+            self.ensure_writable()?;
+        }
+        let path = self.resolve(filename)?;
+        let file = OpenOptions::new()
+            .read(pflags.contains(OpenFlags::READ) || !writable)
+            .write(pflags.contains(OpenFlags::WRITE))
+            .append(pflags.contains(OpenFlags::APPEND))
+            .create(pflags.contains(OpenFlags::CREATE))
+            .truncate(pflags.contains(OpenFlags::TRUNCATE))
+            .create_new(pflags.contains(OpenFlags::CREATE | OpenFlags::EXCLUDE))
+            .mode(0o644)
+            .open(&path)
+            .map_err(io_status)?;
+        Ok(OpenHandle::File {
+            file,
+            path,
+            name: virtual_path(filename).to_string_lossy().to_string(),
+            writable,
+            uploaded: 0,
+            downloaded: 0,
+        })
+    }
+
+    fn insert_handle(&mut self, handle: OpenHandle) -> String {
+        let id = self.next_handle.to_string();
+        self.next_handle += 1;
+        self.handles.insert(id.clone(), handle);
+        id
+    }
+
+    fn handle_path(&self, handle: &str) -> Result<&Path, StatusCode> {
+        match self.handles.get(handle) {
+            Some(OpenHandle::File { path, .. }) | Some(OpenHandle::Dir { path, .. }) => {
+                Ok(path.as_path())
+            },
+            None => Err(StatusCode::Failure),
+        }
+    }
+
+    fn notify(&self, message: String) {
+        log::info!("{}", message);
+        if let Some(session_name) = self.session_name.clone() {
+            tokio::task::spawn_blocking(move || notify_session(&session_name, message));
+        }
+    }
+}
+
+#[async_trait]
+impl russh_sftp::server::Handler for SftpSession {
+    type Error = StatusCode;
+
+    fn unimplemented(&self) -> Self::Error {
+        StatusCode::OpUnsupported
+    }
+
+    async fn init(
+        &mut self,
+        _version: u32,
+        _extensions: HashMap<String, String>,
+    ) -> Result<Version, Self::Error> {
+        Ok(Version::new())
+    }
+
+    async fn open(
+        &mut self,
+        id: u32,
+        filename: String,
+        pflags: OpenFlags,
+        _attrs: FileAttributes,
+    ) -> Result<Handle, Self::Error> {
+        let handle = self.open_file(&filename, pflags)?;
+        Ok(Handle {
+            id,
+            handle: self.insert_handle(handle),
+        })
+    }
+
+    async fn close(&mut self, id: u32, handle: String) -> Result<Status, Self::Error> {
+        match self.handles.remove(&handle) {
+            Some(OpenHandle::File {
+                name,
+                uploaded,
+                downloaded,
+                ..
+            }) => {
+                if uploaded > 0 {
+                    self.notify(format!(
+                        "{} was uploaded over ssh ({} bytes)",
+                        name, uploaded
+                    ));
+                }
+                if downloaded > 0 {
+                    self.notify(format!("{} was downloaded over ssh", name));
+                }
+                Ok(ok_status(id))
+            },
+            Some(OpenHandle::Dir { .. }) => Ok(ok_status(id)),
+            None => Err(StatusCode::Failure),
+        }
+    }
+
+    async fn read(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        len: u32,
+    ) -> Result<Data, Self::Error> {
+        match self.handles.get_mut(&handle) {
+            Some(OpenHandle::File {
+                file, downloaded, ..
+            }) => {
+                let mut data = vec![0; len.min(MAX_READ_LENGTH) as usize];
+                let read = file.read_at(&mut data, offset).map_err(io_status)?;
+                if read == 0 {
+                    return Err(StatusCode::Eof);
+                }
+                data.truncate(read);
+                *downloaded += read as u64;
+                Ok(Data { id, data })
+            },
+            _ => Err(StatusCode::Failure),
+        }
+    }
+
+    async fn write(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> Result<Status, Self::Error> {
+        match self.handles.get_mut(&handle) {
+            Some(OpenHandle::File {
+                file,
+                writable: true,
+                uploaded,
+                ..
+            }) => {
+                file.write_all_at(&data, offset).map_err(io_status)?;
+                *uploaded += data.len() as u64;
+                Ok(ok_status(id))
+            },
+            Some(OpenHandle::File { .. }) => Err(StatusCode::PermissionDenied),
+            _ => Err(StatusCode::Failure),
+        }
+    }
+
+    async fn lstat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let metadata = fs::symlink_metadata(self.resolve(&path)?).map_err(io_status)?;
+        Ok(Attrs {
+            id,
+            attrs: FileAttributes::from(&metadata),
+        })
+    }
+
+    async fn fstat(&mut self, id: u32, handle: String) -> Result<Attrs, Self::Error> {
+        let metadata = fs::metadata(self.handle_path(&handle)?).map_err(io_status)?;
+        Ok(Attrs {
+            id,
+            attrs: FileAttributes::from(&metadata),
+        })
+    }
+
+    async fn setstat(
+        &mut self,
+        id: u32,
+        path: String,
+        attrs: FileAttributes,
+    ) -> Result<Status, Self::Error> {
+        self.ensure_writable()?;
+        set_permissions(&self.resolve(&path)?, &attrs)?;
+        Ok(ok_status(id))
+    }
+
+    async fn fsetstat(
+        &mut self,
+        id: u32,
+        handle: String,
+        attrs: FileAttributes,
+    ) -> Result<Status, Self::Error> {
+        self.ensure_writable()?;
+        set_permissions(self.handle_path(&handle)?, &attrs)?;
+        Ok(ok_status(id))
+    }
+
+    async fn opendir(&mut self, id: u32, path: String) -> Result<Handle, Self::Error> {
+        let path = self.resolve(&path)?;
+        if !path.is_dir() {
+            return Err(StatusCode::NoSuchFile);
+        }
+        Ok(Handle {
+            id,
+            handle: self.insert_handle(OpenHandle::Dir {
+                path,
+                listed: false,
+            }),
+        })
+    }
+
+    async fn readdir(&mut self, id: u32, handle: String) -> Result<Name, Self::Error> {
+        match self.handles.get_mut(&handle) {
+            // the whole directory is sent at once, the next read ends the listing
+            Some(OpenHandle::Dir { listed: true, .. }) => Err(StatusCode::Eof),
+            Some(OpenHandle::Dir { path, listed }) => {
+                *listed = true;
+                let files = fs::read_dir(path)
+                    .map_err(io_status)?
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        let metadata = entry.metadata().ok()?;
+                        Some(File {
+                            filename: entry.file_name().to_string_lossy().into_owned(),
+                            attrs: FileAttributes::from(&metadata),
+                        })
+                    })
+                    .collect();
+                Ok(Name { id, files })
+            },
+            _ => Err(StatusCode::Failure),
+        }
+    }
+
+    async fn remove(&mut self, id: u32, filename: String) -> Result<Status, Self::Error> {
+        self.ensure_writable()?;
+        fs::remove_file(self.resolve(&filename)?).map_err(io_status)?;
+        Ok(ok_status(id))
+    }
+
+    async fn mkdir(
+        &mut self,
+        id: u32,
+        path: String,
+        _attrs: FileAttributes,
+    ) -> Result<Status, Self::Error> {
+        self.ensure_writable()?;
+        fs::create_dir(self.resolve(&path)?).map_err(io_status)?;
+        Ok(ok_status(id))
+    }
+
+    async fn rmdir(&mut self, id: u32, path: String) -> Result<Status, Self::Error> {
+        self.ensure_writable()?;
+        fs::remove_dir(self.resolve(&path)?).map_err(io_status)?;
+        Ok(ok_status(id))
+    }
+
+    async fn realpath(&mut self, id: u32, path: String) -> Result<Name, Self::Error> {
+        Ok(Name {
+            id,
+            files: vec![File {
+                filename: virtual_path(&path).to_string_lossy().into_owned(),
+                attrs: FileAttributes::default(),
+            }],
+        })
+    }
+
+    async fn stat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let metadata = fs::metadata(self.resolve(&path)?).map_err(io_status)?;
+        Ok(Attrs {
+            id,
+            attrs: FileAttributes::from(&metadata),
+        })
+    }
+
+    async fn rename(
+        &mut self,
+        id: u32,
+        oldpath: String,
+        newpath: String,
+    ) -> Result<Status, Self::Error> {
+        self.ensure_writable()?;
+        fs::rename(self.resolve(&oldpath)?, self.resolve(&newpath)?).map_err(io_status)?;
+        Ok(ok_status(id))
+    }
+}
+
+/// The absolute path the client means by `path`, with `.` and `..` resolved without ever
+/// going above `/`
+fn virtual_path(path: &str) -> PathBuf {
+    let mut virtual_path = PathBuf::from("/");
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => virtual_path.push(name),
+            Component::ParentDir => {
+                virtual_path.pop();
+            },
+            _ => {},
+        }
+    }
+    virtual_path
+}
+
+fn set_permissions(path: &Path, attrs: &FileAttributes) -> Result<(), StatusCode> {
+    // only the permission bits are applied, ownership and times stay as they are
+    if let Some(permissions) = attrs.permissions {
+        fs::set_permissions(path, Permissions::from_mode(permissions & 0o777))
+            .map_err(io_status)?;
+    }
+    Ok(())
+}
+
+fn ok_status(id: u32) -> Status {
+    Status {
+        id,
+        status_code: StatusCode::Ok,
+        error_message: "Ok".to_string(),
+        language_tag: "en-US".to_string(),
+    }
+}
+
+fn io_status(error: io::Error) -> StatusCode {
+    match error.kind() {
+        io::ErrorKind::NotFound => StatusCode::NoSuchFile,
+        io::ErrorKind::PermissionDenied => StatusCode::PermissionDenied,
+        _ => StatusCode::Failure,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zellij_utils::tempfile::tempdir;
+
+    #[test]
+    fn paths_stay_under_the_root() {
+        assert_eq!(virtual_path("."), PathBuf::from("/"));
+        assert_eq!(
+            virtual_path("notes/../../etc/passwd"),
+            PathBuf::from("/etc/passwd")
+        );
+        assert_eq!(
+            virtual_path("/notes/./todo.md"),
+            PathBuf::from("/notes/todo.md")
+        );
+
+        let root = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.path().join("escape")).unwrap();
+        let sftp = SftpSession::new(root.path(), false, None).unwrap();
+        assert_eq!(
+            sftp.resolve("../etc/passwd").unwrap(),
+            sftp.root.join("etc/passwd")
+        );
+        assert_eq!(
+            sftp.resolve("escape/secret").err(),
+            Some(StatusCode::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn read_only_viewers_can_only_download() {
+        let root = tempdir().unwrap();
+        fs::write(root.path().join("notes.txt"), "shared notes").unwrap();
+        let sftp = SftpSession::new(root.path(), true, None).unwrap();
+        assert!(sftp.open_file("notes.txt", OpenFlags::READ).is_ok());
+        assert_eq!(
+            sftp.open_file("upload.txt", OpenFlags::WRITE | OpenFlags::CREATE)
+                .err(),
+            Some(StatusCode::PermissionDenied)
+        );
+        assert!(!root.path().join("upload.txt").exists());
+    }
+
+    #[test]
+    fn broken_config_serves_no_directory() {
+        let config_dir = tempdir().unwrap();
+        let config = config_dir.path().join("config.kdl");
+        fs::write(&config, "keybinds {").unwrap();
+        let args = CliArgs {
+            config: Some(config),
+            ..Default::default()
+        };
+        assert!(sftp_root(None, &args).is_err());
+        assert_eq!(
+            sftp_root(Some(config_dir.path()), &args).unwrap(),
+            config_dir.path()
+        );
+    }
+}
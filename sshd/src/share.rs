@@ -0,0 +1,397 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::{MetadataExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use zellij_utils::{consts::ZELLIJ_SOCK_DIR, nix, shared::set_permissions};
+
+use crate::host_keys::create_ssh_data_dir;
+
+const TOKEN_LENGTH: usize = 26;
+/// Appended to a username (`alice+ro`) or token to join read-only
+const READ_ONLY_SUFFIX: &str = "+ro";
+
+/// What a share token lets its holder do in the session it belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShareAccess {
+    ReadWrite,
+    ReadOnly,
+}
+
+/// The pair of tmate-style tokens of a session, used as ssh username to join it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShareTokens {
+    pub read_write: String,
+    pub read_only: String,
+}
+
+impl ShareTokens {
+    fn generate() -> Self {
+        ShareTokens {
+            read_write: generate_token(),
+            read_only: generate_token(),
+        }
+    }
+
+    fn access_for(&self, token: &str) -> Option<ShareAccess> {
+        if constant_time_eq(token, &self.read_write) {
+            Some(ShareAccess::ReadWrite)
+        } else if constant_time_eq(token, &self.read_only) {
+            Some(ShareAccess::ReadOnly)
+        } else {
+            None
+        }
+    }
+
+    /// The tokens stored in `raw` and the run of the session they were made for
+    fn decode(raw: &str) -> Option<(String, Self)> {
+        let mut instance = None;
+        let mut read_write = None;
+        let mut read_only = None;
+        for line in raw.lines() {
+            match line.split_once(' ') {
+                Some(("session", id)) => instance = Some(id.trim().to_string()),
+                Some(("rw", token)) => read_write = Some(token.trim().to_string()),
+                Some(("ro", token)) => read_only = Some(token.trim().to_string()),
+                _ => {},
+            }
+        }
+        Some((
+            instance?,
+            ShareTokens {
+                read_write: read_write?,
+                read_only: read_only?,
+            },
+        ))
+    }
+
+    fn encode(&self, instance: &str) -> String {
+        format!(
+            "session {}\nrw {}\nro {}\n",
+            instance, self.read_write, self.read_only
+        )
+    }
+}
+
+/// Share tokens of all sessions on this machine, stored under `ssh/shares` in the data dir.
+///
+/// Tokens are created the first time they are asked for and kept on disk, so a running
+/// `zellij ssh` daemon can route tokens printed by another zellij process. They belong to a
+/// single run of a session: once it ends they stop working, and a session started later under
+/// the same name gets new ones.
+#[derive(Clone, Debug)]
+pub struct ShareRegistry {
+    dir: PathBuf,
+    sock_dir: PathBuf,
+}
+
+impl ShareRegistry {
+    pub fn new(data_dir: Option<&Path>) -> io::Result<Self> {
+        Self::with_sock_dir(data_dir, ZELLIJ_SOCK_DIR.clone())
+    }
+
+    fn with_sock_dir(data_dir: Option<&Path>, sock_dir: PathBuf) -> io::Result<Self> {
+        let dir = create_ssh_data_dir(data_dir)?.join("shares");
+        fs::create_dir_all(&dir)?;
+        set_permissions(&dir, 0o700)?;
+        Ok(ShareRegistry { dir, sock_dir })
+    }
+
+    /// Returns the tokens of the running session `session_name`, generating them if it has
+    /// none yet
+    pub fn tokens_for(&self, session_name: &str) -> io::Result<ShareTokens> {
+        if let Some(tokens) = self.stored_tokens(session_name) {
+            return Ok(tokens);
+        }
+        let instance = self.session_instance(session_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no running session named {:?}", session_name),
+            )
+        })?;
+        self.remove_stale_tokens();
+
+        // the tokens are written to a file of their own and linked into place, which fails
+        // rather than replacing the tokens of a concurrent caller that got there first
+        let tokens = ShareTokens::generate();
+        let path = self.dir.join(session_name);
+        let temp_path = self
+            .dir
+            .join(format!(".{}.{}.tmp", session_name, generate_token()));
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&temp_path)?;
+        let stored = file
+            .write_all(tokens.encode(&instance).as_bytes())
+            .and_then(|_| file.sync_all())
+            .and_then(|_| fs::hard_link(&temp_path, &path));
+        let _ = fs::remove_file(&temp_path);
+        match stored {
+            Ok(()) => Ok(tokens),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                self.stored_tokens(session_name).ok_or(e)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Finds the running session `token` belongs to, without changing anything on disk
+    pub fn find(&self, token: &str) -> Option<(String, ShareAccess)> {
+        if token.len() != TOKEN_LENGTH {
+            return None;
+        }
+        fs::read_dir(&self.dir)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .find_map(|session_name| {
+                let access = self.stored_tokens(&session_name)?.access_for(token)?;
+                Some((session_name, access))
+            })
+    }
+
+    /// Removes the tokens of the sessions that ended
+    pub fn remove_stale_tokens(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let file_name = match entry.file_name().into_string() {
+                Ok(file_name) => file_name,
+                Err(_) => continue,
+            };
+            // tokens still being written
+            if file_name.starts_with('.') {
+                continue;
+            }
+            if self.stored_tokens(&file_name).is_none() {
+                log::info!("Removing the share tokens of ended session {}", file_name);
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    // the tokens of a session that ended, or of an earlier session by the same name, are stale
+    fn stored_tokens(&self, session_name: &str) -> Option<ShareTokens> {
+        let raw = fs::read_to_string(self.dir.join(session_name)).ok()?;
+        let (instance, tokens) = ShareTokens::decode(&raw)?;
+        if self.session_instance(session_name)? == instance {
+            Some(tokens)
+        } else {
+            None
+        }
+    }
+
+    // tells runs of a session apart by the socket each of them creates
+    fn session_instance(&self, session_name: &str) -> Option<String> {
+        let metadata = fs::metadata(self.sock_dir.join(session_name)).ok()?;
+        Some(format!(
+            "{}.{}.{}",
+            metadata.ino(),
+            metadata.mtime(),
+            metadata.mtime_nsec()
+        ))
+    }
+}
+
+/// Prints the ssh commands that join `session_names` over the server listening on `port`
+pub fn print_connect_strings(registry: &ShareRegistry, session_names: &[String], port: u16) {
+    let host = hostname().unwrap_or_else(|| "localhost".to_string());
+    for session_name in session_names {
+        match registry.tokens_for(session_name) {
+            Ok(tokens) => {
+                println!("Session \u{1b}[32;1m{}\u{1b}[m:", session_name);
+                println!(
+                    "  read-write: ssh -p {} {}@{}",
+                    port, tokens.read_write, host
+                );
+                println!(
+                    "  read-only:  ssh -p {} {}@{}",
+                    port, tokens.read_only, host
+                );
+            },
+            Err(e) => eprintln!(
+                "Failed to create share tokens for session {}: {}",
+                session_name, e
+            ),
+        }
+    }
+}
+
+/// Prints the ssh commands that join `session_names` through the relay at `relay`, which
+/// routes `relay_id` to this machine
+pub fn print_relay_connect_strings(
+    registry: &ShareRegistry,
+    session_names: &[String],
+    relay: &str,
+    relay_id: &str,
+) {
+    let host = hostname().unwrap_or_else(|| "localhost".to_string());
+    for session_name in session_names {
+        match registry.tokens_for(session_name) {
+            Ok(tokens) => {
+                println!(
+                    "Session \u{1b}[32;1m{}\u{1b}[m through {}:",
+                    session_name, relay
+                );
+                println!(
+                    "  read-write: ssh -J {}@{} {}@{}",
+                    relay_id, relay, tokens.read_write, host
+                );
+                println!(
+                    "  read-only:  ssh -J {}@{} {}@{}",
+                    relay_id, relay, tokens.read_only, host
+                );
+            },
+            Err(e) => eprintln!(
+                "Failed to create share tokens for session {}: {}",
+                session_name, e
+            ),
+        }
+    }
+}
+
+/// Splits the read-only suffix off `user`, returning the bare username and whether it was there
+pub fn strip_read_only_suffix(user: &str) -> (&str, bool) {
+    match user.strip_suffix(READ_ONLY_SUFFIX) {
+        Some(user) => (user, true),
+        None => (user, false),
+    }
+}
+
+pub(crate) fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    nix::unistd::gethostname(&mut buf)
+        .ok()
+        .and_then(|name| name.to_str().ok())
+        .map(|name| name.to_string())
+}
+
+pub(crate) fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+// compares without bailing out on the first differing byte, so the time taken doesn't leak
+// how much of a guessed token is right
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zellij_utils::tempfile::tempdir;
+
+    fn start_session(sock_dir: &Path, session_name: &str) {
+        fs::write(sock_dir.join(session_name), "").unwrap();
+    }
+
+    #[test]
+    fn tokens_are_kept_per_session() {
+        let data_dir = tempdir().unwrap();
+        let sock_dir = tempdir().unwrap();
+        let registry =
+            ShareRegistry::with_sock_dir(Some(data_dir.path()), sock_dir.path().to_path_buf())
+                .unwrap();
+        start_session(sock_dir.path(), "first-session");
+        start_session(sock_dir.path(), "second-session");
+        let first = registry.tokens_for("first-session").unwrap();
+        let second = registry.tokens_for("second-session").unwrap();
+        assert_ne!(first, second);
+        assert_ne!(first.read_write, first.read_only);
+        assert_eq!(first.read_write.len(), TOKEN_LENGTH);
+        assert_eq!(registry.tokens_for("first-session").unwrap(), first);
+        assert!(registry.tokens_for("no-such-session").is_err());
+    }
+
+    #[test]
+    fn tokens_end_with_their_session() {
+        let data_dir = tempdir().unwrap();
+        let sock_dir = tempdir().unwrap();
+        let registry =
+            ShareRegistry::with_sock_dir(Some(data_dir.path()), sock_dir.path().to_path_buf())
+                .unwrap();
+        start_session(sock_dir.path(), "pairing");
+        let tokens = registry.tokens_for("pairing").unwrap();
+        assert_eq!(
+            registry.find(&tokens.read_only),
+            Some(("pairing".to_string(), ShareAccess::ReadOnly))
+        );
+
+        fs::remove_file(sock_dir.path().join("pairing")).unwrap();
+        assert_eq!(registry.find(&tokens.read_write), None);
+        // a new session by the same name doesn't inherit the tokens
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        start_session(sock_dir.path(), "pairing");
+        assert_eq!(registry.find(&tokens.read_write), None);
+        assert_ne!(registry.tokens_for("pairing").unwrap(), tokens);
+    }
+
+    #[test]
+    fn finding_tokens_writes_nothing() {
+        let data_dir = tempdir().unwrap();
+        let sock_dir = tempdir().unwrap();
+        let registry =
+            ShareRegistry::with_sock_dir(Some(data_dir.path()), sock_dir.path().to_path_buf())
+                .unwrap();
+        start_session(sock_dir.path(), "pairing");
+        assert_eq!(registry.find(&generate_token()), None);
+        assert_eq!(fs::read_dir(&registry.dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn stale_tokens_are_removed() {
+        let data_dir = tempdir().unwrap();
+        let sock_dir = tempdir().unwrap();
+        let registry =
+            ShareRegistry::with_sock_dir(Some(data_dir.path()), sock_dir.path().to_path_buf())
+                .unwrap();
+        start_session(sock_dir.path(), "pairing");
+        start_session(sock_dir.path(), "other");
+        registry.tokens_for("pairing").unwrap();
+        registry.tokens_for("other").unwrap();
+
+        fs::remove_file(sock_dir.path().join("pairing")).unwrap();
+        registry.remove_stale_tokens();
+        assert!(!registry.dir.join("pairing").exists());
+        assert!(registry.dir.join("other").exists());
+    }
+
+    #[test]
+    fn token_selects_access() {
+        let tokens = ShareTokens::generate();
+        assert_eq!(
+            tokens.access_for(&tokens.read_write),
+            Some(ShareAccess::ReadWrite)
+        );
+        assert_eq!(
+            tokens.access_for(&tokens.read_only),
+            Some(ShareAccess::ReadOnly)
+        );
+        assert_eq!(tokens.access_for("alice"), None);
+        assert_eq!(
+            ShareTokens::decode(&tokens.encode("1.2.3")),
+            Some(("1.2.3".to_string(), tokens))
+        );
+    }
+
+    #[test]
+    fn read_only_suffix_is_stripped() {
+        assert_eq!(strip_read_only_suffix("alice+ro"), ("alice", true));
+        assert_eq!(strip_read_only_suffix("alice"), ("alice", false));
+        assert_eq!(strip_read_only_suffix("+ro"), ("", true));
+    }
+}
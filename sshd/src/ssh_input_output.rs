@@ -3,24 +3,29 @@ use interprocess::local_socket::LocalSocketStream;
 
 use russh::{ChannelId, Sig};
 
+use std::collections::BTreeMap;
 use std::os::unix::io::RawFd;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::{io, time};
-use tokio::sync::mpsc::UnboundedSender;
+use std::{io, thread, time};
 use zellij_client::os_input_output::{ClientOsApi, StdinPoller};
 use zellij_utils::{
     anyhow::{Context, Result},
     data::Palette,
     errors::ErrorContext,
     interprocess,
-    ipc::{ClientToServerMsg, IpcReceiverWithContext, IpcSenderWithContext, ServerToClientMsg},
+    ipc::{
+        ClientToServerMsg, IpcReceiverWithContext, IpcSenderWithContext, RemoteClient,
+        ServerToClientMsg,
+    },
     libc, nix,
     pane_size::Size,
     shared::default_palette,
 };
 
-use crate::{ServerHandle, ServerOutput, ZellijClientData};
+use crate::{output::OutputWriter, ClientSignal, ServerHandle, ServerOutput};
+
+const SIGWINCH_CB_THROTTLE_DURATION: time::Duration = time::Duration::from_millis(50);
 
 const ENABLE_MOUSE_SUPPORT: &str = "\u{1b}[?1000h\u{1b}[?1002h\u{1b}[?1015h\u{1b}[?1006h";
 const DISABLE_MOUSE_SUPPORT: &str = "\u{1b}[?1006l\u{1b}[?1015l\u{1b}[?1002l\u{1b}[?1000l";
@@ -28,28 +33,33 @@ const DISABLE_MOUSE_SUPPORT: &str = "\u{1b}[?1006l\u{1b}[?1015l\u{1b}[?1002l\u{1
 #[derive(Clone)]
 pub struct SshInputOutput {
     pub handle: ServerHandle,
-    pub win_size: libc::winsize,
+    pub win_size: Arc<Mutex<libc::winsize>>,
     pub channel_id: ChannelId,
     pub send_instructions_to_server: Arc<Mutex<Option<IpcSenderWithContext<ClientToServerMsg>>>>,
     pub receive_instructions_from_server:
         Arc<Mutex<Option<IpcReceiverWithContext<ServerToClientMsg>>>>,
     pub reading_from_stdin: Arc<Mutex<Option<Vec<u8>>>>,
     pub session_name: Arc<Mutex<Option<String>>>,
-    pub sender: UnboundedSender<ZellijClientData>,
+    pub output: OutputWriter,
     pub server_receiver: Receiver<Vec<u8>>,
-    pub server_signal_receiver: Receiver<Sig>,
+    pub server_signal_receiver: Receiver<ClientSignal>,
+    /// the environment of the remote terminal, from the pty and env requests
+    pub env: BTreeMap<String, String>,
+    /// who logged in, shown to the others in the session
+    pub remote: RemoteClient,
 }
 
 impl zellij_client::os_input_output::ClientOsApi for SshInputOutput {
     fn get_terminal_size_using_fd(&self, _: i32) -> Size {
+        let win_size = self.win_size.lock().unwrap();
         Size {
-            rows: self.win_size.ws_row as usize,
-            cols: self.win_size.ws_col as usize,
+            rows: win_size.ws_row as usize,
+            cols: win_size.ws_col as usize,
         }
     }
 
     fn set_terminal_size(&mut self, win_size: libc::winsize) {
-        self.win_size = win_size
+        *self.win_size.lock().unwrap() = win_size
     }
 
     fn set_raw_mode(&mut self, _: RawFd) {
@@ -124,7 +134,7 @@ impl zellij_client::os_input_output::ClientOsApi for SshInputOutput {
 
     fn get_stdout_writer(&self) -> Box<dyn io::Write> {
         Box::new(ServerOutput {
-            sender: self.sender.clone(),
+            output: self.output.clone(),
         })
     }
     fn get_stdin_reader(&self) -> Box<dyn io::Read> {
@@ -150,36 +160,28 @@ impl zellij_client::os_input_output::ClientOsApi for SshInputOutput {
             .unwrap()
             .recv()
     }
-    fn handle_signals(&self, _sigwinch_cb: Box<dyn Fn()>, quit_cb: Box<dyn Fn()>) {
-        let _sigwinch_cb_timestamp = time::Instant::now();
-        match self.server_signal_receiver.recv() {
-            Ok(sig) => match sig {
-                Sig::TERM | Sig::INT | Sig::QUIT | Sig::HUP => {
+    fn handle_signals(&self, sigwinch_cb: Box<dyn Fn()>, quit_cb: Box<dyn Fn()>) {
+        let mut sigwinch_cb_timestamp = time::Instant::now();
+        for signal in self.server_signal_receiver.iter() {
+            match signal {
+                ClientSignal::WindowChange(win_size) => {
+                    *self.win_size.lock().unwrap() = win_size;
+                    // throttle sigwinch_cb calls, reduce excessive renders while resizing
+                    if sigwinch_cb_timestamp.elapsed() < SIGWINCH_CB_THROTTLE_DURATION {
+                        thread::sleep(SIGWINCH_CB_THROTTLE_DURATION);
+                    }
+                    sigwinch_cb_timestamp = time::Instant::now();
+                    sigwinch_cb();
+                },
+                ClientSignal::Signal(Sig::TERM | Sig::INT | Sig::QUIT | Sig::HUP) => {
                     quit_cb();
+                    break;
                 },
-                _ => unreachable!(),
-            },
-
-            Err(_) => {},
+                ClientSignal::Signal(sig) => {
+                    log::debug!("Ignoring signal {:?} sent by the ssh client", sig);
+                },
+            }
         }
-        //let mut signals = Signals::new(&[SIGWINCH, SIGTERM, SIGINT, SIGQUIT, SIGHUP]).unwrap();
-        //for signal in signals.forever() {
-        //    match signal {
-        //        SIGWINCH => {
-        //            // throttle sigwinch_cb calls, reduce excessive renders while resizing
-        //            if sigwinch_cb_timestamp.elapsed() < SIGWINCH_CB_THROTTLE_DURATION {
-        //                thread::sleep(SIGWINCH_CB_THROTTLE_DURATION);
-        //            }
-        //            sigwinch_cb_timestamp = time::Instant::now();
-        //            sigwinch_cb();
-        //        },
-        //        SIGTERM | SIGINT | SIGQUIT | SIGHUP => {
-        //            quit_cb();
-        //            break;
-        //        },
-        //        _ => unreachable!(),
-        //    }
-        //}
     }
     fn connect_to_server(&self, path: &Path) {
         let socket;
@@ -238,11 +240,19 @@ impl zellij_client::os_input_output::ClientOsApi for SshInputOutput {
     }
 
     fn env_variable(&self, name: &str) -> Option<String> {
-        std::env::var(name).ok()
+        self.env.get(name).cloned()
+    }
+
+    fn env_variables(&self) -> BTreeMap<String, String> {
+        self.env.clone()
+    }
+
+    fn remote_client(&self) -> Option<RemoteClient> {
+        Some(self.remote.clone())
     }
 
     fn close(&self) {
-        let _ = self.sender.send(ZellijClientData::Exit);
+        self.output.close();
     }
 }
 
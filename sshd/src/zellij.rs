@@ -1,4 +1,5 @@
 use crate::{
+    output::OutputWriter,
     session_util::{
         assert_dead_session, assert_session, assert_session_ne,
         delete_session as delete_session_impl, get_active_session, get_name_generator,
@@ -8,12 +9,13 @@ use crate::{
         SessionNameMatch,
     },
     ssh_input_output::SshInputOutput,
-    ServerHandle, ZellijClientData,
+    ClientSignal, ServerHandle,
 };
 use dialoguer::Confirm;
 use log::info;
-use russh::{ChannelId, Sig};
+use russh::ChannelId;
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::Read,
     path::PathBuf,
@@ -22,7 +24,6 @@ use std::{
     thread::{self, JoinHandle},
     time::Duration,
 };
-use tokio::sync::mpsc::UnboundedSender;
 use zellij_client::{
     old_config_converter::{
         config_yaml_to_config_kdl, convert_old_yaml_files, layout_yaml_to_layout_kdl,
@@ -33,6 +34,7 @@ use zellij_client::{
 };
 use zellij_server::{os_input_output::get_server_os_input, start_server};
 use zellij_utils::{
+    clap::Parser,
     cli::{CliArgs, Command, SessionCommand, Sessions},
     consts::ZELLIJ_SOCK_DIR,
     data::{ConnectToSession, Style},
@@ -43,7 +45,7 @@ use zellij_utils::{
         layout::Layout,
         options::Options,
     },
-    ipc::{ClientAttributes, ClientToServerMsg},
+    ipc::{ClientAttributes, ClientToServerMsg, ClientType, RemoteClient},
     miette::{Report, Result},
     nix,
     setup::Setup,
@@ -393,12 +395,15 @@ fn attach_with_session_name(
 
 pub(crate) fn start_client(
     opts: CliArgs,
-    sender: UnboundedSender<ZellijClientData>,
+    output: OutputWriter,
     server_receiver: crossbeam_channel::Receiver<Vec<u8>>,
-    server_signal_receiver: crossbeam_channel::Receiver<Sig>,
+    server_signal_receiver: crossbeam_channel::Receiver<ClientSignal>,
     handle: ServerHandle,
     channel_id: ChannelId,
     win_size: libc::winsize,
+    client_type: ClientType,
+    env: BTreeMap<String, String>,
+    remote: RemoteClient,
 ) {
     // look for old YAML config/layout/theme files and convert them to KDL
     convert_old_yaml_files(&opts);
@@ -419,9 +424,11 @@ pub(crate) fn start_client(
         handle,
         channel_id,
         win_size,
-        sender,
+        output,
         server_receiver,
         server_signal_receiver,
+        env,
+        remote,
     );
     loop {
         let os_input = os_input.clone();
@@ -514,6 +521,7 @@ pub(crate) fn start_client(
                 config,
                 config_options,
                 client,
+                client_type,
                 attach_layout,
                 tab_position_to_focus,
                 pane_id_to_focus,
@@ -528,6 +536,7 @@ pub(crate) fn start_client(
                     config,
                     config_options,
                     ClientInfo::New(session_name),
+                    client_type,
                     Some(layout),
                     None,
                     None,
@@ -568,6 +577,7 @@ pub(crate) fn start_client(
                                 config,
                                 config_options,
                                 client,
+                                client_type,
                                 attach_layout,
                                 None,
                                 None,
@@ -582,6 +592,7 @@ pub(crate) fn start_client(
                                 config,
                                 config_options.clone(),
                                 ClientInfo::New(session_name.clone()),
+                                client_type,
                                 Some(layout),
                                 None,
                                 None,
@@ -605,6 +616,7 @@ pub(crate) fn start_client(
                     config,
                     config_options,
                     ClientInfo::New(session_name),
+                    client_type,
                     Some(layout),
                     None,
                     None,
@@ -618,26 +630,43 @@ pub(crate) fn start_client(
     }
 }
 
+/// Parses a command line such as `attach pairing` (the leading `zellij` is optional) into
+/// the zellij cli command it represents
+pub(crate) fn parse_zellij_command(command_line: &str) -> Result<Command, String> {
+    let mut words = shell_words::split(command_line).map_err(|e| e.to_string())?;
+    if words.first().map(|w| w.as_str()) != Some("zellij") {
+        words.insert(0, "zellij".to_string());
+    }
+    CliArgs::try_parse_from(words)
+        .map_err(|e| e.to_string())?
+        .command
+        .ok_or_else(|| format!("no zellij command in {:?}", command_line))
+}
+
 fn get_ssh_client_input(
     handle: ServerHandle,
     channel_id: ChannelId,
     win_size: libc::winsize,
-    sender: UnboundedSender<ZellijClientData>,
+    output: OutputWriter,
     server_receiver: crossbeam_channel::Receiver<Vec<u8>>,
-    server_signal_receiver: crossbeam_channel::Receiver<Sig>,
+    server_signal_receiver: crossbeam_channel::Receiver<ClientSignal>,
+    env: BTreeMap<String, String>,
+    remote: RemoteClient,
 ) -> SshInputOutput {
     let reading_from_stdin = Arc::new(Mutex::new(None));
     SshInputOutput {
         handle,
-        win_size,
+        win_size: Arc::new(Mutex::new(win_size)),
         channel_id,
-        sender,
+        output,
         server_receiver,
         server_signal_receiver,
         send_instructions_to_server: Arc::new(Mutex::new(None)),
         receive_instructions_from_server: Arc::new(Mutex::new(None)),
         reading_from_stdin,
         session_name: Arc::new(Mutex::new(None)),
+        env,
+        remote,
     }
 }
 
@@ -714,6 +743,10 @@ pub fn init_zellij_client(
             hide_session_name: config.ui.pane_frames.hide_session_name,
         },
         keybinds: config.keybinds.clone(),
+        client_type: ClientType::Writer,
+        env: os_input.env_variables(),
+        remote: os_input.remote_client(),
+        display_name: opts.name.clone(),
     };
 
     let first_msg = ClientToServerMsg::NewClient(
@@ -730,31 +763,34 @@ pub fn init_zellij_client(
 }
 
 fn generate_unique_session_name() -> String {
-    let sessions = get_sessions().map(|sessions| {
-        sessions
-            .iter()
-            .map(|s| s.0.clone())
-            .collect::<Vec<String>>()
-    });
+    match unique_session_name() {
+        Ok(name) => name,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        },
+    }
+}
+
+/// A generated session name that no running or resurrectable session has
+pub(crate) fn unique_session_name() -> Result<String, String> {
+    let sessions = get_sessions()
+        .map(|sessions| {
+            sessions
+                .iter()
+                .map(|s| s.0.clone())
+                .collect::<Vec<String>>()
+        })
+        .map_err(|e| format!("Failed to list existing sessions: {:?}", e))?;
     let dead_sessions: Vec<String> = get_resurrectable_sessions()
         .iter()
         .map(|(s, _, _)| s.clone())
         .collect();
-    let Ok(sessions) = sessions else {
-        eprintln!("Failed to list existing sessions: {:?}", sessions);
-        process::exit(1);
-    };
 
-    let name = get_name_generator()
+    get_name_generator()
         .take(1000)
-        .find(|name| !sessions.contains(name) && !dead_sessions.contains(name));
-
-    if let Some(name) = name {
-        return name;
-    } else {
-        eprintln!("Failed to generate a unique session name, giving up");
-        process::exit(1);
-    }
+        .find(|name| !sessions.contains(name) && !dead_sessions.contains(name))
+        .ok_or_else(|| "Failed to generate a unique session name, giving up".to_string())
 }
 
 fn create_ipc_pipe() -> PathBuf {
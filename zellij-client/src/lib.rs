@@ -29,7 +29,7 @@ use zellij_utils::{
     envs,
     errors::{ClientContext, ContextType, ErrorInstruction},
     input::{config::Config, options::Options},
-    ipc::{ClientAttributes, ClientToServerMsg, ExitReason, ServerToClientMsg},
+    ipc::{ClientAttributes, ClientToServerMsg, ClientType, ExitReason, ServerToClientMsg},
     termwiz::input::InputEvent,
 };
 use zellij_utils::{cli::CliArgs, input::layout::Layout};
@@ -51,6 +51,7 @@ pub(crate) enum ClientInstruction {
     SwitchSession(ConnectToSession),
     SetSynchronizedOutput(Option<SyncOutput>),
     ServerMode(ServerMode),
+    PromptAnswer(Option<String>),
 }
 
 impl From<ServerToClientMsg> for ClientInstruction {
@@ -70,6 +71,7 @@ impl From<ServerToClientMsg> for ClientInstruction {
                 ClientInstruction::SwitchSession(connect_to_session)
             },
             ServerToClientMsg::ServerMode(mode) => ClientInstruction::ServerMode(mode),
+            ServerToClientMsg::PromptAnswer(answer) => ClientInstruction::PromptAnswer(answer),
         }
     }
 }
@@ -91,6 +93,7 @@ impl From<&ClientInstruction> for ClientContext {
             ClientInstruction::SwitchSession(..) => ClientContext::SwitchSession,
             ClientInstruction::SetSynchronizedOutput(..) => ClientContext::SetSynchronisedOutput,
             ClientInstruction::ServerMode(_) => ClientContext::ServerMode,
+            ClientInstruction::PromptAnswer(_) => ClientContext::PromptAnswer,
         }
     }
 }
@@ -197,6 +200,10 @@ pub fn start_client(
             hide_session_name: config.ui.pane_frames.hide_session_name,
         },
         keybinds: config.keybinds.clone(),
+        client_type: ClientType::Writer,
+        env: os_input.env_variables(),
+        remote: os_input.remote_client(),
+        display_name: opts.name.clone(),
     };
 
     let create_ipc_pipe = || -> std::path::PathBuf {
@@ -7,6 +7,7 @@ use mio::{unix::SourceFd, Events, Interest, Poll, Token};
 use nix::pty::Winsize;
 use nix::sys::termios;
 use signal_hook::{consts::signal::*, iterator::Signals};
+use std::collections::BTreeMap;
 use std::io::prelude::*;
 use std::os::unix::io::RawFd;
 use std::path::Path;
@@ -15,7 +16,10 @@ use std::{io, thread, time};
 use zellij_utils::{
     data::Palette,
     errors::ErrorContext,
-    ipc::{ClientToServerMsg, IpcReceiverWithContext, IpcSenderWithContext, ServerToClientMsg},
+    ipc::{
+        ClientToServerMsg, IpcReceiverWithContext, IpcSenderWithContext, RemoteClient,
+        ServerToClientMsg,
+    },
     shared::default_palette,
 };
 
@@ -120,6 +124,15 @@ pub trait ClientOsApi: Send + Sync {
     fn env_variable(&self, _name: &str) -> Option<String> {
         None
     }
+    /// Variables of the client's terminal to set in the panes it opens, only needed when the
+    /// client doesn't run on the same machine as the server
+    fn env_variables(&self) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+    /// The ssh login the client was started for, if any
+    fn remote_client(&self) -> Option<RemoteClient> {
+        None
+    }
     // Set server mode, ssh or normal
     fn set_server_mode(&mut self, _mode: ServerMode) {}
     // Get server mode, ssh or normal
@@ -11,7 +11,7 @@ use zellij_utils::{
     envs,
     errors::{ContextType, FatalError},
     input::{config::Config, layout::Layout, options::Options},
-    ipc::{ClientAttributes, ClientToServerMsg, ExitReason, ServerToClientMsg},
+    ipc::{ClientAttributes, ClientToServerMsg, ClientType, ExitReason, ServerToClientMsg},
     shared::set_permissions,
 };
 
@@ -32,6 +32,7 @@ pub fn start_client_ssh(
     config: Config,
     config_options: Options,
     info: ClientInfo,
+    client_type: ClientType,
     layout: Option<Layout>,
     tab_position_to_focus: Option<usize>,
     pane_id_to_focus: Option<(u32, bool)>, // (pane_id, is_plugin)
@@ -73,6 +74,10 @@ pub fn start_client_ssh(
             hide_session_name: config.ui.pane_frames.hide_session_name,
         },
         keybinds: config.keybinds.clone(),
+        client_type,
+        env: os_input.env_variables(),
+        remote: os_input.remote_client(),
+        display_name: opts.name.clone(),
     };
 
     let create_ipc_pipe = || -> std::path::PathBuf {
@@ -3,9 +3,7 @@ use crate::stdin_ansi_parser::StdinAnsiParser;
 use crate::InputInstruction;
 use std::sync::{Arc, Mutex};
 use zellij_utils::channels::SenderWithContext;
-use zellij_utils::ipc::ClientToServerMsg;
 use zellij_utils::termwiz::input::{InputEvent, InputParser, MouseButtons};
-use zellij_utils::ServerMode;
 
 fn send_done_parsing_after_query_timeout(
     send_input_instructions: SenderWithContext<InputInstruction>,
@@ -64,13 +62,6 @@ pub(crate) fn stdin_loop(
         match os_input.read_from_stdin() {
             Ok(buf) => {
                 {
-                    if os_input.get_server_mode() == ServerMode::Ssh
-                        && (&buf).len() == 1
-                        && &buf[0] == &4
-                    {
-                        os_input.send_to_server(ClientToServerMsg::DetachSession(vec![]));
-                        break;
-                    }
                     // here we check if we need to parse specialized ANSI instructions sent over STDIN
                     // this happens either on startup (see above) or on SIGWINCH
                     //
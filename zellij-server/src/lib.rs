@@ -18,7 +18,7 @@ mod ui;
 use background_jobs::{background_jobs_main, BackgroundJob};
 use log::info;
 use pty_writer::{pty_writer_main, PtyWriteInstruction};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::{
     path::PathBuf,
     sync::{Arc, RwLock},
@@ -39,25 +39,29 @@ use crate::{
     thread_bus::{Bus, ThreadSenders},
 };
 use route::route_thread_main;
+use uuid::Uuid;
 use zellij_utils::{
     channels::{self, ChannelWithContext, SenderWithContext},
     cli::CliArgs,
     consts::{DEFAULT_SCROLL_BUFFER_SIZE, SCROLL_BUFFER_SIZE},
-    data::{ConnectToSession, Event, PluginCapabilities},
+    data::{ClientRole, ConnectToSession, ConnectedClientInfo, Event, PluginCapabilities},
     errors::{prelude::*, ContextType, ErrorInstruction, FatalError, ServerContext},
     home::get_default_data_dir,
     input::{
         command::{RunCommand, TerminalAction},
         get_mode_info,
-        layout::Layout,
+        layout::{Layout, PluginUserConfiguration, RunPlugin},
         options::Options,
         plugins::PluginsConfig,
     },
-    ipc::{ClientAttributes, ExitReason, ServerToClientMsg},
+    ipc::{ClientAttributes, ClientType, ExitReason, Prompt, RemoteClient, ServerToClientMsg},
 };
 
 pub type ClientId = u16;
 
+// the key of the dialog configuration a prompt is answered with
+pub(crate) const PROMPT_SECRET_KEY: &str = "prompt_secret";
+
 /// Instructions related to server-side application
 #[derive(Debug, Clone)]
 pub enum ServerInstruction {
@@ -88,6 +92,12 @@ pub enum ServerInstruction {
     Log(Vec<String>, ClientId),
     SwitchSession(ConnectToSession, ClientId),
     Mode(ClientId),
+    Prompt(Prompt, ClientId), // client that waits for the answer
+    PromptAnswer(Option<String>, ClientId, String), // client that asked, secret of its prompt
+    ListConnectedClients(ConnectedClientsRequest),
+    DisconnectClient(ClientId, ClientId), // client to disconnect, client disconnecting it
+    SetClientRole(ClientId, ClientRole, ClientId), // client to change, client changing it
+    SetFollowing(ClientId, bool),         // client that started or stopped following
 }
 
 impl From<&ServerInstruction> for ServerContext {
@@ -107,6 +117,12 @@ impl From<&ServerInstruction> for ServerContext {
             ServerInstruction::Log(..) => ServerContext::Log,
             ServerInstruction::SwitchSession(..) => ServerContext::SwitchSession,
             ServerInstruction::Mode(_) => ServerContext::Mode,
+            ServerInstruction::Prompt(..) => ServerContext::Prompt,
+            ServerInstruction::PromptAnswer(..) => ServerContext::PromptAnswer,
+            ServerInstruction::ListConnectedClients(..) => ServerContext::ListConnectedClients,
+            ServerInstruction::DisconnectClient(..) => ServerContext::DisconnectClient,
+            ServerInstruction::SetClientRole(..) => ServerContext::SetClientRole,
+            ServerInstruction::SetFollowing(..) => ServerContext::SetFollowing,
         }
     }
 }
@@ -186,15 +202,41 @@ macro_rules! send_to_client {
     };
 }
 
+/// Who asked for the clients attached to the session, the list goes back to them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConnectedClientsRequest {
+    Plugin(u32, ClientId), // plugin id
+    Cli(ClientId, bool),   // bool is whether to list them as JSON
+}
+
+impl ConnectedClientsRequest {
+    pub fn client_id(&self) -> ClientId {
+        match self {
+            ConnectedClientsRequest::Plugin(_, client_id) => *client_id,
+            ConnectedClientsRequest::Cli(client_id, _) => *client_id,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct SessionState {
     clients: HashMap<ClientId, Option<Size>>,
+    client_roles: HashMap<ClientId, ClientRole>,
+    remote_clients: HashMap<ClientId, RemoteClient>,
+    display_names: HashMap<ClientId, String>,
+    prompt_secrets: HashMap<ClientId, String>,
+    following_clients: HashSet<ClientId>,
 }
 
 impl SessionState {
     pub fn new() -> Self {
         SessionState {
             clients: HashMap::new(),
+            client_roles: HashMap::new(),
+            remote_clients: HashMap::new(),
+            display_names: HashMap::new(),
+            prompt_secrets: HashMap::new(),
+            following_clients: HashSet::new(),
         }
     }
     pub fn new_client(&mut self) -> ClientId {
@@ -212,10 +254,115 @@ impl SessionState {
     }
     pub fn remove_client(&mut self, client_id: ClientId) {
         self.clients.remove(&client_id);
+        self.client_roles.remove(&client_id);
+        self.remote_clients.remove(&client_id);
+        self.display_names.remove(&client_id);
+        self.prompt_secrets.remove(&client_id);
+        self.following_clients.remove(&client_id);
+    }
+    pub fn set_client_role(&mut self, client_id: ClientId, role: ClientRole) {
+        self.client_roles.insert(client_id, role);
+    }
+    /// Clients that never attached, like those of the cli, reach the session through its socket
+    /// which only its owner can access, unless they act for an ssh login
+    pub fn client_role(&self, client_id: ClientId) -> ClientRole {
+        self.client_roles
+            .get(&client_id)
+            .copied()
+            .unwrap_or(ClientRole::Owner)
+    }
+    pub fn client_type(&self, client_id: ClientId) -> ClientType {
+        match self.client_role(client_id) {
+            ClientRole::Viewer => ClientType::Reader,
+            ClientRole::Collaborator | ClientRole::Owner => ClientType::Writer,
+        }
+    }
+    /// Whoever creates the session or attaches to it from this machine owns it, ssh clients
+    /// only do once an owner makes them one
+    pub fn role_of_new_client(
+        &self,
+        client_attributes: &ClientAttributes,
+        creates_session: bool,
+    ) -> ClientRole {
+        if client_attributes.client_type == ClientType::Reader {
+            ClientRole::Viewer
+        } else if creates_session || client_attributes.remote.is_none() {
+            ClientRole::Owner
+        } else {
+            ClientRole::Collaborator
+        }
+    }
+    pub fn is_attached(&self, client_id: ClientId) -> bool {
+        self.clients
+            .get(&client_id)
+            .map_or(false, |size| size.is_some())
+    }
+    /// How the other clients know `client_id`
+    pub fn client_name(&self, client_id: ClientId) -> String {
+        let name = self.display_names.get(&client_id).or_else(|| {
+            self.remote_clients
+                .get(&client_id)
+                .map(|remote| &remote.user)
+        });
+        match name {
+            Some(name) => format!("{} (client {})", name, client_id),
+            None => format!("client {}", client_id),
+        }
+    }
+    pub fn display_name(&self, client_id: ClientId) -> Option<String> {
+        self.display_names.get(&client_id).cloned()
+    }
+    /// Whether `client_id` follows another client, as the screen last reported it
+    pub fn is_following(&self, client_id: ClientId) -> bool {
+        self.following_clients.contains(&client_id)
+    }
+    pub fn set_following(&mut self, client_id: ClientId, is_following: bool) {
+        if is_following {
+            self.following_clients.insert(client_id);
+        } else {
+            self.following_clients.remove(&client_id);
+        }
     }
     pub fn set_client_size(&mut self, client_id: ClientId, size: Size) {
         self.clients.insert(client_id, Some(size));
     }
+    pub fn set_remote_client(&mut self, client_id: ClientId, remote: Option<RemoteClient>) {
+        match remote {
+            Some(remote) => self.remote_clients.insert(client_id, remote),
+            None => self.remote_clients.remove(&client_id),
+        };
+    }
+    pub fn set_display_name(&mut self, client_id: ClientId, display_name: Option<String>) {
+        match display_name {
+            Some(display_name) => self.display_names.insert(client_id, display_name),
+            None => self.display_names.remove(&client_id),
+        };
+    }
+    /// The attached clients as far as the server knows them, the screen adds what they focus and
+    /// follow
+    pub fn connected_clients(&self, current_client_id: ClientId) -> Vec<ConnectedClientInfo> {
+        let mut connected_clients: Vec<ConnectedClientInfo> = self
+            .clients
+            .iter()
+            .filter_map(|(client_id, size)| size.map(|size| (*client_id, size)))
+            .map(|(client_id, size)| {
+                let remote = self.remote_clients.get(&client_id);
+                ConnectedClientInfo {
+                    client_id,
+                    ssh_user: remote.map(|remote| remote.user.clone()),
+                    peer_address: remote.and_then(|remote| remote.peer_addr.clone()),
+                    role: self.client_role(client_id),
+                    rows: size.rows,
+                    cols: size.cols,
+                    focused_pane: None,
+                    following: None,
+                    is_current_client: client_id == current_client_id,
+                }
+            })
+            .collect();
+        connected_clients.sort_by_key(|client| client.client_id);
+        connected_clients
+    }
     pub fn min_client_terminal_size(&self) -> Option<Size> {
         // None if there are no client sizes
         let mut rows: Vec<usize> = self
@@ -243,6 +390,57 @@ impl SessionState {
     pub fn client_ids(&self) -> Vec<ClientId> {
         self.clients.keys().copied().collect()
     }
+    /// The client that answers prompts: the first owner attached, other than `asking_client_id`
+    pub fn prompt_client_id(&self, asking_client_id: ClientId) -> Option<ClientId> {
+        self.clients
+            .iter()
+            .filter(|(client_id, size)| **client_id != asking_client_id && size.is_some())
+            .map(|(client_id, _)| *client_id)
+            .filter(|client_id| self.client_role(*client_id) == ClientRole::Owner)
+            .min()
+    }
+    /// A new secret for the prompt of `client_id`, only the dialog launched with it can answer
+    pub fn new_prompt_secret(&mut self, client_id: ClientId) -> String {
+        let secret = Uuid::new_v4().to_string();
+        self.prompt_secrets.insert(client_id, secret.clone());
+        secret
+    }
+    /// Whether `secret` is the one of the prompt of `client_id`, which is answered only once
+    pub fn take_prompt_secret(&mut self, client_id: ClientId, secret: &str) -> bool {
+        let is_prompt_secret = self
+            .prompt_secrets
+            .get(&client_id)
+            .map_or(false, |prompt_secret| prompt_secret == secret);
+        if is_prompt_secret {
+            self.prompt_secrets.remove(&client_id);
+        }
+        is_prompt_secret
+    }
+}
+
+/// Shown to everyone in the session when `client_name` got `role`
+fn role_change_message(client_name: &str, role: ClientRole) -> String {
+    match role {
+        ClientRole::Owner => format!("{} is now an owner of the session", client_name),
+        ClientRole::Collaborator => format!("{} is now a collaborator", client_name),
+        ClientRole::Viewer => format!("{} is now a viewer", client_name),
+    }
+}
+
+/// The dialog plugin showing `prompt`, it answers on behalf of the client `prompt_id` with the
+/// secret of its prompt
+fn prompt_plugin(prompt: Prompt, prompt_id: ClientId, secret: String) -> Result<RunPlugin> {
+    let mut run_plugin = RunPlugin::from_url("zellij:dialog")
+        .map_err(|e| anyhow!("failed to find the dialog plugin: {}", e))?;
+    let mut configuration = BTreeMap::new();
+    configuration.insert("prompt_id".to_owned(), prompt_id.to_string());
+    configuration.insert(PROMPT_SECRET_KEY.to_owned(), secret);
+    configuration.insert("title".to_owned(), prompt.title);
+    configuration.insert("message".to_owned(), prompt.message);
+    configuration.insert("choices".to_owned(), prompt.choices.join(","));
+    configuration.insert("timeout".to_owned(), prompt.timeout_secs.to_string());
+    run_plugin.configuration = PluginUserConfiguration::new(configuration);
+    Ok(run_plugin)
 }
 
 pub fn start_server(mut os_input: Box<dyn ServerOsApi>, socket_path: PathBuf, foreground: bool) {
@@ -354,6 +552,25 @@ pub fn start_server(mut os_input: Box<dyn ServerOsApi>, socket_path: PathBuf, fo
                     .write()
                     .unwrap()
                     .set_client_size(client_id, client_attributes.size);
+                session_state
+                    .write()
+                    .unwrap()
+                    .set_remote_client(client_id, client_attributes.remote.clone());
+                session_state
+                    .write()
+                    .unwrap()
+                    .set_display_name(client_id, client_attributes.display_name.clone());
+                session_data
+                    .read()
+                    .unwrap()
+                    .as_ref()
+                    .unwrap()
+                    .senders
+                    .send_to_pty(PtyInstruction::SetClientEnv(
+                        client_id,
+                        client_attributes.env.clone(),
+                    ))
+                    .unwrap();
 
                 let default_shell = config_options.default_shell.map(|shell| {
                     TerminalAction::RunCommand(RunCommand {
@@ -442,6 +659,14 @@ pub fn start_server(mut os_input: Box<dyn ServerOsApi>, socket_path: PathBuf, fo
                     .write()
                     .unwrap()
                     .set_client_size(client_id, attrs.size);
+                session_state
+                    .write()
+                    .unwrap()
+                    .set_remote_client(client_id, attrs.remote.clone());
+                session_state
+                    .write()
+                    .unwrap()
+                    .set_display_name(client_id, attrs.display_name.clone());
                 let min_size = session_state
                     .read()
                     .unwrap()
@@ -451,6 +676,10 @@ pub fn start_server(mut os_input: Box<dyn ServerOsApi>, socket_path: PathBuf, fo
                     .senders
                     .send_to_screen(ScreenInstruction::TerminalResize(min_size))
                     .unwrap();
+                session_data
+                    .senders
+                    .send_to_pty(PtyInstruction::SetClientEnv(client_id, attrs.env.clone()))
+                    .unwrap();
                 session_data
                     .senders
                     .send_to_screen(ScreenInstruction::AddClient(
@@ -614,6 +843,44 @@ pub fn start_server(mut os_input: Box<dyn ServerOsApi>, socket_path: PathBuf, fo
                         .unwrap();
                 }
             },
+            ServerInstruction::DisconnectClient(client_id, disconnecting_client_id) => {
+                if session_state
+                    .read()
+                    .unwrap()
+                    .client_role(disconnecting_client_id)
+                    != ClientRole::Owner
+                {
+                    log::warn!(
+                        "Client {} is not an owner, it can't disconnect client {}",
+                        disconnecting_client_id,
+                        client_id
+                    );
+                    continue;
+                }
+                if !session_state.read().unwrap().is_attached(client_id) {
+                    log::warn!("No attached client {} to disconnect", client_id);
+                    continue;
+                }
+                let _ =
+                    os_input.send_to_client(client_id, ServerToClientMsg::Exit(ExitReason::Kicked));
+                remove_client!(client_id, os_input, session_state);
+                let rlock = session_data.read().unwrap();
+                let session_data = rlock.as_ref().unwrap();
+                if let Some(min_size) = session_state.read().unwrap().min_client_terminal_size() {
+                    session_data
+                        .senders
+                        .send_to_screen(ScreenInstruction::TerminalResize(min_size))
+                        .unwrap();
+                }
+                session_data
+                    .senders
+                    .send_to_screen(ScreenInstruction::RemoveClient(client_id))
+                    .unwrap();
+                session_data
+                    .senders
+                    .send_to_plugin(PluginInstruction::RemoveClient(client_id))
+                    .unwrap();
+            },
             ServerInstruction::Render(serialized_output) => {
                 let client_ids = session_state.read().unwrap().client_ids();
                 // If `Some(_)`- unwrap it and forward it to the clients to render.
@@ -721,6 +988,122 @@ pub fn start_server(mut os_input: Box<dyn ServerOsApi>, socket_path: PathBuf, fo
 
                 let _ = os_input.send_to_client(client_id, ServerToClientMsg::ServerMode(mode));
             },
+            ServerInstruction::Prompt(prompt, client_id) => {
+                let prompt_client_id = session_state.read().unwrap().prompt_client_id(client_id);
+                let secret = session_state.write().unwrap().new_prompt_secret(client_id);
+                let launched = match (prompt_client_id, session_data.read().unwrap().as_ref()) {
+                    (Some(prompt_client_id), Some(session_data)) => {
+                        prompt_plugin(prompt, client_id, secret).and_then(|run_plugin| {
+                            session_data.senders.send_to_screen(
+                                ScreenInstruction::LaunchOrFocusPlugin(
+                                    run_plugin,
+                                    true,
+                                    false,
+                                    false,
+                                    None,
+                                    prompt_client_id,
+                                ),
+                            )
+                        })
+                    },
+                    _ => Err(anyhow!("no attached client can answer the prompt")),
+                };
+                if let Err(e) = launched {
+                    log::warn!("Prompt of client {} not shown: {}", client_id, e);
+                    send_to_client!(
+                        client_id,
+                        os_input,
+                        ServerToClientMsg::PromptAnswer(None),
+                        session_state
+                    );
+                }
+            },
+            ServerInstruction::PromptAnswer(answer, client_id, secret) => {
+                if !session_state
+                    .write()
+                    .unwrap()
+                    .take_prompt_secret(client_id, &secret)
+                {
+                    log::warn!(
+                        "Ignoring an answer to a prompt client {} isn't waiting on",
+                        client_id
+                    );
+                    continue;
+                }
+                send_to_client!(
+                    client_id,
+                    os_input,
+                    ServerToClientMsg::PromptAnswer(answer),
+                    session_state
+                );
+            },
+            ServerInstruction::SetClientRole(client_id, role, changing_client_id) => {
+                if session_state
+                    .read()
+                    .unwrap()
+                    .client_role(changing_client_id)
+                    != ClientRole::Owner
+                {
+                    log::warn!(
+                        "Client {} is not an owner, it can't change the role of client {}",
+                        changing_client_id,
+                        client_id
+                    );
+                    continue;
+                }
+                if client_id == changing_client_id {
+                    log::warn!("Owners can't change their own role");
+                    continue;
+                }
+                if !session_state.read().unwrap().is_attached(client_id) {
+                    log::warn!("No attached client {} to change the role of", client_id);
+                    continue;
+                }
+                session_state
+                    .write()
+                    .unwrap()
+                    .set_client_role(client_id, role);
+                let message = role_change_message(
+                    &session_state.read().unwrap().client_name(client_id),
+                    role,
+                );
+                log::info!("{}", message);
+                if let Some(session_data) = session_data.read().unwrap().as_ref() {
+                    session_data
+                        .senders
+                        .send_to_screen(ScreenInstruction::ChangeClientRole(client_id, role))
+                        .unwrap();
+                    session_data
+                        .senders
+                        .send_to_plugin(PluginInstruction::Update(vec![(
+                            None,
+                            None,
+                            Event::Notification(message),
+                        )]))
+                        .unwrap();
+                }
+            },
+            ServerInstruction::SetFollowing(client_id, is_following) => {
+                session_state
+                    .write()
+                    .unwrap()
+                    .set_following(client_id, is_following);
+            },
+            ServerInstruction::ListConnectedClients(request) => {
+                let connected_clients = session_state
+                    .read()
+                    .unwrap()
+                    .connected_clients(request.client_id());
+                if let Some(session_data) = session_data.read().unwrap().as_ref() {
+                    session_data
+                        .senders
+                        .send_to_screen(ScreenInstruction::ListConnectedClients(
+                            connected_clients,
+                            request,
+                        ))
+                        .unwrap();
+                }
+            },
         }
     }
 
@@ -965,3 +1348,7 @@ fn get_store() -> Store {
     log::info!("Compiling plugins using Singlepass");
     Store::new(wasmer::Singlepass::default())
 }
+
+#[path = "./unit/lib_tests.rs"]
+#[cfg(test)]
+mod lib_tests;
@@ -155,6 +155,7 @@ fn handle_openpty(
     cmd: RunCommand,
     quit_cb: Box<dyn Fn(PaneId, Option<i32>, RunCommand) + Send>, // u32 is the exit status
     terminal_id: u32,
+    env: BTreeMap<String, String>,
 ) -> Result<(RawFd, RawFd)> {
     let err_context = |cmd: &RunCommand| {
         format!(
@@ -183,6 +184,7 @@ fn handle_openpty(
             }
             command
                 .args(&cmd.args)
+                .envs(env)
                 .env("ZELLIJ_PANE_ID", &format!("{}", terminal_id))
                 .pre_exec(move || -> std::io::Result<()> {
                     if libc::login_tty(pid_secondary) != 0 {
@@ -224,16 +226,17 @@ fn handle_terminal(
     orig_termios: termios::Termios,
     quit_cb: Box<dyn Fn(PaneId, Option<i32>, RunCommand) + Send>,
     terminal_id: u32,
+    env: BTreeMap<String, String>,
 ) -> Result<(RawFd, RawFd)> {
     let err_context = || "failed to spawn child terminal".to_string();
 
     // Create a pipe to allow the child the communicate the shell's pid to its
     // parent.
     match openpty(None, Some(&orig_termios)) {
-        Ok(open_pty_res) => handle_openpty(open_pty_res, cmd, quit_cb, terminal_id),
+        Ok(open_pty_res) => handle_openpty(open_pty_res, cmd, quit_cb, terminal_id, env),
         Err(e) => match failover_cmd {
             Some(failover_cmd) => {
-                handle_terminal(failover_cmd, None, orig_termios, quit_cb, terminal_id)
+                handle_terminal(failover_cmd, None, orig_termios, quit_cb, terminal_id, env)
                     .with_context(err_context)
             },
             None => Err::<(i32, i32), _>(e)
@@ -272,6 +275,7 @@ fn separate_command_arguments(command: &mut PathBuf, args: &mut Vec<String>) {
 /// in the new terminal.
 /// If None is given, the shell specified by environment variable `SHELL` will
 /// be started in the new terminal.
+/// The variables of `env` are set in the environment of the started process.
 ///
 /// # Panics
 ///
@@ -283,6 +287,7 @@ fn spawn_terminal(
     quit_cb: Box<dyn Fn(PaneId, Option<i32>, RunCommand) + Send>, // u32 is the exit_status
     default_editor: Option<PathBuf>,
     terminal_id: u32,
+    env: BTreeMap<String, String>,
 ) -> Result<(RawFd, RawFd)> {
     // returns the terminal_id, the primary fd and the
     // secondary fd
@@ -349,7 +354,7 @@ fn spawn_terminal(
         None
     };
 
-    handle_terminal(cmd, failover_cmd, orig_termios, quit_cb, terminal_id)
+    handle_terminal(cmd, failover_cmd, orig_termios, quit_cb, terminal_id, env)
 }
 
 // The ClientSender is in charge of sending messages to the client on a special thread
@@ -476,6 +481,17 @@ pub trait ServerOsApi: Send + Sync {
         quit_cb: Box<dyn Fn(PaneId, Option<i32>, RunCommand) + Send>, // u32 is the exit status
         default_editor: Option<PathBuf>,
     ) -> Result<(u32, RawFd, RawFd)>;
+    /// Like [`ServerOsApi::spawn_terminal`], with `env` added to the environment of the new
+    /// terminal, eg. the `TERM` of the remote client it is opened for.
+    fn spawn_terminal_with_env(
+        &self,
+        terminal_action: TerminalAction,
+        quit_cb: Box<dyn Fn(PaneId, Option<i32>, RunCommand) + Send>, // u32 is the exit status
+        default_editor: Option<PathBuf>,
+        _env: BTreeMap<String, String>,
+    ) -> Result<(u32, RawFd, RawFd)> {
+        self.spawn_terminal(terminal_action, quit_cb, default_editor)
+    }
     // reserves a terminal id without actually opening a terminal
     fn reserve_terminal_id(&self) -> Result<u32> {
         unimplemented!()
@@ -566,12 +582,21 @@ impl ServerOsApi for ServerOsInputOutput {
         }
         Ok(())
     }
-    #[allow(unused_assignments)]
     fn spawn_terminal(
         &self,
         terminal_action: TerminalAction,
         quit_cb: Box<dyn Fn(PaneId, Option<i32>, RunCommand) + Send>, // u32 is the exit status
         default_editor: Option<PathBuf>,
+    ) -> Result<(u32, RawFd, RawFd)> {
+        self.spawn_terminal_with_env(terminal_action, quit_cb, default_editor, BTreeMap::new())
+    }
+    #[allow(unused_assignments)]
+    fn spawn_terminal_with_env(
+        &self,
+        terminal_action: TerminalAction,
+        quit_cb: Box<dyn Fn(PaneId, Option<i32>, RunCommand) + Send>, // u32 is the exit status
+        default_editor: Option<PathBuf>,
+        env: BTreeMap<String, String>,
     ) -> Result<(u32, RawFd, RawFd)> {
         let err_context = || "failed to spawn terminal".to_string();
 
@@ -605,6 +630,7 @@ impl ServerOsApi for ServerOsInputOutput {
                     quit_cb,
                     default_editor,
                     terminal_id,
+                    env,
                 )
                 .and_then(|(pid_primary, pid_secondary)| {
                     self.terminal_id_to_raw_fd
@@ -829,6 +855,7 @@ impl ServerOsApi for ServerOsInputOutput {
                     quit_cb,
                     default_editor,
                     terminal_id,
+                    BTreeMap::new(),
                 )
             })
             .and_then(|(pid_primary, pid_secondary)| {
@@ -15,7 +15,7 @@ use crate::{
     panes::{ActivePanes, PaneId},
     plugins::PluginInstruction,
     thread_bus::ThreadSenders,
-    ui::pane_contents_and_ui::PaneContentsAndUi,
+    ui::pane_contents_and_ui::{client_names, PaneContentsAndUi},
     ClientId,
 };
 use std::cell::RefCell;
@@ -327,6 +327,11 @@ impl FloatingPanes {
         let err_context = || "failed to render output";
         let connected_clients: Vec<ClientId> =
             { self.connected_clients.borrow().iter().copied().collect() };
+        let client_names = client_names(
+            &connected_clients,
+            &self.mode_info.borrow(),
+            &self.default_mode_info,
+        );
         let mut floating_panes: Vec<_> = self.panes.iter_mut().collect();
         floating_panes.sort_by(|(a_id, _a_pane), (b_id, _b_pane)| {
             self.z_indices
@@ -354,6 +359,7 @@ impl FloatingPanes {
                 output,
                 self.style,
                 &active_panes,
+                &client_names,
                 multiple_users_exist_in_session,
                 Some(z_index + 1), // +1 because 0 is reserved for non-floating panes
                 false,
@@ -13,7 +13,7 @@ use crate::{
     tab::{pane_info_for_pane, Pane, MIN_TERMINAL_HEIGHT, MIN_TERMINAL_WIDTH},
     thread_bus::ThreadSenders,
     ui::boundaries::Boundaries,
-    ui::pane_contents_and_ui::PaneContentsAndUi,
+    ui::pane_contents_and_ui::{client_names, PaneContentsAndUi},
     ClientId,
 };
 use stacked_panes::StackedPanes;
@@ -641,6 +641,11 @@ impl TiledPanes {
         let connected_clients: Vec<ClientId> =
             { self.connected_clients.borrow().iter().copied().collect() };
         let multiple_users_exist_in_session = { self.connected_clients_in_app.borrow().len() > 1 };
+        let client_names = client_names(
+            &connected_clients,
+            &self.mode_info.borrow(),
+            &self.default_mode_info,
+        );
         let mut client_id_to_boundaries: HashMap<ClientId, Boundaries> = HashMap::new();
         let active_panes = if floating_panes_are_visible {
             HashMap::new()
@@ -670,6 +675,7 @@ impl TiledPanes {
                     output,
                     self.style,
                     &active_panes,
+                    &client_names,
                     multiple_users_exist_in_session,
                     None,
                     pane_is_stacked_under,
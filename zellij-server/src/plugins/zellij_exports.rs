@@ -3,7 +3,7 @@ use crate::background_jobs::BackgroundJob;
 use crate::plugins::plugin_map::{PluginEnv, Subscriptions};
 use crate::plugins::wasm_bridge::handle_plugin_crash;
 use crate::route::route_action;
-use crate::ServerInstruction;
+use crate::{ClientId, ConnectedClientsRequest, ServerInstruction, PROMPT_SECRET_KEY};
 use log::{debug, warn};
 use serde::Serialize;
 use std::{
@@ -18,7 +18,8 @@ use std::{
 use wasmer::{imports, AsStoreMut, Function, FunctionEnv, FunctionEnvMut, Imports};
 use wasmer_wasi::WasiEnv;
 use zellij_utils::data::{
-    CommandType, ConnectToSession, HttpVerb, PermissionStatus, PermissionType, PluginPermission,
+    ClientRole, CommandType, ConnectToSession, HttpVerb, PermissionStatus, PermissionType,
+    PluginPermission,
 };
 use zellij_utils::input::permission::PermissionCache;
 
@@ -240,6 +241,16 @@ fn host_run_plugin_command(env: FunctionEnvMut<ForeignFunctionEnv>) {
                     PluginCommand::RenameSession(new_session_name) => {
                         rename_session(env, new_session_name)
                     },
+                    PluginCommand::AnswerPrompt(prompt_id, answer) => {
+                        answer_prompt(env, prompt_id, answer)?
+                    },
+                    PluginCommand::ListConnectedClients => list_connected_clients(env)?,
+                    PluginCommand::DisconnectClient(client_id) => {
+                        disconnect_client(env, client_id)?
+                    },
+                    PluginCommand::SetClientRole(client_id, role) => {
+                        set_client_role(env, client_id, role)?
+                    },
                 },
                 (PermissionStatus::Denied, permission) => {
                     log::error!(
@@ -1224,6 +1235,62 @@ fn rename_session(env: &ForeignFunctionEnv, new_session_name: String) {
     apply_action!(action, error_msg, env);
 }
 
+fn answer_prompt(env: &ForeignFunctionEnv, prompt_id: u32, answer: Option<String>) -> Result<()> {
+    // prompts are asked by clients, and answered on their behalf by the dialog launched for them
+    let client_id = u16::try_from(prompt_id)
+        .map_err(|_| anyhow!("prompt id {} is not a client id", prompt_id))?;
+    let secret = env
+        .plugin_env
+        .plugin
+        .userspace_configuration
+        .inner()
+        .get(PROMPT_SECRET_KEY)
+        .cloned()
+        .ok_or_else(|| anyhow!("{} wasn't launched for a prompt", env.plugin_env.name()))?;
+    env.plugin_env
+        .senders
+        .send_to_server(ServerInstruction::PromptAnswer(answer, client_id, secret))
+        .with_context(|| format!("failed to answer prompt {}", prompt_id))
+}
+
+fn list_connected_clients(env: &ForeignFunctionEnv) -> Result<()> {
+    // the list comes back as a ConnectedClients event to this plugin only
+    let request =
+        ConnectedClientsRequest::Plugin(env.plugin_env.plugin_id, env.plugin_env.client_id);
+    env.plugin_env
+        .senders
+        .send_to_server(ServerInstruction::ListConnectedClients(request))
+        .with_context(|| {
+            format!(
+                "failed to list connected clients for plugin {}",
+                env.plugin_env.name()
+            )
+        })
+}
+
+fn disconnect_client(env: &ForeignFunctionEnv, client_id: ClientId) -> Result<()> {
+    // the server only lets the owners of the session disconnect clients
+    env.plugin_env
+        .senders
+        .send_to_server(ServerInstruction::DisconnectClient(
+            client_id,
+            env.plugin_env.client_id,
+        ))
+        .with_context(|| format!("failed to disconnect client {}", client_id))
+}
+
+fn set_client_role(env: &ForeignFunctionEnv, client_id: ClientId, role: ClientRole) -> Result<()> {
+    // the server only lets the owners of the session change roles
+    env.plugin_env
+        .senders
+        .send_to_server(ServerInstruction::SetClientRole(
+            client_id,
+            role,
+            env.plugin_env.client_id,
+        ))
+        .with_context(|| format!("failed to make client {} a {}", client_id, role))
+}
+
 // Custom panic handler for plugins.
 //
 // This is called when a panic occurs in a plugin. Since most panics will likely originate in the
@@ -1305,6 +1372,7 @@ fn check_command_permission(
         | PluginCommand::ExecCmd(..) => PermissionType::RunCommands,
         PluginCommand::WebRequest(..) => PermissionType::WebAccess,
         PluginCommand::Write(..) | PluginCommand::WriteChars(..) => PermissionType::WriteToStdin,
+        PluginCommand::ListConnectedClients => PermissionType::ReadApplicationState,
         PluginCommand::SwitchTabTo(..)
         | PluginCommand::SwitchToMode(..)
         | PluginCommand::NewTabsWithLayout(..)
@@ -1352,6 +1420,9 @@ fn check_command_permission(
         | PluginCommand::DeleteDeadSession(..)
         | PluginCommand::DeleteAllDeadSessions
         | PluginCommand::RenameSession(..)
+        | PluginCommand::AnswerPrompt(..)
+        | PluginCommand::DisconnectClient(..)
+        | PluginCommand::SetClientRole(..)
         | PluginCommand::RenameTab(..) => PermissionType::ChangeApplicationState,
         _ => return (PermissionStatus::Granted, None),
     };
@@ -9,7 +9,11 @@ use crate::{
     ClientId, ServerInstruction,
 };
 use async_std::task::{self, JoinHandle};
-use std::{collections::HashMap, os::unix::io::RawFd, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    os::unix::io::RawFd,
+    path::PathBuf,
+};
 use zellij_utils::nix::unistd::Pid;
 use zellij_utils::{
     async_std,
@@ -89,6 +93,7 @@ pub enum PtyInstruction {
         ClientId,
         Size,
     ),
+    SetClientEnv(ClientId, BTreeMap<String, String>), // empty for clients on this machine
     Exit,
 }
 
@@ -110,6 +115,7 @@ impl From<&PtyInstruction> for PtyContext {
             PtyInstruction::DumpLayout(..) => PtyContext::DumpLayout,
             PtyInstruction::LogLayoutToHd(..) => PtyContext::LogLayoutToHd,
             PtyInstruction::FillPluginCwd(..) => PtyContext::FillPluginCwd,
+            PtyInstruction::SetClientEnv(..) => PtyContext::SetClientEnv,
             PtyInstruction::Exit => PtyContext::Exit,
         }
     }
@@ -122,6 +128,7 @@ pub(crate) struct Pty {
     debug_to_file: bool,
     task_handles: HashMap<u32, JoinHandle<()>>, // terminal_id to join-handle
     default_editor: Option<PathBuf>,
+    client_envs: HashMap<ClientId, BTreeMap<String, String>>, // given to the panes a client opens
 }
 
 pub(crate) fn pty_thread_main(mut pty: Pty, layout: Box<Layout>) -> Result<()> {
@@ -665,6 +672,13 @@ pub(crate) fn pty_thread_main(mut pty: Pty, layout: Box<Layout>) -> Result<()> {
                     size,
                 )?;
             },
+            PtyInstruction::SetClientEnv(client_id, env) => {
+                if env.is_empty() {
+                    pty.client_envs.remove(&client_id);
+                } else {
+                    pty.client_envs.insert(client_id, env);
+                }
+            },
             PtyInstruction::Exit => break,
         }
     }
@@ -684,6 +698,7 @@ impl Pty {
             debug_to_file,
             task_handles: HashMap::new(),
             default_editor,
+            client_envs: HashMap::new(),
         }
     }
     pub fn get_default_terminal(
@@ -743,6 +758,12 @@ impl Pty {
             };
         };
     }
+    fn client_env(&self, client_id: ClientId) -> BTreeMap<String, String> {
+        self.client_envs
+            .get(&client_id)
+            .cloned()
+            .unwrap_or_default()
+    }
     fn fill_cwd_from_pane_id(&self, terminal_action: &mut TerminalAction, pane_id: &u32) {
         if let TerminalAction::RunCommand(run_command) = terminal_action {
             if run_command.cwd.is_none() {
@@ -764,23 +785,24 @@ impl Pty {
         let err_context = || format!("failed to spawn terminal for {:?}", client_or_tab_index);
 
         // returns the terminal id
-        let terminal_action = match client_or_tab_index {
+        let (terminal_action, env) = match client_or_tab_index {
             ClientTabIndexOrPaneId::ClientId(client_id) => {
                 let mut terminal_action =
                     terminal_action.unwrap_or_else(|| self.get_default_terminal(None, None));
                 self.fill_cwd(&mut terminal_action, client_id);
-                terminal_action
-            },
-            ClientTabIndexOrPaneId::TabIndex(_) => {
-                terminal_action.unwrap_or_else(|| self.get_default_terminal(None, None))
+                (terminal_action, self.client_env(client_id))
             },
+            ClientTabIndexOrPaneId::TabIndex(_) => (
+                terminal_action.unwrap_or_else(|| self.get_default_terminal(None, None)),
+                BTreeMap::new(),
+            ),
             ClientTabIndexOrPaneId::PaneId(pane_id) => {
                 let mut terminal_action =
                     terminal_action.unwrap_or_else(|| self.get_default_terminal(None, None));
                 if let PaneId::Terminal(terminal_pane_id) = pane_id {
                     self.fill_cwd_from_pane_id(&mut terminal_action, &terminal_pane_id);
                 }
-                terminal_action
+                (terminal_action, BTreeMap::new())
             },
         };
         let (hold_on_start, hold_on_close) = match &terminal_action {
@@ -825,7 +847,12 @@ impl Pty {
             .as_mut()
             .context("no OS I/O interface found")
             .and_then(|os_input| {
-                os_input.spawn_terminal(terminal_action, quit_cb, self.default_editor.clone())
+                os_input.spawn_terminal_with_env(
+                    terminal_action,
+                    quit_cb,
+                    self.default_editor.clone(),
+                    env,
+                )
             })
             .with_context(err_context)?;
         let terminal_bytes = task::spawn({
@@ -869,6 +896,7 @@ impl Pty {
         let mut default_shell =
             default_shell.unwrap_or_else(|| self.get_default_terminal(cwd, None));
         self.fill_cwd(&mut default_shell, client_id);
+        let env = self.client_env(client_id);
         let extracted_run_instructions = layout.extract_run_instructions();
         let extracted_floating_run_instructions = floating_panes_layout
             .iter()
@@ -884,14 +912,14 @@ impl Pty {
                     // new_pane_pids
         for run_instruction in extracted_run_instructions {
             if let Some(new_pane_data) =
-                self.apply_run_instruction(run_instruction, default_shell.clone(), tab_index)?
+                self.apply_run_instruction(run_instruction, default_shell.clone(), tab_index, &env)?
             {
                 new_pane_pids.push(new_pane_data);
             }
         }
         for run_instruction in extracted_floating_run_instructions {
             if let Some(new_pane_data) =
-                self.apply_run_instruction(run_instruction, default_shell.clone(), tab_index)?
+                self.apply_run_instruction(run_instruction, default_shell.clone(), tab_index, &env)?
             {
                 new_floating_panes_pids.push(new_pane_data);
             }
@@ -993,6 +1021,7 @@ impl Pty {
         run_instruction: Option<Run>,
         default_shell: TerminalAction,
         tab_index: usize,
+        env: &BTreeMap<String, String>,
     ) -> Result<Option<(u32, bool, Option<RunCommand>, Result<i32>)>> {
         // terminal_id,
         // starts_held,
@@ -1060,7 +1089,12 @@ impl Pty {
                         .as_mut()
                         .context("no OS I/O interface found")
                         .with_context(err_context)?
-                        .spawn_terminal(cmd, quit_cb, self.default_editor.clone())
+                        .spawn_terminal_with_env(
+                            cmd,
+                            quit_cb,
+                            self.default_editor.clone(),
+                            env.clone(),
+                        )
                         .with_context(err_context)
                     {
                         Ok((terminal_id, pid_primary, child_fd)) => {
@@ -1092,7 +1126,12 @@ impl Pty {
                     .as_mut()
                     .context("no OS I/O interface found")
                     .with_context(err_context)?
-                    .spawn_terminal(shell, quit_cb, self.default_editor.clone())
+                    .spawn_terminal_with_env(
+                        shell,
+                        quit_cb,
+                        self.default_editor.clone(),
+                        env.clone(),
+                    )
                     .with_context(err_context)
                 {
                     Ok((terminal_id, pid_primary, child_fd)) => {
@@ -1115,10 +1154,11 @@ impl Pty {
                     .as_mut()
                     .context("no OS I/O interface found")
                     .with_context(err_context)?
-                    .spawn_terminal(
+                    .spawn_terminal_with_env(
                         TerminalAction::OpenFile(path_to_file, line_number, cwd),
                         quit_cb,
                         self.default_editor.clone(),
+                        env.clone(),
                     )
                     .with_context(err_context)
                 {
@@ -1142,7 +1182,12 @@ impl Pty {
                     .as_mut()
                     .context("no OS I/O interface found")
                     .with_context(err_context)?
-                    .spawn_terminal(default_shell.clone(), quit_cb, self.default_editor.clone())
+                    .spawn_terminal_with_env(
+                        default_shell.clone(),
+                        quit_cb,
+                        self.default_editor.clone(),
+                        env.clone(),
+                    )
                     .with_context(err_context)
                 {
                     Ok((terminal_id, pid_primary, child_fd)) => {
@@ -8,11 +8,11 @@ use crate::{
     plugins::PluginInstruction,
     pty::{ClientTabIndexOrPaneId, PtyInstruction},
     screen::ScreenInstruction,
-    ServerInstruction, SessionMetaData, SessionState,
+    ConnectedClientsRequest, ServerInstruction, SessionMetaData, SessionState,
 };
 use zellij_utils::{
     channels::SenderWithContext,
-    data::{Direction, Event, PluginCapabilities, ResizeStrategy},
+    data::{ClientRole, Direction, Event, PluginCapabilities, ResizeStrategy},
     errors::prelude::*,
     input::{
         actions::{Action, SearchDirection, SearchOption},
@@ -21,7 +21,8 @@ use zellij_utils::{
         layout::Layout,
     },
     ipc::{
-        ClientAttributes, ClientToServerMsg, ExitReason, IpcReceiverWithContext, ServerToClientMsg,
+        ClientAttributes, ClientToServerMsg, ClientType, ExitReason, IpcReceiverWithContext,
+        ServerToClientMsg,
     },
 };
 
@@ -800,10 +801,118 @@ pub(crate) fn route_action(
                 .send_to_screen(ScreenInstruction::RenameSession(name, client_id))
                 .with_context(err_context)?;
         },
+        Action::ListClients(json) => {
+            senders
+                .send_to_server(ServerInstruction::ListConnectedClients(
+                    ConnectedClientsRequest::Cli(client_id, json),
+                ))
+                .with_context(err_context)?;
+        },
+        Action::KickClient(kicked_client_id) => {
+            senders
+                .send_to_server(ServerInstruction::DisconnectClient(
+                    kicked_client_id,
+                    client_id,
+                ))
+                .with_context(err_context)?;
+            senders
+                .send_to_server(ServerInstruction::UnblockInputThread)
+                .with_context(err_context)?;
+        },
+        Action::SetClientRole(changed_client_id, role) => {
+            senders
+                .send_to_server(ServerInstruction::SetClientRole(
+                    changed_client_id,
+                    role,
+                    client_id,
+                ))
+                .with_context(err_context)?;
+            senders
+                .send_to_server(ServerInstruction::UnblockInputThread)
+                .with_context(err_context)?;
+        },
+        Action::FollowClient(followed_client_id) => {
+            senders
+                .send_to_screen(ScreenInstruction::FollowClient(
+                    client_id,
+                    followed_client_id,
+                ))
+                .with_context(err_context)?;
+            senders
+                .send_to_server(ServerInstruction::UnblockInputThread)
+                .with_context(err_context)?;
+        },
+        Action::UnfollowClient => {
+            senders
+                .send_to_screen(ScreenInstruction::UnfollowClient(client_id))
+                .with_context(err_context)?;
+            senders
+                .send_to_server(ServerInstruction::UnblockInputThread)
+                .with_context(err_context)?;
+        },
     }
     Ok(should_break)
 }
 
+fn role_can_perform(role: ClientRole, action: &Action) -> bool {
+    match role {
+        ClientRole::Owner => true,
+        ClientRole::Collaborator => !owner_only(action),
+        ClientRole::Viewer => reader_can_perform(action),
+    }
+}
+
+// ending the session and deciding what the other clients can do in it is up to its owners
+fn owner_only(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Quit | Action::KickClient(..) | Action::SetClientRole(..)
+    )
+}
+
+// read-only clients may only change their own view of the session or leave it, scrolling
+// moves the pane for everyone watching it
+fn reader_can_perform(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::SwitchToMode(..)
+            | Action::GoToTab(..)
+            | Action::GoToTabName(_, false)
+            | Action::GoToNextTab
+            | Action::GoToPreviousTab
+            | Action::ToggleTab
+            | Action::MoveFocus(..)
+            | Action::MoveFocusOrTab(..)
+            | Action::Detach
+            | Action::ListClients(..)
+            | Action::FollowClient(..)
+            | Action::UnfollowClient
+            | Action::NoOp
+    )
+}
+
+/// Whether a client with `role` may perform `action`. The input thread of the client waits for
+/// some actions to be carried out before reading more input, so it is unblocked when the
+/// action is dropped
+pub(crate) fn role_allows(
+    os_input: &dyn ServerOsApi,
+    client_id: ClientId,
+    role: ClientRole,
+    action: &Action,
+) -> Result<bool> {
+    if role_can_perform(role, action) {
+        return Ok(true);
+    }
+    log::debug!(
+        "Dropping {:?} from client {}, a {}",
+        action,
+        client_id,
+        role
+    );
+    os_input.send_to_client(client_id, ServerToClientMsg::UnblockInputThread)?;
+    Ok(false)
+}
+
 // this should only be used for one-off startup instructions
 macro_rules! send_to_screen_or_retry_queue {
     ($rlocked_sessions:expr, $message:expr, $instruction: expr, $retry_queue:expr) => {{
@@ -841,8 +950,43 @@ pub(crate) fn route_thread_main(
                 >|
                  -> Result<bool> {
                     let mut should_break = false;
+                    let (client_role, client_type, display_name, is_attached, is_following) = {
+                        let session_state =
+                            session_state.read().to_anyhow().with_context(err_context)?;
+                        (
+                            session_state.client_role(client_id),
+                            session_state.client_type(client_id),
+                            session_state.display_name(client_id),
+                            session_state.is_attached(client_id),
+                            session_state.is_following(client_id),
+                        )
+                    };
                     match instruction {
                         ClientToServerMsg::Action(action, maybe_pane_id, maybe_client_id) => {
+                            // anything a client following another one does breaks away, even
+                            // what its role drops
+                            let breaks_away =
+                                is_following && !matches!(action, Action::FollowClient(..));
+                            match rlocked_sessions.as_ref() {
+                                Some(rlocked_sessions) if is_attached && breaks_away => {
+                                    rlocked_sessions
+                                        .senders
+                                        .send_to_screen(ScreenInstruction::UnfollowClient(
+                                            client_id,
+                                        ))
+                                        .with_context(err_context)?;
+                                },
+                                _ => {},
+                            }
+                            match role_allows(&*os_input, client_id, client_role, &action) {
+                                Ok(true) => {},
+                                Ok(false) => return Ok(should_break),
+                                Err(_) => {
+                                    let _ =
+                                        to_server.send(ServerInstruction::RemoveClient(client_id));
+                                    return Ok(true);
+                                },
+                            }
                             let client_id = maybe_client_id.unwrap_or(client_id);
                             if let Some(rlocked_sessions) = rlocked_sessions.as_ref() {
                                 if let Action::SwitchToMode(input_mode) = action {
@@ -856,13 +1000,20 @@ pub(crate) fn route_thread_main(
                                         return Ok(true);
                                     }
                                 }
+                                // the session keeps the attributes of the client that created it,
+                                // mode updates should still reflect whether this one is read-only
+                                // and the name it goes by
+                                let mut client_attributes =
+                                    rlocked_sessions.client_attributes.clone();
+                                client_attributes.client_type = client_type;
+                                client_attributes.display_name = display_name;
                                 if route_action(
                                     action,
                                     client_id,
                                     maybe_pane_id.map(|p| PaneId::Terminal(p)),
                                     rlocked_sessions.senders.clone(),
                                     rlocked_sessions.capabilities.clone(),
-                                    rlocked_sessions.client_attributes.clone(),
+                                    client_attributes,
                                     rlocked_sessions.default_shell.clone(),
                                     rlocked_sessions.layout.clone(),
                                 )? {
@@ -940,6 +1091,15 @@ pub(crate) fn route_thread_main(
                             layout,
                             plugin_config,
                         ) => {
+                            // recorded here rather than in the server thread so that no input
+                            // from a read-only client can slip through before it is known
+                            let mut state = session_state
+                                .write()
+                                .to_anyhow()
+                                .with_context(err_context)?;
+                            let role = state.role_of_new_client(&client_attributes, true);
+                            state.set_client_role(client_id, role);
+                            drop(state);
                             let new_client_instruction = ServerInstruction::NewClient(
                                 client_attributes,
                                 cli_args,
@@ -958,6 +1118,13 @@ pub(crate) fn route_thread_main(
                             tab_position_to_focus,
                             pane_id_to_focus,
                         ) => {
+                            let mut state = session_state
+                                .write()
+                                .to_anyhow()
+                                .with_context(err_context)?;
+                            let role = state.role_of_new_client(&client_attributes, false);
+                            state.set_client_role(client_id, role);
+                            drop(state);
                             let attach_client_instruction = ServerInstruction::AttachClient(
                                 client_attributes,
                                 opts,
@@ -975,6 +1142,13 @@ pub(crate) fn route_thread_main(
                             let _ = to_server.send(ServerInstruction::RemoveClient(client_id));
                             return Ok(true);
                         },
+                        ClientToServerMsg::KillSession if client_role != ClientRole::Owner => {
+                            log::debug!(
+                                "Ignoring kill session request from client {}, a {}",
+                                client_id,
+                                client_role
+                            );
+                        },
                         ClientToServerMsg::KillSession => {
                             to_server
                                 .send(ServerInstruction::KillSession)
@@ -985,11 +1159,13 @@ pub(crate) fn route_thread_main(
                             should_break = true;
                         },
                         ClientToServerMsg::DetachSession(client_ids) => {
-                            let client_ids = if client_ids.len() == 0 {
-                                vec![client_id]
-                            } else {
-                                client_ids
-                            };
+                            // only owners can detach other clients
+                            let client_ids =
+                                if client_ids.len() == 0 || client_role != ClientRole::Owner {
+                                    vec![client_id]
+                                } else {
+                                    client_ids
+                                };
                             let _ = to_server.send(ServerInstruction::DetachSession(client_ids));
                             should_break = true;
                         },
@@ -999,6 +1175,35 @@ pub(crate) fn route_thread_main(
                         ClientToServerMsg::ServerMode => {
                             let _ = to_server.send(ServerInstruction::Mode(client_id));
                         },
+                        ClientToServerMsg::Prompt(prompt) => {
+                            let _ = to_server.send(ServerInstruction::Prompt(prompt, client_id));
+                        },
+                        ClientToServerMsg::Remote(remote, client_type) => {
+                            // the commands run over ssh get the role of a guest of the same type
+                            // rather than the one of the socket's owner
+                            let role = match client_type {
+                                ClientType::Reader => ClientRole::Viewer,
+                                ClientType::Writer => ClientRole::Collaborator,
+                            };
+                            let mut state = session_state
+                                .write()
+                                .to_anyhow()
+                                .with_context(err_context)?;
+                            state.set_client_role(client_id, role);
+                            state.set_remote_client(client_id, Some(remote));
+                        },
+                        ClientToServerMsg::Notify(message) => {
+                            if let Some(rlocked_sessions) = rlocked_sessions.as_ref() {
+                                rlocked_sessions
+                                    .senders
+                                    .send_to_plugin(PluginInstruction::Update(vec![(
+                                        None,
+                                        None,
+                                        Event::Notification(message),
+                                    )]))
+                                    .with_context(err_context)?;
+                            }
+                        },
                     }
                     Ok(should_break)
                 };
@@ -8,7 +8,8 @@ use std::str;
 use std::time::Duration;
 
 use zellij_utils::data::{
-    Direction, PaneManifest, PluginPermission, Resize, ResizeStrategy, SessionInfo,
+    ClientRole, ConnectedClientInfo, Direction, PaneManifest, PluginPermission, Resize,
+    ResizeStrategy, SessionInfo,
 };
 use zellij_utils::errors::prelude::*;
 use zellij_utils::input::command::RunCommand;
@@ -43,7 +44,7 @@ use crate::{
         loading_indication::LoadingIndication,
         overlay::{Overlay, OverlayWindow},
     },
-    ClientId, ServerInstruction,
+    ClientId, ConnectedClientsRequest, ServerInstruction,
 };
 use zellij_utils::{
     data::{Event, InputMode, ModeInfo, Palette, PaletteColor, PluginCapabilities, Style, TabInfo},
@@ -320,6 +321,10 @@ pub enum ScreenInstruction {
     ),
     DumpLayoutToHd,
     RenameSession(String, ClientId), // String -> new name
+    ListConnectedClients(Vec<ConnectedClientInfo>, ConnectedClientsRequest),
+    ChangeClientRole(ClientId, ClientRole),
+    FollowClient(ClientId, ClientId), // client following, client to follow
+    UnfollowClient(ClientId),
 }
 
 impl From<&ScreenInstruction> for ScreenContext {
@@ -500,6 +505,10 @@ impl From<&ScreenInstruction> for ScreenContext {
             ScreenInstruction::NewInPlacePluginPane(..) => ScreenContext::NewInPlacePluginPane,
             ScreenInstruction::DumpLayoutToHd => ScreenContext::DumpLayoutToHd,
             ScreenInstruction::RenameSession(..) => ScreenContext::RenameSession,
+            ScreenInstruction::ListConnectedClients(..) => ScreenContext::ListConnectedClients,
+            ScreenInstruction::ChangeClientRole(..) => ScreenContext::ChangeClientRole,
+            ScreenInstruction::FollowClient(..) => ScreenContext::FollowClient,
+            ScreenInstruction::UnfollowClient(..) => ScreenContext::UnfollowClient,
         }
     }
 }
@@ -565,6 +574,8 @@ pub(crate) struct Screen {
     serialize_pane_viewport: bool,
     scrollback_lines_to_serialize: Option<usize>,
     session_is_mirrored: bool,
+    /// The clients following another one around, by the client they follow
+    followed_clients: BTreeMap<ClientId, ClientId>,
     copy_options: CopyOptions,
     debug: bool,
     session_name: String,
@@ -623,6 +634,7 @@ impl Screen {
             draw_pane_frames,
             auto_layout,
             session_is_mirrored,
+            followed_clients: BTreeMap::new(),
             copy_options,
             debug,
             session_name,
@@ -1315,10 +1327,41 @@ impl Screen {
             self.tab_history.remove(&client_id);
         }
         self.connected_clients.borrow_mut().remove(&client_id);
+        self.followed_clients.remove(&client_id);
+        let followers: Vec<ClientId> = self
+            .followed_clients
+            .iter()
+            .filter(|(_, followed_client_id)| **followed_client_id == client_id)
+            .map(|(follower_id, _)| *follower_id)
+            .collect();
+        for follower_id in followers {
+            self.followed_clients.remove(&follower_id);
+            self.report_following(follower_id, false)
+                .with_context(err_context)?;
+            self.notify_client(
+                follower_id,
+                format!("{} left, stopped following", self.client_label(client_id)),
+            )
+            .with_context(err_context)?;
+        }
         self.log_and_report_session_state()
             .with_context(err_context)
     }
 
+    /// The names `client_ids` go by, for those that picked one
+    fn client_names(&self, client_ids: &[ClientId]) -> BTreeMap<ClientId, String> {
+        client_ids
+            .iter()
+            .filter_map(|client_id| {
+                self.mode_info
+                    .get(client_id)
+                    .unwrap_or(&self.default_mode_info)
+                    .display_name
+                    .clone()
+                    .map(|display_name| (*client_id, display_name))
+            })
+            .collect()
+    }
     pub fn generate_and_report_tab_state(&mut self) -> Result<Vec<TabInfo>> {
         let mut plugin_updates = vec![];
         let mut tab_infos_for_screen_state = BTreeMap::new();
@@ -1330,6 +1373,7 @@ impl Screen {
                 .map(|(c_id, _)| c_id)
                 .copied()
                 .collect();
+            let all_focused_client_names = self.client_names(&all_focused_clients);
             let (active_swap_layout_name, is_swap_layout_dirty) = tab.swap_layout_info();
             let tab_info_for_screen = TabInfo {
                 position: tab.position,
@@ -1340,6 +1384,7 @@ impl Screen {
                 is_sync_panes_active: tab.is_sync_panes_active(),
                 are_floating_panes_visible: tab.are_floating_panes_visible(),
                 other_focused_clients: all_focused_clients,
+                other_focused_client_names: all_focused_client_names,
                 active_swap_layout_name,
                 is_swap_layout_dirty,
             };
@@ -1360,6 +1405,7 @@ impl Screen {
                         .copied()
                         .collect()
                 };
+                let other_focused_client_names = self.client_names(&other_focused_clients);
                 let (active_swap_layout_name, is_swap_layout_dirty) = tab.swap_layout_info();
                 let tab_info_for_plugins = TabInfo {
                     position: tab.position,
@@ -1370,6 +1416,7 @@ impl Screen {
                     is_sync_panes_active: tab.is_sync_panes_active(),
                     are_floating_panes_visible: tab.are_floating_panes_visible(),
                     other_focused_clients,
+                    other_focused_client_names,
                     active_swap_layout_name,
                     is_swap_layout_dirty,
                 };
@@ -1404,11 +1451,13 @@ impl Screen {
         // generate own session info
         let pane_manifest = self.generate_and_report_pane_state()?;
         let tab_infos = self.generate_and_report_tab_state()?;
+        let connected_client_ids: Vec<ClientId> = self.active_tab_indices.keys().copied().collect();
         let session_info = SessionInfo {
             name: self.session_name.clone(),
             tabs: tab_infos,
             panes: pane_manifest,
-            connected_clients: self.active_tab_indices.keys().len(),
+            connected_clients: connected_client_ids.len(),
+            connected_client_names: self.client_names(&connected_client_ids),
             is_current_session: true,
         };
         self.bus
@@ -1595,6 +1644,160 @@ impl Screen {
 
         Ok(())
     }
+    /// Lets the client and its status bar know whether it can still type into the session
+    pub fn change_client_role(&mut self, client_id: ClientId, role: ClientRole) -> Result<()> {
+        let mut mode_info = self
+            .mode_info
+            .get(&client_id)
+            .unwrap_or(&self.default_mode_info)
+            .clone();
+        mode_info.read_only = role == ClientRole::Viewer;
+        self.change_mode(mode_info, client_id)
+            .with_context(|| format!("failed to change the role of client {}", client_id))
+    }
+    /// Has `client_id` move along with `followed_client_id`, to its active tab and focused pane,
+    /// until it stops following it
+    pub fn follow_client(
+        &mut self,
+        client_id: ClientId,
+        followed_client_id: ClientId,
+    ) -> Result<()> {
+        let err_context =
+            || format!("failed to have client {client_id} follow client {followed_client_id}");
+        let client_id = match self.attached_client_or_first(client_id) {
+            Some(client_id) => client_id,
+            None => return Ok(()),
+        };
+        if client_id == followed_client_id {
+            log::warn!("Client {} can't follow itself", client_id);
+            return Ok(());
+        }
+        if !self.active_tab_indices.contains_key(&followed_client_id) {
+            log::warn!("No attached client {} to follow", followed_client_id);
+            return Ok(());
+        }
+        self.followed_clients.insert(client_id, followed_client_id);
+        self.report_following(client_id, true)
+            .with_context(err_context)?;
+        self.notify_client(
+            client_id,
+            format!(
+                "FOLLOWING {}, press any key to stop",
+                self.client_label(followed_client_id)
+            ),
+        )
+        .with_context(err_context)?;
+        self.notify_client(
+            followed_client_id,
+            format!("{} is following you", self.client_label(client_id)),
+        )
+        .with_context(err_context)?;
+        self.move_followers().with_context(err_context)?;
+        Ok(())
+    }
+    /// Stops `client_id` from following another client, returns whether it was following one
+    pub fn unfollow_client(&mut self, client_id: ClientId) -> Result<bool> {
+        let err_context = || format!("failed to stop client {client_id} from following");
+        let client_id = match self.attached_client_or_first(client_id) {
+            Some(client_id) => client_id,
+            None => return Ok(false),
+        };
+        match self.followed_clients.remove(&client_id) {
+            Some(followed_client_id) => {
+                self.report_following(client_id, false)
+                    .with_context(err_context)?;
+                self.notify_client(
+                    followed_client_id,
+                    format!("{} stopped following you", self.client_label(client_id)),
+                )
+                .with_context(err_context)?;
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+    /// The active tab and focused pane of each client being followed, the followers only have
+    /// to move when these change
+    pub fn followed_client_positions(&self) -> BTreeMap<ClientId, (usize, Option<PaneId>)> {
+        self.followed_clients
+            .values()
+            .filter_map(|followed_client_id| {
+                self.get_active_tab(*followed_client_id).ok().map(|tab| {
+                    (
+                        *followed_client_id,
+                        (tab.position, tab.get_active_pane_id(*followed_client_id)),
+                    )
+                })
+            })
+            .collect()
+    }
+    /// Moves the clients following others to the tab and pane the clients they follow are on,
+    /// returns whether any of them moved.
+    ///
+    /// Fullscreen panes and scroll positions belong to tabs and panes, so they come along.
+    pub fn move_followers(&mut self) -> Result<bool> {
+        let err_context = || "failed to move the clients following others";
+        let mut moved = false;
+        let followed_clients: Vec<(ClientId, ClientId)> = self
+            .followed_clients
+            .iter()
+            .map(|(client_id, followed_client_id)| (*client_id, *followed_client_id))
+            .collect();
+        for (client_id, followed_client_id) in followed_clients {
+            let (tab_position, pane_id) = match self.get_active_tab(followed_client_id) {
+                Ok(tab) => (tab.position, tab.get_active_pane_id(followed_client_id)),
+                Err(_) => continue,
+            };
+            let client_tab_position = self.get_active_tab(client_id).map(|tab| tab.position).ok();
+            if client_tab_position != Some(tab_position) {
+                self.switch_active_tab(tab_position, None, true, client_id)
+                    .with_context(err_context)?;
+                moved = true;
+            }
+            if let Some(pane_id) = pane_id {
+                let tab = self
+                    .get_active_tab_mut(client_id)
+                    .with_context(err_context)?;
+                if tab.get_active_pane_id(client_id) != Some(pane_id) {
+                    tab.focus_pane_with_id(pane_id, false, client_id)
+                        .with_context(err_context)?;
+                    tab.set_force_render();
+                    moved = true;
+                }
+            }
+        }
+        Ok(moved)
+    }
+    // cli clients act on behalf of the first client, like they act on its tab
+    fn attached_client_or_first(&self, client_id: ClientId) -> Option<ClientId> {
+        if self.active_tab_indices.contains_key(&client_id) {
+            Some(client_id)
+        } else {
+            self.get_first_client_id()
+        }
+    }
+    /// How the other participants know `client_id`, eg. `alice (client 2)`
+    fn client_label(&self, client_id: ClientId) -> String {
+        match self.client_names(&[client_id]).remove(&client_id) {
+            Some(name) => format!("{} (client {})", name, client_id),
+            None => format!("client {}", client_id),
+        }
+    }
+    // the route of a client following another one breaks it away with whatever it sends next
+    fn report_following(&self, client_id: ClientId, is_following: bool) -> Result<()> {
+        self.bus
+            .senders
+            .send_to_server(ServerInstruction::SetFollowing(client_id, is_following))
+    }
+    fn notify_client(&self, client_id: ClientId, message: String) -> Result<()> {
+        self.bus
+            .senders
+            .send_to_plugin(PluginInstruction::Update(vec![(
+                None,
+                Some(client_id),
+                Event::Notification(message),
+            )]))
+    }
     pub fn change_mode_for_all_clients(&mut self, mode_info: ModeInfo) -> Result<()> {
         let err_context = || {
             format!(
@@ -1605,7 +1808,20 @@ impl Screen {
 
         let connected_client_ids: Vec<ClientId> = self.active_tab_indices.keys().copied().collect();
         for client_id in connected_client_ids {
-            self.change_mode(mode_info.clone(), client_id)
+            // read-only clients keep their view only indicator, and all of them their names
+            let mut mode_info = mode_info.clone();
+            mode_info.read_only = self
+                .mode_info
+                .get(&client_id)
+                .map(|mode_info| mode_info.read_only)
+                .unwrap_or(false);
+            mode_info.display_name = self
+                .mode_info
+                .get(&client_id)
+                .unwrap_or(&self.default_mode_info)
+                .display_name
+                .clone();
+            self.change_mode(mode_info, client_id)
                 .with_context(err_context)?;
         }
         Ok(())
@@ -2125,6 +2341,7 @@ pub(crate) fn screen_thread_main(
         // here we start caching resizes, so that we'll send them in bulk at the end of each event
         // when this cache is Dropped, for more information, see the comments in PtyWriter
         let _resize_cache = ResizeCache::new(thread_senders.clone());
+        let followed_client_positions = screen.followed_client_positions();
 
         match event {
             ScreenInstruction::PtyBytes(pid, vte_bytes) => {
@@ -3634,11 +3851,123 @@ pub(crate) fn screen_thread_main(
                 }
                 screen.unblock_input()?;
             },
+            ScreenInstruction::ListConnectedClients(mut connected_clients, request) => {
+                for connected_client in connected_clients.iter_mut() {
+                    connected_client.focused_pane = screen
+                        .get_active_tab(connected_client.client_id)
+                        .ok()
+                        .and_then(|tab| tab.get_active_pane_id(connected_client.client_id))
+                        .map(|pane_id| match pane_id {
+                            PaneId::Terminal(id) => (id, false),
+                            PaneId::Plugin(id) => (id, true),
+                        });
+                    connected_client.following = screen
+                        .followed_clients
+                        .get(&connected_client.client_id)
+                        .copied();
+                }
+                match request {
+                    ConnectedClientsRequest::Plugin(plugin_id, client_id) => {
+                        screen
+                            .bus
+                            .senders
+                            .send_to_plugin(PluginInstruction::Update(vec![(
+                                Some(plugin_id),
+                                Some(client_id),
+                                Event::ConnectedClients(connected_clients),
+                            )]))?;
+                    },
+                    ConnectedClientsRequest::Cli(client_id, json) => {
+                        let lines = if json {
+                            serde_json::to_string_pretty(&connected_clients)
+                                .with_context(|| "failed to serialize connected clients")?
+                                .lines()
+                                .map(|line| line.to_owned())
+                                .collect()
+                        } else {
+                            connected_clients_table(&connected_clients)
+                        };
+                        screen
+                            .bus
+                            .senders
+                            .send_to_server(ServerInstruction::Log(lines, client_id))?;
+                    },
+                }
+            },
+            ScreenInstruction::ChangeClientRole(client_id, role) => {
+                screen.change_client_role(client_id, role)?;
+                screen.render()?;
+            },
+            ScreenInstruction::FollowClient(client_id, followed_client_id) => {
+                screen.follow_client(client_id, followed_client_id)?;
+                screen.render()?;
+            },
+            ScreenInstruction::UnfollowClient(client_id) => {
+                screen.unfollow_client(client_id)?;
+            },
+        }
+        // wherever the clients being followed went, their followers go along
+        if screen.followed_client_positions() != followed_client_positions
+            && screen.move_followers()?
+        {
+            screen.render()?;
         }
     }
     Ok(())
 }
 
+/// The clients attached to the session as a table with one line per client, eg.
+/// `2  alice  10.0.0.5  viewer  80x24  terminal_1  1`
+fn connected_clients_table(connected_clients: &[ConnectedClientInfo]) -> Vec<String> {
+    let mut rows = vec![[
+        "CLIENT_ID".to_owned(),
+        "SSH_USER".to_owned(),
+        "PEER_ADDRESS".to_owned(),
+        "ROLE".to_owned(),
+        "SIZE".to_owned(),
+        "FOCUSED_PANE".to_owned(),
+        "FOLLOWING".to_owned(),
+    ]];
+    for client in connected_clients {
+        rows.push([
+            client.client_id.to_string(),
+            client.ssh_user.clone().unwrap_or_else(|| "-".to_owned()),
+            client
+                .peer_address
+                .clone()
+                .unwrap_or_else(|| "-".to_owned()),
+            client.role.to_string(),
+            format!("{}x{}", client.cols, client.rows),
+            match client.focused_pane {
+                Some((id, false)) => format!("terminal_{}", id),
+                Some((id, true)) => format!("plugin_{}", id),
+                None => "-".to_owned(),
+            },
+            client
+                .following
+                .map(|client_id| client_id.to_string())
+                .unwrap_or_else(|| "-".to_owned()),
+        ]);
+    }
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect()
+}
+
 #[path = "./unit/screen_tests.rs"]
 #[cfg(test)]
 mod screen_tests;
@@ -6,6 +6,7 @@ use zellij_utils::data::{client_id_to_colors, PaletteColor, Style};
 use zellij_utils::errors::prelude::*;
 use zellij_utils::pane_size::Viewport;
 
+use std::collections::HashMap;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 fn foreground_color(characters: &str, color: Option<PaletteColor>) -> Vec<TerminalCharacter> {
@@ -62,6 +63,30 @@ fn background_color(characters: &str, color: Option<PaletteColor>) -> Vec<Termin
     colored_string
 }
 
+// the client's primary color behind its name, in its secondary color
+fn client_label(
+    characters: &str,
+    colors: Option<(PaletteColor, PaletteColor)>,
+) -> Vec<TerminalCharacter> {
+    let mut styles = CharacterStyles::new();
+    styles.reset_all();
+    let styles = match colors {
+        Some((primary_color, secondary_color)) => styles
+            .background(Some(AnsiCode::from(primary_color)))
+            .foreground(Some(AnsiCode::from(secondary_color)))
+            .bold(Some(AnsiCode::On)),
+        None => styles.bold(Some(AnsiCode::On)),
+    };
+    characters
+        .chars()
+        .map(|character| TerminalCharacter {
+            character,
+            styles,
+            width: character.width().unwrap_or(0),
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum ExitStatus {
     Code(i32),
@@ -72,6 +97,8 @@ pub struct FrameParams {
     pub focused_client: Option<ClientId>,
     pub is_main_client: bool,
     pub other_focused_clients: Vec<ClientId>,
+    /// The names the focused clients go by, for those that picked one
+    pub client_names: HashMap<ClientId, String>,
     pub style: Style,
     pub color: Option<PaletteColor>,
     pub other_cursors_exist_in_session: bool,
@@ -91,6 +118,7 @@ pub struct PaneFrame {
     pub is_main_client: bool,
     pub other_cursors_exist_in_session: bool,
     pub other_focused_clients: Vec<ClientId>,
+    pub client_names: HashMap<ClientId, String>,
     exit_status: Option<ExitStatus>,
     is_first_run: bool,
     pane_is_stacked_over: bool,
@@ -114,6 +142,7 @@ impl PaneFrame {
             focused_client: frame_params.focused_client,
             is_main_client: frame_params.is_main_client,
             other_focused_clients: frame_params.other_focused_clients,
+            client_names: frame_params.client_names,
             other_cursors_exist_in_session: frame_params.other_cursors_exist_in_session,
             exit_status: None,
             is_first_run: false,
@@ -138,6 +167,18 @@ impl PaneFrame {
         let color = client_id_to_colors(client_id, self.style.colors);
         background_color(" ", color.map(|c| c.0))
     }
+    /// The client's name in its colors, its cursor when it did not pick one
+    fn client_name_or_cursor(&self, client_id: ClientId) -> (Vec<TerminalCharacter>, usize) {
+        match self.client_names.get(&client_id) {
+            Some(name) => {
+                let label = format!(" {} ", name);
+                let colors = client_id_to_colors(client_id, self.style.colors);
+                let width = label.width();
+                (client_label(&label, colors), width)
+            },
+            None => (self.client_cursor(client_id), 1),
+        }
+    }
     fn get_corner(&self, corner: &'static str) -> &'static str {
         let corner = if !self.should_draw_pane_frames
             && (corner == boundary_type::TOP_LEFT || corner == boundary_type::TOP_RIGHT)
@@ -237,10 +278,11 @@ impl PaneFrame {
         let mut short_indication = foreground_color(short_indication_text, self.color);
         let mut short_indication_len = short_indication_text.width();
         for client_id in &self.other_focused_clients {
-            let mut text = self.client_cursor(*client_id);
-            full_indication_len += 2;
+            let (mut name, name_len) = self.client_name_or_cursor(*client_id);
+            full_indication_len += name_len + 1;
             full_indication.push(EMPTY_TERMINAL_CHARACTER);
-            full_indication.append(&mut text.clone());
+            full_indication.append(&mut name);
+            let mut text = self.client_cursor(*client_id);
             short_indication_len += 2;
             short_indication.append(&mut text);
         }
@@ -285,13 +327,14 @@ impl PaneFrame {
         let mut short_indication = vec![];
         let mut short_indication_len = 0;
         for client_id in &self.other_focused_clients {
-            let mut text = self.client_cursor(*client_id);
-            full_indication_len += 2;
+            let (mut name, name_len) = self.client_name_or_cursor(*client_id);
+            full_indication_len += name_len + 1;
             full_indication.push(EMPTY_TERMINAL_CHARACTER);
-            full_indication.append(&mut text.clone());
-            middle_indication_len += 2;
+            full_indication.append(&mut name.clone());
+            middle_indication_len += name_len + 1;
             middle_indication.push(EMPTY_TERMINAL_CHARACTER);
-            middle_indication.append(&mut text.clone());
+            middle_indication.append(&mut name);
+            let mut text = self.client_cursor(*client_id);
             short_indication_len += 2;
             short_indication.push(EMPTY_TERMINAL_CHARACTER);
             short_indication.append(&mut text);
@@ -6,14 +6,35 @@ use crate::ui::pane_boundaries_frame::FrameParams;
 use crate::ClientId;
 use std::collections::HashMap;
 use zellij_utils::data::{
-    client_id_to_colors, single_client_color, InputMode, PaletteColor, Style,
+    client_id_to_colors, single_client_color, InputMode, ModeInfo, PaletteColor, Style,
 };
 use zellij_utils::errors::prelude::*;
+
+/// The names `clients` go by, as their mode info carries them
+pub fn client_names(
+    clients: &[ClientId],
+    mode_info: &HashMap<ClientId, ModeInfo>,
+    default_mode_info: &ModeInfo,
+) -> HashMap<ClientId, String> {
+    clients
+        .iter()
+        .filter_map(|client_id| {
+            mode_info
+                .get(client_id)
+                .unwrap_or(default_mode_info)
+                .display_name
+                .clone()
+                .map(|display_name| (*client_id, display_name))
+        })
+        .collect()
+}
+
 pub struct PaneContentsAndUi<'a> {
     pane: &'a mut Box<dyn Pane>,
     output: &'a mut Output,
     style: Style,
     focused_clients: Vec<ClientId>,
+    client_names: HashMap<ClientId, String>,
     multiple_users_exist_in_session: bool,
     z_index: Option<usize>,
     pane_is_stacked_under: bool,
@@ -27,6 +48,7 @@ impl<'a> PaneContentsAndUi<'a> {
         output: &'a mut Output,
         style: Style,
         active_panes: &HashMap<ClientId, PaneId>,
+        client_names: &HashMap<ClientId, String>,
         multiple_users_exist_in_session: bool,
         z_index: Option<usize>,
         pane_is_stacked_under: bool,
@@ -39,11 +61,17 @@ impl<'a> PaneContentsAndUi<'a> {
             .map(|(c_id, _p_id)| *c_id)
             .collect();
         focused_clients.sort_unstable();
+        let client_names = client_names
+            .iter()
+            .filter(|(c_id, _name)| focused_clients.contains(c_id))
+            .map(|(c_id, name)| (*c_id, name.clone()))
+            .collect();
         PaneContentsAndUi {
             pane,
             output,
             style,
             focused_clients,
+            client_names,
             multiple_users_exist_in_session,
             z_index,
             pane_is_stacked_under,
@@ -200,6 +228,7 @@ impl<'a> PaneContentsAndUi<'a> {
                 focused_client,
                 is_main_client: pane_focused_for_client_id,
                 other_focused_clients: vec![],
+                client_names: HashMap::new(),
                 style: self.style,
                 color: frame_color,
                 other_cursors_exist_in_session: false,
@@ -212,6 +241,7 @@ impl<'a> PaneContentsAndUi<'a> {
                 focused_client,
                 is_main_client: pane_focused_for_client_id,
                 other_focused_clients,
+                client_names: self.client_names.clone(),
                 style: self.style,
                 color: frame_color,
                 other_cursors_exist_in_session: self.multiple_users_exist_in_session,
@@ -0,0 +1,74 @@
+use super::SessionState;
+use zellij_utils::data::ClientRole;
+use zellij_utils::ipc::{ClientAttributes, ClientType, RemoteClient};
+
+fn remote_client_attributes(client_type: ClientType) -> ClientAttributes {
+    ClientAttributes {
+        client_type,
+        remote: Some(RemoteClient {
+            user: "alice".to_owned(),
+            peer_addr: Some("10.0.0.5".to_owned()),
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn remote_writer_joins_a_session_with_no_attached_owner() {
+    let mut session_state = SessionState::new();
+    let owner_client_id = session_state.new_client();
+    session_state.set_client_role(owner_client_id, ClientRole::Owner);
+    session_state.remove_client(owner_client_id);
+    assert_eq!(
+        session_state.role_of_new_client(&remote_client_attributes(ClientType::Writer), false),
+        ClientRole::Collaborator,
+        "the owner being away doesn't make guests owners"
+    );
+    assert_eq!(
+        session_state.role_of_new_client(&remote_client_attributes(ClientType::Reader), false),
+        ClientRole::Viewer
+    );
+}
+
+#[test]
+fn owners_create_the_session_or_attach_from_its_machine() {
+    let session_state = SessionState::new();
+    assert_eq!(
+        session_state.role_of_new_client(&remote_client_attributes(ClientType::Writer), true),
+        ClientRole::Owner
+    );
+    assert_eq!(
+        session_state.role_of_new_client(&ClientAttributes::default(), false),
+        ClientRole::Owner
+    );
+}
+
+#[test]
+fn clients_stop_following_when_they_leave() {
+    let mut session_state = SessionState::new();
+    let client_id = session_state.new_client();
+    session_state.set_following(client_id, true);
+    assert!(session_state.is_following(client_id));
+
+    session_state.remove_client(client_id);
+    let client_id = session_state.new_client();
+    assert!(
+        !session_state.is_following(client_id),
+        "a client reusing the id of one that left doesn't follow anyone"
+    );
+}
+
+#[test]
+fn prompts_are_only_answered_with_their_secret_and_once() {
+    let mut session_state = SessionState::new();
+    let asking_client_id = session_state.new_client();
+    let secret = session_state.new_prompt_secret(asking_client_id);
+
+    assert!(!session_state.take_prompt_secret(asking_client_id, "guessed"));
+    assert!(
+        !session_state.take_prompt_secret(asking_client_id + 1, &secret),
+        "the secret only answers the prompt it was made for"
+    );
+    assert!(session_state.take_prompt_secret(asking_client_id, &secret));
+    assert!(!session_state.take_prompt_secret(asking_client_id, &secret));
+}
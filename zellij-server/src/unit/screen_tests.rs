@@ -3123,3 +3123,172 @@ pub fn screen_can_move_pane_to_a_new_tab_left() {
     }
     assert_snapshot!(format!("{}", snapshot_count));
 }
+
+#[test]
+pub fn connected_clients_are_listed_as_a_table() {
+    use super::connected_clients_table;
+    use zellij_utils::data::{ClientRole, ConnectedClientInfo};
+    let connected_clients = vec![
+        ConnectedClientInfo {
+            client_id: 1,
+            rows: 40,
+            cols: 120,
+            focused_pane: Some((0, false)),
+            is_current_client: true,
+            ..Default::default()
+        },
+        ConnectedClientInfo {
+            client_id: 2,
+            ssh_user: Some("alice".to_owned()),
+            peer_address: Some("10.0.0.5".to_owned()),
+            role: ClientRole::Viewer,
+            rows: 24,
+            cols: 80,
+            focused_pane: Some((3, true)),
+            following: Some(1),
+            is_current_client: false,
+        },
+    ];
+    assert_eq!(
+        connected_clients_table(&connected_clients),
+        vec![
+            "CLIENT_ID  SSH_USER  PEER_ADDRESS  ROLE          SIZE    FOCUSED_PANE  FOLLOWING",
+            "1          -         -             collaborator  120x40  terminal_0    -",
+            "2          alice     10.0.0.5      viewer        80x24   plugin_3      1",
+        ]
+    );
+}
+
+#[test]
+pub fn changing_client_role_updates_its_view_only_mode() {
+    use zellij_utils::data::ClientRole;
+    let size = Size {
+        cols: 121,
+        rows: 20,
+    };
+    let mut screen = create_new_screen(size);
+    new_tab(&mut screen, 1, 0);
+
+    screen
+        .change_client_role(1, ClientRole::Viewer)
+        .expect("TEST");
+    assert!(
+        screen.mode_info.get(&1).unwrap().read_only,
+        "viewers only get to watch"
+    );
+
+    screen
+        .change_client_role(1, ClientRole::Collaborator)
+        .expect("TEST");
+    assert!(
+        !screen.mode_info.get(&1).unwrap().read_only,
+        "collaborators can type again"
+    );
+}
+
+#[test]
+pub fn tab_state_names_the_clients_focused_on_each_tab() {
+    use std::collections::BTreeMap;
+    let size = Size {
+        cols: 121,
+        rows: 20,
+    };
+    let mut screen = create_new_screen(size);
+    new_tab(&mut screen, 1, 0);
+    screen.add_client(2).expect("TEST");
+    let mut mode_info = ModeInfo::default();
+    mode_info.display_name = Some("alice".to_owned());
+    screen.change_mode(mode_info, 2).expect("TEST");
+
+    let tab_infos = screen.generate_and_report_tab_state().expect("TEST");
+    assert_eq!(
+        tab_infos[0].other_focused_client_names,
+        BTreeMap::from([(2, "alice".to_owned())]),
+        "only the clients that picked a name are named"
+    );
+}
+
+#[test]
+pub fn followers_move_to_the_tab_and_pane_of_the_client_they_follow() {
+    let size = Size {
+        cols: 121,
+        rows: 20,
+    };
+    let mut screen = create_new_screen(size);
+    screen.session_is_mirrored = false;
+    new_tab(&mut screen, 1, 0);
+    new_tab(&mut screen, 2, 1);
+    screen.add_client(2).expect("TEST");
+    screen.switch_tab_prev(None, true, 2).expect("TEST");
+
+    screen.follow_client(2, 1).expect("TEST");
+    assert_eq!(
+        screen.get_active_tab(2).unwrap().position,
+        1,
+        "the follower joins the tab of the client it follows"
+    );
+
+    let followed_client_positions = screen.followed_client_positions();
+    screen
+        .get_active_tab_mut(1)
+        .unwrap()
+        .new_pane(PaneId::Terminal(3), None, None, None, Some(1))
+        .unwrap();
+    assert_ne!(
+        screen.followed_client_positions(),
+        followed_client_positions,
+        "the followers move when the client they follow focuses another pane"
+    );
+    assert!(screen.move_followers().expect("TEST"));
+    assert_eq!(
+        screen.get_active_tab(2).unwrap().get_active_pane_id(2),
+        Some(PaneId::Terminal(3)),
+        "the follower focuses the pane the client it follows focuses"
+    );
+
+    assert!(screen.unfollow_client(2).expect("TEST"));
+    screen.switch_tab_prev(None, true, 1).expect("TEST");
+    assert!(!screen.move_followers().expect("TEST"));
+    assert_eq!(
+        screen.get_active_tab(2).unwrap().position,
+        1,
+        "the client stays where it is once it stopped following"
+    );
+}
+
+#[test]
+pub fn actions_dropped_for_the_client_role_still_unblock_its_input() {
+    use crate::route::role_allows;
+    use zellij_utils::data::ClientRole;
+    let os_input = FakeInputOutput::default();
+
+    assert!(!role_allows(&os_input, 1, ClientRole::Viewer, &Action::CloseFocus).expect("TEST"));
+    assert!(matches!(
+        os_input
+            .server_to_client_messages
+            .lock()
+            .unwrap()
+            .get(&1)
+            .map(|messages| messages.as_slice()),
+        Some([ServerToClientMsg::UnblockInputThread])
+    ));
+
+    assert!(
+        !role_allows(&os_input, 3, ClientRole::Viewer, &Action::ScrollUp).expect("TEST"),
+        "scrolling would move the pane for everyone watching it"
+    );
+
+    assert!(role_allows(&os_input, 2, ClientRole::Viewer, &Action::GoToNextTab).expect("TEST"));
+    assert!(
+        role_allows(&os_input, 2, ClientRole::Collaborator, &Action::CloseFocus).expect("TEST")
+    );
+    assert!(
+        os_input
+            .server_to_client_messages
+            .lock()
+            .unwrap()
+            .get(&2)
+            .is_none(),
+        "actions the client may perform are left to unblock it once carried out"
+    );
+}
@@ -694,6 +694,40 @@ pub fn rename_session(name: &str) {
     unsafe { host_run_plugin_command() };
 }
 
+/// Answers the prompt `prompt_id` of the configuration a dialog was opened with, `None` if the
+/// user dismissed it
+pub fn answer_prompt(prompt_id: u32, answer: Option<&str>) {
+    let plugin_command = PluginCommand::AnswerPrompt(prompt_id, answer.map(|a| a.to_owned()));
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
+/// Asks for the clients attached to the session, they come back with a `ConnectedClients` event
+pub fn list_connected_clients() {
+    let plugin_command = PluginCommand::ListConnectedClients;
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
+/// Disconnects the client `client_id` from the session
+pub fn disconnect_client(client_id: ClientId) {
+    let plugin_command = PluginCommand::DisconnectClient(client_id);
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
+/// Makes the client `client_id` an owner, a collaborator or a viewer of the session, this only
+/// works for plugins of the session's owners
+pub fn set_client_role(client_id: ClientId, role: ClientRole) {
+    let plugin_command = PluginCommand::SetClientRole(client_id, role);
+    let protobuf_plugin_command: ProtobufPluginCommand = plugin_command.try_into().unwrap();
+    object_to_stdout(&protobuf_plugin_command.encode_to_vec());
+    unsafe { host_run_plugin_command() };
+}
+
 // Utility Functions
 
 #[allow(unused)]
@@ -11,7 +11,7 @@ pub struct Event {
     pub name: i32,
     #[prost(
         oneof = "event::Payload",
-        tags = "2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15"
+        tags = "2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17"
     )]
     pub payload: ::core::option::Option<event::Payload>,
 }
@@ -48,6 +48,10 @@ pub mod event {
         RunCommandResultPayload(super::RunCommandResultPayload),
         #[prost(message, tag = "15")]
         WebRequestResultPayload(super::WebRequestResultPayload),
+        #[prost(string, tag = "16")]
+        NotificationPayload(::prost::alloc::string::String),
+        #[prost(message, tag = "17")]
+        ConnectedClientsPayload(super::ConnectedClientsPayload),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -84,6 +88,36 @@ pub struct WebRequestResultPayload {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConnectedClientsPayload {
+    #[prost(message, repeated, tag = "1")]
+    pub clients: ::prost::alloc::vec::Vec<ConnectedClientInfo>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConnectedClientInfo {
+    #[prost(uint32, tag = "1")]
+    pub client_id: u32,
+    #[prost(string, optional, tag = "2")]
+    pub ssh_user: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub peer_address: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(enumeration = "ClientRole", tag = "4")]
+    pub role: i32,
+    #[prost(uint32, tag = "5")]
+    pub rows: u32,
+    #[prost(uint32, tag = "6")]
+    pub cols: u32,
+    #[prost(uint32, optional, tag = "7")]
+    pub focused_pane_id: ::core::option::Option<u32>,
+    #[prost(bool, tag = "8")]
+    pub focused_pane_is_plugin: bool,
+    #[prost(bool, tag = "9")]
+    pub is_current_client: bool,
+    #[prost(uint32, optional, tag = "10")]
+    pub following: ::core::option::Option<u32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ContextItem {
     #[prost(string, tag = "1")]
     pub name: ::prost::alloc::string::String,
@@ -172,6 +206,8 @@ pub struct SessionManifest {
     pub connected_clients: u32,
     #[prost(bool, tag = "5")]
     pub is_current_session: bool,
+    #[prost(message, repeated, tag = "6")]
+    pub connected_client_names: ::prost::alloc::vec::Vec<ClientName>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -252,6 +288,16 @@ pub struct TabInfo {
     pub active_swap_layout_name: ::core::option::Option<::prost::alloc::string::String>,
     #[prost(bool, tag = "10")]
     pub is_swap_layout_dirty: bool,
+    #[prost(message, repeated, tag = "11")]
+    pub other_focused_client_names: ::prost::alloc::vec::Vec<ClientName>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClientName {
+    #[prost(uint32, tag = "1")]
+    pub client_id: u32,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -266,6 +312,10 @@ pub struct ModeUpdatePayload {
     pub arrow_fonts_support: bool,
     #[prost(string, optional, tag = "5")]
     pub session_name: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(bool, tag = "6")]
+    pub read_only: bool,
+    #[prost(string, optional, tag = "7")]
+    pub display_name: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -320,6 +370,10 @@ pub enum EventType {
     SessionUpdate = 16,
     RunCommandResult = 17,
     WebRequestResult = 18,
+    /// / A message for everyone in the session, eg. a file was uploaded over ssh
+    Notification = 19,
+    /// / The clients attached to the session, as requested by the plugin
+    ConnectedClients = 20,
 }
 impl EventType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -347,6 +401,8 @@ impl EventType {
             EventType::SessionUpdate => "SessionUpdate",
             EventType::RunCommandResult => "RunCommandResult",
             EventType::WebRequestResult => "WebRequestResult",
+            EventType::Notification => "Notification",
+            EventType::ConnectedClients => "ConnectedClients",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -371,6 +427,8 @@ impl EventType {
             "SessionUpdate" => Some(Self::SessionUpdate),
             "RunCommandResult" => Some(Self::RunCommandResult),
             "WebRequestResult" => Some(Self::WebRequestResult),
+            "Notification" => Some(Self::Notification),
+            "ConnectedClients" => Some(Self::ConnectedClients),
             _ => None,
         }
     }
@@ -406,6 +464,35 @@ impl CopyDestination {
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum ClientRole {
+    Collaborator = 0,
+    Viewer = 1,
+    Owner = 2,
+}
+impl ClientRole {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ClientRole::Collaborator => "Collaborator",
+            ClientRole::Viewer => "Viewer",
+            ClientRole::Owner => "Owner",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "Collaborator" => Some(Self::Collaborator),
+            "Viewer" => Some(Self::Viewer),
+            "Owner" => Some(Self::Owner),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum MouseEventName {
     MouseScrollUp = 0,
     MouseScrollDown = 1,
@@ -5,7 +5,7 @@ pub struct PluginCommand {
     pub name: i32,
     #[prost(
         oneof = "plugin_command::Payload",
-        tags = "2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46"
+        tags = "2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49"
     )]
     pub payload: ::core::option::Option<plugin_command::Payload>,
 }
@@ -104,6 +104,12 @@ pub mod plugin_command {
         DeleteDeadSessionPayload(::prost::alloc::string::String),
         #[prost(string, tag = "46")]
         RenameSessionPayload(::prost::alloc::string::String),
+        #[prost(message, tag = "47")]
+        AnswerPromptPayload(super::AnswerPromptPayload),
+        #[prost(uint32, tag = "48")]
+        DisconnectClientPayload(u32),
+        #[prost(message, tag = "49")]
+        SetClientRolePayload(super::SetClientRolePayload),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -232,6 +238,22 @@ pub struct MovePayload {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AnswerPromptPayload {
+    #[prost(uint32, tag = "1")]
+    pub prompt_id: u32,
+    #[prost(string, optional, tag = "2")]
+    pub answer: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetClientRolePayload {
+    #[prost(uint32, tag = "1")]
+    pub client_id: u32,
+    #[prost(enumeration = "super::event::ClientRole", tag = "2")]
+    pub role: i32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct IdAndNewName {
     /// pane id or tab index
     #[prost(uint32, tag = "1")]
@@ -318,6 +340,10 @@ pub enum CommandName {
     DeleteDeadSession = 73,
     DeleteAllDeadSessions = 74,
     RenameSession = 75,
+    AnswerPrompt = 76,
+    ListConnectedClients = 77,
+    DisconnectClient = 78,
+    SetClientRole = 79,
 }
 impl CommandName {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -402,6 +428,10 @@ impl CommandName {
             CommandName::DeleteDeadSession => "DeleteDeadSession",
             CommandName::DeleteAllDeadSessions => "DeleteAllDeadSessions",
             CommandName::RenameSession => "RenameSession",
+            CommandName::AnswerPrompt => "AnswerPrompt",
+            CommandName::ListConnectedClients => "ListConnectedClients",
+            CommandName::DisconnectClient => "DisconnectClient",
+            CommandName::SetClientRole => "SetClientRole",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -483,6 +513,10 @@ impl CommandName {
             "DeleteDeadSession" => Some(Self::DeleteDeadSession),
             "DeleteAllDeadSessions" => Some(Self::DeleteAllDeadSessions),
             "RenameSession" => Some(Self::RenameSession),
+            "AnswerPrompt" => Some(Self::AnswerPrompt),
+            "ListConnectedClients" => Some(Self::ListConnectedClients),
+            "DisconnectClient" => Some(Self::DisconnectClient),
+            "SetClientRole" => Some(Self::SetClientRole),
             _ => None,
         }
     }
@@ -1,4 +1,4 @@
-use crate::data::{Direction, InputMode, Resize};
+use crate::data::{ClientId, ClientRole, Direction, InputMode, Resize};
 use crate::pane_size::Size;
 use crate::setup::Setup;
 use crate::ssh::Ssh;
@@ -66,6 +66,10 @@ pub struct CliArgs {
     #[clap(long, overrides_with = "config_dir", env = ZELLIJ_CONFIG_DIR_ENV, value_parser)]
     pub config_dir: Option<PathBuf>,
 
+    /// Name to show the other participants of a shared session on the panes you focus
+    #[clap(long, value_parser)]
+    pub name: Option<String>,
+
     #[clap(subcommand)]
     pub command: Option<Command>,
 
@@ -547,6 +551,30 @@ pub enum CliAction {
     RenameSession {
         name: String,
     },
+    /// List the clients attached to the session: who they are, where they connect from and
+    /// what they look at
+    ListClients {
+        /// Print the list as JSON
+        #[clap(long, value_parser)]
+        json: bool,
+    },
+    /// Disconnect a client from the session, see `list-clients` for the ids
+    KickClient {
+        client_id: ClientId,
+    },
+    /// Change what a client may do in the session, only its owners can do this
+    /// [owner|collaborator|viewer]
+    SetClientRole {
+        client_id: ClientId,
+        role: ClientRole,
+    },
+    /// Move along with another client, to its active tab and focused pane, until any key is
+    /// pressed, see `list-clients` for the ids
+    FollowClient {
+        client_id: ClientId,
+    },
+    /// Stop following another client
+    UnfollowClient,
 }
 
 #[derive(Clone)]
@@ -500,6 +500,10 @@ pub enum Event {
         Vec<SessionInfo>,
         Vec<(String, Duration)>, // resurrectable sessions
     ),
+    /// A message for everyone in the session, eg. a file was uploaded over ssh
+    Notification(String),
+    /// The clients attached to the session, as requested with `list_connected_clients`
+    ConnectedClients(Vec<ConnectedClientInfo>),
     RunCommandResult(Option<i32>, Vec<u8>, Vec<u8>, BTreeMap<String, String>), // exit_code, STDOUT, STDERR,
     // context
     WebRequestResult(
@@ -735,6 +739,9 @@ pub struct ModeInfo {
     pub style: Style,
     pub capabilities: PluginCapabilities,
     pub session_name: Option<String>,
+    pub read_only: bool,
+    /// The name the client goes by in the session, `None` when it did not pick one
+    pub display_name: Option<String>,
 }
 
 impl ModeInfo {
@@ -758,6 +765,8 @@ pub struct SessionInfo {
     pub tabs: Vec<TabInfo>,
     pub panes: PaneManifest,
     pub connected_clients: usize,
+    /// The names the connected clients go by, for those that picked one
+    pub connected_client_names: BTreeMap<ClientId, String>,
     pub is_current_session: bool,
 }
 
@@ -805,6 +814,8 @@ pub struct TabInfo {
     pub is_sync_panes_active: bool,
     pub are_floating_panes_visible: bool,
     pub other_focused_clients: Vec<ClientId>,
+    /// The names of those of `other_focused_clients` that picked one
+    pub other_focused_client_names: BTreeMap<ClientId, String>,
     pub active_swap_layout_name: Option<String>,
     /// Whether the user manually changed the layout, moving out of the swap layout scheme
     pub is_swap_layout_dirty: bool,
@@ -875,6 +886,69 @@ pub struct PaneInfo {
     pub is_selectable: bool,
 }
 
+/// What a client may do in the session
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientRole {
+    /// Can type into the session and manage its panes
+    Collaborator,
+    /// Can only watch the session
+    Viewer,
+    /// Can also end the session and decide what the other clients may do in it
+    Owner,
+}
+
+impl Default for ClientRole {
+    fn default() -> Self {
+        ClientRole::Collaborator
+    }
+}
+
+impl fmt::Display for ClientRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientRole::Collaborator => write!(f, "collaborator"),
+            ClientRole::Viewer => write!(f, "viewer"),
+            ClientRole::Owner => write!(f, "owner"),
+        }
+    }
+}
+
+impl FromStr for ClientRole {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "collaborator" | "Collaborator" => Ok(ClientRole::Collaborator),
+            "viewer" | "Viewer" => Ok(ClientRole::Viewer),
+            "owner" | "Owner" => Ok(ClientRole::Owner),
+            _ => Err(format!(
+                "Unknown client role '{}', expected owner, collaborator or viewer",
+                s
+            )),
+        }
+    }
+}
+
+/// Contains the information about a client attached to the session
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ConnectedClientInfo {
+    pub client_id: ClientId,
+    /// The user the client logged in as over ssh, `None` for clients running on this machine
+    pub ssh_user: Option<String>,
+    /// The address the client connected from over ssh
+    pub peer_address: Option<String>,
+    pub role: ClientRole,
+    /// The size of the client's terminal
+    pub rows: usize,
+    pub cols: usize,
+    /// The pane the client is focused on, as (pane_id, is_plugin)
+    pub focused_pane: Option<(u32, bool)>,
+    /// The client this one follows to whichever tab and pane it focuses
+    pub following: Option<ClientId>,
+    /// Whether this is the client that asked for the list
+    pub is_current_client: bool,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct PluginIds {
     pub plugin_id: u32,
@@ -1104,5 +1178,9 @@ pub enum PluginCommand {
         Vec<u8>,                  // body
         BTreeMap<String, String>, // context
     ),
-    RenameSession(String), // String -> new session name
+    RenameSession(String),             // String -> new session name
+    AnswerPrompt(u32, Option<String>), // prompt id, the chosen answer (None if dismissed)
+    ListConnectedClients,
+    DisconnectClient(ClientId),
+    SetClientRole(ClientId, ClientRole),
 }
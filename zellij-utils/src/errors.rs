@@ -349,6 +349,10 @@ pub enum ScreenContext {
     NewInPlacePluginPane,
     DumpLayoutToHd,
     RenameSession,
+    ListConnectedClients,
+    ChangeClientRole,
+    FollowClient,
+    UnfollowClient,
 }
 
 /// Stack call representations corresponding to the different types of [`PtyInstruction`]s.
@@ -369,6 +373,7 @@ pub enum PtyContext {
     DumpLayout,
     LogLayoutToHd,
     FillPluginCwd,
+    SetClientEnv,
     Exit,
 }
 
@@ -414,6 +419,7 @@ pub enum ClientContext {
     SwitchSession,
     SetSynchronisedOutput,
     ServerMode,
+    PromptAnswer,
 }
 
 /// Stack call representations corresponding to the different types of [`ServerInstruction`]s.
@@ -433,6 +439,12 @@ pub enum ServerContext {
     Log,
     SwitchSession,
     Mode,
+    Prompt,
+    PromptAnswer,
+    ListConnectedClients,
+    DisconnectClient,
+    SetClientRole,
+    SetFollowing,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
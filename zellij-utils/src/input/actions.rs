@@ -7,7 +7,7 @@ use super::layout::{
 };
 use crate::cli::CliAction;
 use crate::data::InputMode;
-use crate::data::{Direction, Resize};
+use crate::data::{ClientId, ClientRole, Direction, Resize};
 use crate::home::{find_default_config_dir, get_layout_dir};
 use crate::input::config::{Config, ConfigError, KdlError};
 use crate::input::options::OnForceClose;
@@ -254,6 +254,16 @@ pub enum Action {
     BreakPaneRight,
     BreakPaneLeft,
     RenameSession(String),
+    /// List the clients attached to the session
+    ListClients(bool), // bool is whether to print JSON
+    /// Disconnect a client from the session
+    KickClient(ClientId),
+    /// Change what a client may do in the session
+    SetClientRole(ClientId, ClientRole),
+    /// Track the active tab and focused pane of another client
+    FollowClient(ClientId),
+    /// Stop following another client
+    UnfollowClient,
 }
 
 impl Action {
@@ -560,6 +570,13 @@ impl Action {
                 Ok(vec![Action::LaunchPlugin(run_plugin, floating, in_place)])
             },
             CliAction::RenameSession { name } => Ok(vec![Action::RenameSession(name)]),
+            CliAction::ListClients { json } => Ok(vec![Action::ListClients(json)]),
+            CliAction::KickClient { client_id } => Ok(vec![Action::KickClient(client_id)]),
+            CliAction::SetClientRole { client_id, role } => {
+                Ok(vec![Action::SetClientRole(client_id, role)])
+            },
+            CliAction::FollowClient { client_id } => Ok(vec![Action::FollowClient(client_id)]),
+            CliAction::UnfollowClient => Ok(vec![Action::UnfollowClient]),
         }
     }
 }
@@ -13,6 +13,7 @@ use super::plugins::{PluginsConfig, PluginsConfigError};
 use super::theme::{Themes, UiConfig};
 use crate::cli::{CliArgs, Command};
 use crate::envs::EnvironmentVariables;
+use crate::ssh::Ssh;
 use crate::{home, setup};
 
 const DEFAULT_CONFIG_FILE_NAME: &str = "config.kdl";
@@ -28,6 +29,7 @@ pub struct Config {
     pub plugins: PluginsConfig,
     pub ui: UiConfig,
     pub env: EnvironmentVariables,
+    pub ssh: Ssh,
 }
 
 #[derive(Error, Debug)]
@@ -232,6 +234,7 @@ mod config_test {
     use crate::input::options::{Clipboard, OnForceClose};
     use crate::input::plugins::{PluginConfig, PluginType, PluginsConfig};
     use crate::input::theme::{FrameConfig, Theme, Themes, UiConfig};
+    use crate::ssh::SshAuthMethod;
     use std::collections::HashMap;
     use std::io::Write;
     use tempfile::tempdir;
@@ -678,4 +681,165 @@ mod config_test {
             "Env variables defined in config"
         );
     }
+
+    #[test]
+    fn can_define_ssh_configuration_in_config_file() {
+        let config_contents = r#"
+            ssh {
+                port 2222
+                bind_address "::1" "/run/zmate.sock"
+                relay "relay.example.com:6222"
+                inactivity_timeout 600
+                auth_rejection_time 1
+                shutdown_grace_period 30
+                escape_char "%"
+                skip_lobby true
+                approve_guests true
+                approval_timeout 30
+                auth_methods "publickey" "keyboard-interactive"
+                host_key "/etc/zellij/ssh_host_ed25519_key"
+                authorized_keys "/home/alice/.ssh/zellij_authorized_keys"
+                allow_auth_none false
+                sftp_root "/home/alice/shared"
+                allow_forward "localhost:3000" "localhost:8080"
+                accept_env "TERM" "LC_*"
+                max_connections 20
+                max_clients_per_session 4
+                ban_minutes 30
+                allow_from "10.0.0.0/8" "192.168.1.0/24"
+                deny_from "10.0.0.66"
+            }
+        "#;
+        let config = Config::from_kdl(config_contents, None).unwrap();
+        assert_eq!(config.ssh.port, Some(2222), "Port defined in config");
+        assert_eq!(
+            config.ssh.bind_addresses,
+            vec!["::1".to_string(), "/run/zmate.sock".to_string()],
+            "Bind addresses defined in config"
+        );
+        assert_eq!(
+            config.ssh.relay,
+            Some("relay.example.com:6222".to_string()),
+            "Relay defined in config"
+        );
+        assert_eq!(
+            config.ssh.inactivity_timeout,
+            Some(600),
+            "Inactivity timeout defined in config"
+        );
+        assert_eq!(config.ssh.auth_rejection_time, Some(1));
+        assert_eq!(config.ssh.auth_rejection_time_initial, None);
+        assert_eq!(
+            config.ssh.shutdown_grace_period,
+            Some(30),
+            "Shutdown grace period defined in config"
+        );
+        assert_eq!(
+            config.ssh.escape_char,
+            Some("%".to_string()),
+            "Escape character defined in config"
+        );
+        assert!(config.ssh.skip_lobby, "Lobby skipped in config");
+        assert!(
+            config.ssh.approve_guests,
+            "Guest approval enabled in config"
+        );
+        assert_eq!(
+            config.ssh.approval_timeout,
+            Some(30),
+            "Approval timeout defined in config"
+        );
+        assert_eq!(
+            config.ssh.auth_methods,
+            vec![SshAuthMethod::Publickey, SshAuthMethod::KeyboardInteractive],
+            "Auth methods defined in config"
+        );
+        assert_eq!(
+            config.ssh.host_keys,
+            vec![PathBuf::from("/etc/zellij/ssh_host_ed25519_key")],
+            "Host keys defined in config"
+        );
+        assert_eq!(
+            config.ssh.authorized_keys,
+            Some(PathBuf::from("/home/alice/.ssh/zellij_authorized_keys")),
+            "Authorized keys defined in config"
+        );
+        assert!(!config.ssh.allow_auth_none, "Auth none disabled in config");
+        assert_eq!(
+            config.ssh.sftp_root,
+            Some(PathBuf::from("/home/alice/shared")),
+            "Sftp root defined in config"
+        );
+        assert_eq!(
+            config.ssh.allowed_forwards,
+            vec!["localhost:3000".to_string(), "localhost:8080".to_string()],
+            "Allowed forwards defined in config"
+        );
+        assert_eq!(
+            config.ssh.accepted_env,
+            vec!["TERM".to_string(), "LC_*".to_string()],
+            "Accepted env defined in config"
+        );
+        assert_eq!(
+            config.ssh.max_connections,
+            Some(20),
+            "Max connections defined in config"
+        );
+        assert_eq!(
+            config.ssh.max_clients_per_session,
+            Some(4),
+            "Max clients per session defined in config"
+        );
+        assert_eq!(config.ssh.max_connections_per_ip, None);
+        assert_eq!(config.ssh.ban_minutes, Some(30), "Ban defined in config");
+        assert_eq!(
+            config.ssh.allow_from,
+            vec!["10.0.0.0/8".to_string(), "192.168.1.0/24".to_string()],
+            "Allowed ranges defined in config"
+        );
+        assert_eq!(
+            config.ssh.deny_from,
+            vec!["10.0.0.66".to_string()],
+            "Denied ranges defined in config"
+        );
+    }
+
+    #[test]
+    fn invalid_ssh_configuration_is_an_error() {
+        for config_contents in [
+            "ssh { port 70000; }",
+            "ssh { auth_methods \"hostbased\"; }",
+            "ssh { max_connections -1; }",
+        ] {
+            assert!(
+                Config::from_kdl(config_contents, None).is_err(),
+                "{} is refused",
+                config_contents
+            );
+        }
+    }
+
+    #[test]
+    fn ssh_cli_flags_take_precedence_over_the_config_file() {
+        let config_contents = r#"
+            ssh {
+                port 2222
+                inactivity_timeout 600
+                allow_forward "localhost:3000"
+            }
+        "#;
+        let config = Config::from_kdl(config_contents, None).unwrap();
+        let cli_ssh = Ssh {
+            port: Some(2223),
+            ..Default::default()
+        };
+        let merged = config.ssh.merge(cli_ssh);
+        assert_eq!(merged.port, Some(2223), "Port from the cli");
+        assert_eq!(
+            merged.inactivity_timeout,
+            Some(600),
+            "Inactivity timeout from the config file"
+        );
+        assert_eq!(merged.allowed_forwards, vec!["localhost:3000".to_string()]);
+    }
 }
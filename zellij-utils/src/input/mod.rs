@@ -20,7 +20,7 @@ mod not_wasm {
     use crate::{
         data::{CharOrArrow, Direction, InputMode, Key, ModeInfo, PluginCapabilities},
         envs,
-        ipc::ClientAttributes,
+        ipc::{ClientAttributes, ClientType},
     };
     use termwiz::input::{InputEvent, InputParser, KeyCode, KeyEvent, Modifiers};
 
@@ -40,6 +40,8 @@ mod not_wasm {
             style: attributes.style,
             capabilities,
             session_name,
+            read_only: attributes.client_type == ClientType::Reader,
+            display_name: attributes.display_name.clone(),
         }
     }
 
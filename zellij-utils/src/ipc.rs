@@ -13,6 +13,7 @@ use log::warn;
 use nix::unistd::dup;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fmt::{Display, Error, Formatter},
     io::{self, Write},
     marker::PhantomData,
@@ -32,17 +33,43 @@ pub struct Session {
 }
 
 // How do we want to connect to a session?
-#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ClientType {
+    // can watch the session, but its input is dropped by the server
     Reader,
     Writer,
 }
 
+impl Default for ClientType {
+    fn default() -> Self {
+        ClientType::Writer
+    }
+}
+
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct ClientAttributes {
     pub size: Size,
     pub style: Style,
     pub keybinds: Keybinds,
+    #[serde(default)]
+    pub client_type: ClientType,
+    /// Variables of the client's terminal (eg. `TERM`, `LANG`) to set in the panes it opens,
+    /// empty when the client runs on the same machine as the server
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Who connected the client over ssh, `None` for local clients
+    #[serde(default)]
+    pub remote: Option<RemoteClient>,
+    /// What the other participants of the session know the client by, `None` to go by its id
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+/// The ssh login behind a client
+#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RemoteClient {
+    pub user: String,
+    pub peer_addr: Option<String>,
 }
 
 #[derive(Default, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -62,6 +89,17 @@ impl PixelDimensions {
     }
 }
 
+/// A question for the people in the session, asked in a floating dialog of the first client
+/// attached to it. The answer is one of `choices`, or none when the dialog was dismissed or
+/// `timeout_secs` passed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    pub title: String,
+    pub message: String,
+    pub choices: Vec<String>,
+    pub timeout_secs: u64,
+}
+
 // Types of messages sent from the client to the server
 #[allow(clippy::large_enum_variant)]
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -91,6 +129,9 @@ pub enum ClientToServerMsg {
     ConnStatus,
     ListClients,
     ServerMode,
+    Notify(String), // shown to everyone in the session through the plugins
+    Prompt(Prompt), // answered with ServerToClientMsg::PromptAnswer
+    Remote(RemoteClient, ClientType), // the ssh login the actions that follow are sent for
 }
 
 // Types of messages sent from the server to the client
@@ -106,6 +147,7 @@ pub enum ServerToClientMsg {
     LogError(Vec<String>),
     SwitchSession(ConnectToSession),
     ServerMode(ServerMode),
+    PromptAnswer(Option<String>),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -115,6 +157,7 @@ pub enum ExitReason {
     ForceDetached,
     CannotAttach,
     Disconnect,
+    Kicked,
     Error(String),
 }
 
@@ -131,6 +174,7 @@ impl Display for ExitReason {
                 f,
                 "Session attached to another client. Use --force flag to force connect."
             ),
+            Self::Kicked => write!(f, "You were disconnected from the session"),
             Self::Disconnect => {
                 let session_tip = match crate::envs::get_session_name() {
                     Ok(name) => format!("`zellij attach {}`", name),
@@ -1,7 +1,7 @@
 mod kdl_layout_parser;
 use crate::data::{
-    Direction, InputMode, Key, Palette, PaletteColor, PaneInfo, PaneManifest, PermissionType,
-    Resize, SessionInfo, TabInfo,
+    ClientId, ClientRole, Direction, InputMode, Key, Palette, PaletteColor, PaneInfo, PaneManifest,
+    PermissionType, Resize, SessionInfo, TabInfo,
 };
 use crate::envs::EnvironmentVariables;
 use crate::home::{find_default_config_dir, get_layout_dir};
@@ -12,6 +12,7 @@ use crate::input::options::{Clipboard, OnForceClose, Options};
 use crate::input::permission::{GrantedPermission, PermissionCache};
 use crate::input::plugins::{PluginConfig, PluginTag, PluginType, PluginsConfig};
 use crate::input::theme::{FrameConfig, Theme, Themes, UiConfig};
+use crate::ssh::{Ssh, SshAuthMethod};
 use kdl_layout_parser::KdlLayoutParser;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use strum::IntoEnumIterator;
@@ -71,6 +72,7 @@ macro_rules! parse_kdl_action_arguments {
                 "PreviousSwapLayout" => Ok(Action::PreviousSwapLayout),
                 "NextSwapLayout" => Ok(Action::NextSwapLayout),
                 "Clear" => Ok(Action::ClearScreen),
+                "UnfollowClient" => Ok(Action::UnfollowClient),
                 _ => Err(ConfigError::new_kdl_error(
                     format!("Unsupported action: {:?}", $action_name),
                     $action_node.span().offset(),
@@ -506,6 +508,35 @@ impl Action {
                 Ok(Action::Search(search_direction))
             },
             "RenameSession" => Ok(Action::RenameSession(string)),
+            "SetClientRole" => {
+                // eg. "2 viewer"
+                let mut words = string.split_whitespace();
+                let client_id = words.next().and_then(|word| word.parse().ok());
+                let role = words
+                    .next()
+                    .and_then(|word| ClientRole::from_str(word).ok());
+                match (client_id, role) {
+                    (Some(client_id), Some(role)) => Ok(Action::SetClientRole(client_id, role)),
+                    _ => Err(ConfigError::new_kdl_error(
+                        format!(
+                            "Expected a client id and a role (owner, collaborator or viewer), got: '{}'",
+                            string
+                        ),
+                        action_node.span().offset(),
+                        action_node.span().len(),
+                    )),
+                }
+            },
+            "FollowClient" => {
+                let client_id = string.trim().parse().map_err(|_| {
+                    ConfigError::new_kdl_error(
+                        format!("Expected a client id, got: '{}'", string),
+                        action_node.span().offset(),
+                        action_node.span().len(),
+                    )
+                })?;
+                Ok(Action::FollowClient(client_id))
+            },
             _ => Err(ConfigError::new_kdl_error(
                 format!("Unsupported action: {}", action_name),
                 action_node.span().offset(),
@@ -996,6 +1027,19 @@ impl TryFrom<(&KdlNode, &Options)> for Action {
                 action_arguments,
                 kdl_action
             ),
+            "SetClientRole" => parse_kdl_action_char_or_string_arguments!(
+                action_name,
+                action_arguments,
+                kdl_action
+            ),
+            "FollowClient" => parse_kdl_action_char_or_string_arguments!(
+                action_name,
+                action_arguments,
+                kdl_action
+            ),
+            "UnfollowClient" => {
+                parse_kdl_action_arguments!(action_name, action_arguments, kdl_action)
+            },
             _ => Err(ConfigError::new_kdl_error(
                 format!("Unsupported action: {}", action_name).into(),
                 kdl_action.span().offset(),
@@ -1773,6 +1817,10 @@ impl Config {
             let config_env = EnvironmentVariables::from_kdl(&env_config)?;
             config.env = config.env.merge(config_env);
         }
+        if let Some(kdl_ssh_config) = kdl_config.get("ssh") {
+            let config_ssh = Ssh::from_kdl(&kdl_ssh_config)?;
+            config.ssh = config.ssh.merge(config_ssh);
+        }
         Ok(config)
     }
 }
@@ -1828,6 +1876,123 @@ impl UiConfig {
     }
 }
 
+impl Ssh {
+    pub fn from_kdl(kdl_ssh_config: &KdlNode) -> Result<Ssh, ConfigError> {
+        let kdl_ssh_options = match kdl_ssh_config.children() {
+            Some(kdl_ssh_options) => kdl_ssh_options,
+            None => return Ok(Ssh::default()),
+        };
+        let port = kdl_ssh_count(kdl_ssh_options, "port")?;
+        let bind_addresses = kdl_ssh_string_list(kdl_ssh_options, "bind_address")?;
+        let relay = kdl_property_first_arg_as_string_or_error!(kdl_ssh_options, "relay")
+            .map(|(string, _entry)| string.to_string());
+        let inactivity_timeout = kdl_ssh_count(kdl_ssh_options, "inactivity_timeout")?;
+        let auth_rejection_time = kdl_ssh_count(kdl_ssh_options, "auth_rejection_time")?;
+        let auth_rejection_time_initial =
+            kdl_ssh_count(kdl_ssh_options, "auth_rejection_time_initial")?;
+        let shutdown_grace_period = kdl_ssh_count(kdl_ssh_options, "shutdown_grace_period")?;
+        let escape_char =
+            kdl_property_first_arg_as_string_or_error!(kdl_ssh_options, "escape_char")
+                .map(|(string, _entry)| string.to_string());
+        let skip_lobby = kdl_property_first_arg_as_bool_or_error!(kdl_ssh_options, "skip_lobby")
+            .map(|(v, _)| v)
+            .unwrap_or(false);
+        let approve_guests =
+            kdl_property_first_arg_as_bool_or_error!(kdl_ssh_options, "approve_guests")
+                .map(|(v, _)| v)
+                .unwrap_or(false);
+        let approval_timeout = kdl_ssh_count(kdl_ssh_options, "approval_timeout")?;
+        let mut auth_methods = vec![];
+        if let Some(kdl_auth_methods) = kdl_ssh_options.get("auth_methods") {
+            for entry in kdl_auth_methods.entries() {
+                let method = entry.value().as_string().ok_or_else(|| {
+                    kdl_parsing_error!("Authentication methods must be strings".into(), entry)
+                })?;
+                auth_methods.push(
+                    SshAuthMethod::from_str(method).map_err(|e| kdl_parsing_error!(e, entry))?,
+                );
+            }
+        }
+        let host_keys = kdl_ssh_string_list(kdl_ssh_options, "host_key")?
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        let authorized_keys =
+            kdl_property_first_arg_as_string_or_error!(kdl_ssh_options, "authorized_keys")
+                .map(|(string, _entry)| PathBuf::from(string));
+        let allow_auth_none =
+            kdl_property_first_arg_as_bool_or_error!(kdl_ssh_options, "allow_auth_none")
+                .map(|(v, _)| v)
+                .unwrap_or(false);
+        let sftp_root = kdl_property_first_arg_as_string_or_error!(kdl_ssh_options, "sftp_root")
+            .map(|(string, _entry)| PathBuf::from(string));
+        let allowed_forwards = kdl_ssh_string_list(kdl_ssh_options, "allow_forward")?;
+        let accepted_env = kdl_ssh_string_list(kdl_ssh_options, "accept_env")?;
+        let max_connections = kdl_ssh_count(kdl_ssh_options, "max_connections")?;
+        let max_connections_per_ip = kdl_ssh_count(kdl_ssh_options, "max_connections_per_ip")?;
+        let max_clients_per_session = kdl_ssh_count(kdl_ssh_options, "max_clients_per_session")?;
+        let max_auth_failures = kdl_ssh_count(kdl_ssh_options, "max_auth_failures")?;
+        let ban_minutes = kdl_ssh_count(kdl_ssh_options, "ban_minutes")?;
+        let allow_from = kdl_ssh_string_list(kdl_ssh_options, "allow_from")?;
+        let deny_from = kdl_ssh_string_list(kdl_ssh_options, "deny_from")?;
+        Ok(Ssh {
+            port,
+            bind_addresses,
+            stdio: false,
+            relay,
+            inactivity_timeout,
+            auth_rejection_time,
+            auth_rejection_time_initial,
+            shutdown_grace_period,
+            escape_char,
+            skip_lobby,
+            approve_guests,
+            approval_timeout,
+            auth_methods,
+            host_keys,
+            authorized_keys,
+            allow_auth_none,
+            sftp_root,
+            allowed_forwards,
+            accepted_env,
+            max_connections,
+            max_connections_per_ip,
+            max_clients_per_session,
+            max_auth_failures,
+            ban_minutes,
+            allow_from,
+            deny_from,
+            command: None,
+        })
+    }
+}
+
+// a non-negative integer option of the ssh block
+fn kdl_ssh_count<T: TryFrom<i64>>(
+    kdl_ssh_options: &KdlDocument,
+    name: &str,
+) -> Result<Option<T>, ConfigError> {
+    match kdl_property_first_arg_as_i64_or_error!(kdl_ssh_options, name) {
+        Some((count, entry)) => Ok(Some(T::try_from(count).map_err(|_| {
+            kdl_parsing_error!(format!("Invalid value for {}: {}", name, count), entry)
+        })?)),
+        None => Ok(None),
+    }
+}
+
+fn kdl_ssh_string_list(
+    kdl_ssh_options: &KdlDocument,
+    name: &str,
+) -> Result<Vec<String>, ConfigError> {
+    match kdl_ssh_options.get(name) {
+        Some(kdl_node) => Ok(kdl_string_arguments!(kdl_node)
+            .iter()
+            .map(|string| string.to_string())
+            .collect()),
+        None => Ok(vec![]),
+    }
+}
+
 impl Themes {
     pub fn from_kdl(themes_from_kdl: &KdlNode) -> Result<Self, ConfigError> {
         let mut themes: HashMap<String, Theme> = HashMap::new();
@@ -1978,12 +2143,14 @@ impl SessionInfo {
             .and_then(|p| p.children())
             .map(|p| PaneManifest::decode_from_kdl(p))
             .ok_or("Failed to parse panes")?;
+        let connected_client_names = decode_client_names(&kdl_document, "connected_client_names");
         let is_current_session = name == current_session_name;
         Ok(SessionInfo {
             name,
             tabs,
             panes,
             connected_clients,
+            connected_client_names,
             is_current_session,
         })
     }
@@ -2013,6 +2180,11 @@ impl SessionInfo {
         kdl_document.nodes_mut().push(tabs);
         kdl_document.nodes_mut().push(panes);
         kdl_document.nodes_mut().push(connected_clients);
+        if let Some(connected_client_names) =
+            encode_client_names("connected_client_names", &self.connected_client_names)
+        {
+            kdl_document.nodes_mut().push(connected_client_names);
+        }
         kdl_document.fmt();
         kdl_document.to_string()
     }
@@ -2077,6 +2249,8 @@ impl TabInfo {
                 }
             }
         }
+        let other_focused_client_names =
+            decode_client_names(kdl_document, "other_focused_client_names");
         let active_swap_layout_name = optional_string_node!("active_swap_layout_name");
         let is_swap_layout_dirty = bool_node!("is_swap_layout_dirty");
         Ok(TabInfo {
@@ -2088,6 +2262,7 @@ impl TabInfo {
             is_sync_panes_active,
             are_floating_panes_visible,
             other_focused_clients,
+            other_focused_client_names,
             active_swap_layout_name,
             is_swap_layout_dirty,
         })
@@ -2131,6 +2306,13 @@ impl TabInfo {
             kdl_doucment.nodes_mut().push(other_focused_clients);
         }
 
+        if let Some(other_focused_client_names) = encode_client_names(
+            "other_focused_client_names",
+            &self.other_focused_client_names,
+        ) {
+            kdl_doucment.nodes_mut().push(other_focused_client_names);
+        }
+
         if let Some(active_swap_layout_name) = self.active_swap_layout_name.as_ref() {
             let mut active_swap_layout = KdlNode::new("active_swap_layout_name");
             active_swap_layout.push(active_swap_layout_name.to_string());
@@ -2145,6 +2327,41 @@ impl TabInfo {
     }
 }
 
+// client names are kept as `node_name { client 1 "alice"; }`
+fn decode_client_names(kdl_document: &KdlDocument, node_name: &str) -> BTreeMap<ClientId, String> {
+    let mut client_names = BTreeMap::new();
+    if let Some(clients) = kdl_document.get(node_name).and_then(|n| n.children()) {
+        for client in clients.nodes() {
+            let mut entries = client.entries().iter();
+            let client_id = entries.next().and_then(|e| e.value().as_i64());
+            let name = entries.next().and_then(|e| e.value().as_string());
+            if let (Some(client_id), Some(name)) = (client_id, name) {
+                client_names.insert(client_id as ClientId, name.to_owned());
+            }
+        }
+    }
+    client_names
+}
+
+fn encode_client_names(
+    node_name: &str,
+    client_names: &BTreeMap<ClientId, String>,
+) -> Option<KdlNode> {
+    if client_names.is_empty() {
+        return None;
+    }
+    let mut node = KdlNode::new(node_name);
+    let mut clients = KdlDocument::new();
+    for (client_id, name) in client_names {
+        let mut client = KdlNode::new("client");
+        client.push(*client_id as i64);
+        client.push(name.clone());
+        clients.nodes_mut().push(client);
+    }
+    node.set_children(clients);
+    Some(node)
+}
+
 impl PaneManifest {
     pub fn decode_from_kdl(kdl_doucment: &KdlDocument) -> Self {
         let mut panes: HashMap<usize, Vec<PaneInfo>> = HashMap::new();
@@ -2479,6 +2696,7 @@ fn serialize_and_deserialize_session_info_with_data() {
                 is_sync_panes_active: false,
                 are_floating_panes_visible: true,
                 other_focused_clients: vec![2, 3],
+                other_focused_client_names: BTreeMap::new(),
                 active_swap_layout_name: Some("BASE".to_owned()),
                 is_swap_layout_dirty: true,
             },
@@ -2491,12 +2709,14 @@ fn serialize_and_deserialize_session_info_with_data() {
                 is_sync_panes_active: true,
                 are_floating_panes_visible: true,
                 other_focused_clients: vec![2, 3],
+                other_focused_client_names: BTreeMap::new(),
                 active_swap_layout_name: None,
                 is_swap_layout_dirty: false,
             },
         ],
         panes: PaneManifest { panes },
         connected_clients: 2,
+        connected_client_names: BTreeMap::new(),
         is_current_session: false,
     };
     let serialized = session_info.to_string();
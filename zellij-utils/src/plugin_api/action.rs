@@ -1229,6 +1229,11 @@ impl TryFrom<Action> for ProtobufAction {
             | Action::Deny
             | Action::Copy
             | Action::DumpLayout
+            | Action::ListClients(..)
+            | Action::KickClient(..)
+            | Action::SetClientRole(..)
+            | Action::FollowClient(..)
+            | Action::UnfollowClient
             | Action::SkipConfirm(..) => Err("Unsupported action"),
         }
     }
@@ -1,12 +1,13 @@
 pub use super::generated_api::api::{
     action::{Action as ProtobufAction, Position as ProtobufPosition},
     event::{
-        event::Payload as ProtobufEventPayload, CopyDestination as ProtobufCopyDestination,
-        Event as ProtobufEvent, EventNameList as ProtobufEventNameList,
-        EventType as ProtobufEventType, InputModeKeybinds as ProtobufInputModeKeybinds,
-        KeyBind as ProtobufKeyBind, ModeUpdatePayload as ProtobufModeUpdatePayload,
-        PaneInfo as ProtobufPaneInfo, PaneManifest as ProtobufPaneManifest,
-        ResurrectableSession as ProtobufResurrectableSession,
+        event::Payload as ProtobufEventPayload, ClientName as ProtobufClientName,
+        ClientRole as ProtobufClientRole, ConnectedClientInfo as ProtobufConnectedClientInfo,
+        CopyDestination as ProtobufCopyDestination, Event as ProtobufEvent,
+        EventNameList as ProtobufEventNameList, EventType as ProtobufEventType,
+        InputModeKeybinds as ProtobufInputModeKeybinds, KeyBind as ProtobufKeyBind,
+        ModeUpdatePayload as ProtobufModeUpdatePayload, PaneInfo as ProtobufPaneInfo,
+        PaneManifest as ProtobufPaneManifest, ResurrectableSession as ProtobufResurrectableSession,
         SessionManifest as ProtobufSessionManifest, TabInfo as ProtobufTabInfo, *,
     },
     input_mode::InputMode as ProtobufInputMode,
@@ -14,14 +15,15 @@ pub use super::generated_api::api::{
     style::Style as ProtobufStyle,
 };
 use crate::data::{
-    CopyDestination, Event, EventType, InputMode, Key, ModeInfo, Mouse, PaneInfo, PaneManifest,
-    PermissionStatus, PluginCapabilities, SessionInfo, Style, TabInfo,
+    ClientId, ClientRole, ConnectedClientInfo, CopyDestination, Event, EventType, InputMode, Key,
+    ModeInfo, Mouse, PaneInfo, PaneManifest, PermissionStatus, PluginCapabilities, SessionInfo,
+    Style, TabInfo,
 };
 
 use crate::errors::prelude::*;
 use crate::input::actions::Action;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -228,6 +230,22 @@ impl TryFrom<ProtobufEvent> for Event {
                 },
                 _ => Err("Malformed payload for the WebRequestResult Event"),
             },
+            Some(ProtobufEventType::Notification) => match protobuf_event.payload {
+                Some(ProtobufEventPayload::NotificationPayload(message)) => {
+                    Ok(Event::Notification(message))
+                },
+                _ => Err("Malformed payload for the Notification Event"),
+            },
+            Some(ProtobufEventType::ConnectedClients) => match protobuf_event.payload {
+                Some(ProtobufEventPayload::ConnectedClientsPayload(connected_clients_payload)) => {
+                    let mut clients = vec![];
+                    for protobuf_client in connected_clients_payload.clients {
+                        clients.push(ConnectedClientInfo::try_from(protobuf_client)?);
+                    }
+                    Ok(Event::ConnectedClients(clients))
+                },
+                _ => Err("Malformed payload for the ConnectedClients Event"),
+            },
             None => Err("Unknown Protobuf Event"),
         }
     }
@@ -425,6 +443,24 @@ impl TryFrom<Event> for ProtobufEvent {
                     )),
                 })
             },
+            Event::Notification(message) => Ok(ProtobufEvent {
+                name: ProtobufEventType::Notification as i32,
+                payload: Some(event::Payload::NotificationPayload(message)),
+            }),
+            Event::ConnectedClients(clients) => {
+                let mut protobuf_clients = vec![];
+                for client in clients {
+                    protobuf_clients.push(client.try_into()?);
+                }
+                Ok(ProtobufEvent {
+                    name: ProtobufEventType::ConnectedClients as i32,
+                    payload: Some(event::Payload::ConnectedClientsPayload(
+                        ConnectedClientsPayload {
+                            clients: protobuf_clients,
+                        },
+                    )),
+                })
+            },
         }
     }
 }
@@ -453,6 +489,7 @@ impl TryFrom<SessionInfo> for ProtobufSessionManifest {
                 .collect(),
             connected_clients: session_info.connected_clients as u32,
             is_current_session: session_info.is_current_session,
+            connected_client_names: protobuf_client_names(session_info.connected_client_names),
         })
     }
 }
@@ -484,6 +521,7 @@ impl TryFrom<ProtobufSessionManifest> for SessionInfo {
                 .collect(),
             panes,
             connected_clients: protobuf_session_manifest.connected_clients as usize,
+            connected_client_names: client_names(protobuf_session_manifest.connected_client_names),
             is_current_session: protobuf_session_manifest.is_current_session,
         })
     }
@@ -511,6 +549,74 @@ impl TryFrom<ProtobufCopyDestination> for CopyDestination {
     }
 }
 
+impl From<ProtobufClientRole> for ClientRole {
+    fn from(protobuf_client_role: ProtobufClientRole) -> ClientRole {
+        match protobuf_client_role {
+            ProtobufClientRole::Collaborator => ClientRole::Collaborator,
+            ProtobufClientRole::Viewer => ClientRole::Viewer,
+            ProtobufClientRole::Owner => ClientRole::Owner,
+        }
+    }
+}
+
+impl From<ClientRole> for ProtobufClientRole {
+    fn from(client_role: ClientRole) -> ProtobufClientRole {
+        match client_role {
+            ClientRole::Collaborator => ProtobufClientRole::Collaborator,
+            ClientRole::Viewer => ProtobufClientRole::Viewer,
+            ClientRole::Owner => ProtobufClientRole::Owner,
+        }
+    }
+}
+
+impl TryFrom<ProtobufConnectedClientInfo> for ConnectedClientInfo {
+    type Error = &'static str;
+    fn try_from(protobuf_client: ProtobufConnectedClientInfo) -> Result<Self, &'static str> {
+        let role = ProtobufClientRole::from_i32(protobuf_client.role)
+            .ok_or("Unknown client role")?
+            .into();
+        Ok(ConnectedClientInfo {
+            client_id: u16::try_from(protobuf_client.client_id)
+                .map_err(|_| "Malformed client id")?,
+            ssh_user: protobuf_client.ssh_user,
+            peer_address: protobuf_client.peer_address,
+            role,
+            rows: protobuf_client.rows as usize,
+            cols: protobuf_client.cols as usize,
+            focused_pane: protobuf_client
+                .focused_pane_id
+                .map(|pane_id| (pane_id, protobuf_client.focused_pane_is_plugin)),
+            following: protobuf_client
+                .following
+                .map(u16::try_from)
+                .transpose()
+                .map_err(|_| "Malformed client id")?,
+            is_current_client: protobuf_client.is_current_client,
+        })
+    }
+}
+
+impl TryFrom<ConnectedClientInfo> for ProtobufConnectedClientInfo {
+    type Error = &'static str;
+    fn try_from(client: ConnectedClientInfo) -> Result<Self, &'static str> {
+        let role: ProtobufClientRole = client.role.into();
+        Ok(ProtobufConnectedClientInfo {
+            client_id: client.client_id as u32,
+            ssh_user: client.ssh_user,
+            peer_address: client.peer_address,
+            role: role as i32,
+            rows: client.rows as u32,
+            cols: client.cols as u32,
+            focused_pane_id: client.focused_pane.map(|(pane_id, _)| pane_id),
+            focused_pane_is_plugin: client
+                .focused_pane
+                .map_or(false, |(_, is_plugin)| is_plugin),
+            following: client.following.map(|client_id| client_id as u32),
+            is_current_client: client.is_current_client,
+        })
+    }
+}
+
 impl TryFrom<MouseEventPayload> for Mouse {
     type Error = &'static str;
     fn try_from(mouse_event_payload: MouseEventPayload) -> Result<Self, &'static str> {
@@ -699,6 +805,7 @@ impl TryFrom<ProtobufTabInfo> for TabInfo {
                 .iter()
                 .map(|c| *c as u16)
                 .collect(),
+            other_focused_client_names: client_names(protobuf_tab_info.other_focused_client_names),
             active_swap_layout_name: protobuf_tab_info.active_swap_layout_name,
             is_swap_layout_dirty: protobuf_tab_info.is_swap_layout_dirty,
         })
@@ -723,10 +830,28 @@ impl TryFrom<TabInfo> for ProtobufTabInfo {
                 .collect(),
             active_swap_layout_name: tab_info.active_swap_layout_name,
             is_swap_layout_dirty: tab_info.is_swap_layout_dirty,
+            other_focused_client_names: protobuf_client_names(tab_info.other_focused_client_names),
         })
     }
 }
 
+fn client_names(protobuf_client_names: Vec<ProtobufClientName>) -> BTreeMap<ClientId, String> {
+    protobuf_client_names
+        .into_iter()
+        .map(|client_name| (client_name.client_id as ClientId, client_name.name))
+        .collect()
+}
+
+fn protobuf_client_names(client_names: BTreeMap<ClientId, String>) -> Vec<ProtobufClientName> {
+    client_names
+        .into_iter()
+        .map(|(client_id, name)| ProtobufClientName {
+            client_id: client_id as u32,
+            name,
+        })
+        .collect()
+}
+
 impl TryFrom<ProtobufModeUpdatePayload> for ModeInfo {
     type Error = &'static str;
     fn try_from(
@@ -773,6 +898,8 @@ impl TryFrom<ProtobufModeUpdatePayload> for ModeInfo {
             style,
             capabilities,
             session_name,
+            read_only: protobuf_mode_update_payload.read_only,
+            display_name: protobuf_mode_update_payload.display_name,
         };
         Ok(mode_info)
     }
@@ -785,6 +912,8 @@ impl TryFrom<ModeInfo> for ProtobufModeUpdatePayload {
         let style: ProtobufStyle = mode_info.style.try_into()?;
         let arrow_fonts_support: bool = mode_info.capabilities.arrow_fonts;
         let session_name = mode_info.session_name;
+        let read_only = mode_info.read_only;
+        let display_name = mode_info.display_name;
         let mut protobuf_input_mode_keybinds: Vec<ProtobufInputModeKeybinds> = vec![];
         for (input_mode, input_mode_keybinds) in mode_info.keybinds {
             let mode: ProtobufInputMode = input_mode.try_into()?;
@@ -815,6 +944,8 @@ impl TryFrom<ModeInfo> for ProtobufModeUpdatePayload {
             keybinds: protobuf_input_mode_keybinds,
             arrow_fonts_support,
             session_name,
+            read_only,
+            display_name,
         })
     }
 }
@@ -872,6 +1003,8 @@ impl TryFrom<ProtobufEventType> for EventType {
             ProtobufEventType::SessionUpdate => EventType::SessionUpdate,
             ProtobufEventType::RunCommandResult => EventType::RunCommandResult,
             ProtobufEventType::WebRequestResult => EventType::WebRequestResult,
+            ProtobufEventType::Notification => EventType::Notification,
+            ProtobufEventType::ConnectedClients => EventType::ConnectedClients,
         })
     }
 }
@@ -899,6 +1032,8 @@ impl TryFrom<EventType> for ProtobufEventType {
             EventType::SessionUpdate => ProtobufEventType::SessionUpdate,
             EventType::RunCommandResult => ProtobufEventType::RunCommandResult,
             EventType::WebRequestResult => ProtobufEventType::WebRequestResult,
+            EventType::Notification => ProtobufEventType::Notification,
+            EventType::ConnectedClients => ProtobufEventType::ConnectedClients,
         })
     }
 }
@@ -998,6 +1133,8 @@ fn serialize_mode_update_event_with_non_default_values() {
         },
         capabilities: PluginCapabilities { arrow_fonts: false },
         session_name: Some("my awesome test session".to_owned()),
+        read_only: true,
+        display_name: Some("alice".to_owned()),
     });
     let protobuf_event: ProtobufEvent = mode_update_event.clone().try_into().unwrap();
     let serialized_protobuf_event = protobuf_event.encode_to_vec();
@@ -1038,6 +1175,7 @@ fn serialize_tab_update_event_with_non_default_values() {
             is_sync_panes_active: false,
             are_floating_panes_visible: true,
             other_focused_clients: vec![2, 3, 4],
+            other_focused_client_names: BTreeMap::from([(2, "alice".to_owned())]),
             active_swap_layout_name: Some("my cool swap layout".to_owned()),
             is_swap_layout_dirty: false,
         },
@@ -1050,6 +1188,7 @@ fn serialize_tab_update_event_with_non_default_values() {
             is_sync_panes_active: true,
             are_floating_panes_visible: true,
             other_focused_clients: vec![1, 5, 111],
+            other_focused_client_names: BTreeMap::new(),
             active_swap_layout_name: None,
             is_swap_layout_dirty: true,
         },
@@ -1308,6 +1447,7 @@ fn serialize_session_update_event_with_non_default_values() {
             is_sync_panes_active: false,
             are_floating_panes_visible: true,
             other_focused_clients: vec![2, 3, 4],
+            other_focused_client_names: BTreeMap::from([(2, "alice".to_owned())]),
             active_swap_layout_name: Some("my cool swap layout".to_owned()),
             is_swap_layout_dirty: false,
         },
@@ -1320,6 +1460,7 @@ fn serialize_session_update_event_with_non_default_values() {
             is_sync_panes_active: true,
             are_floating_panes_visible: true,
             other_focused_clients: vec![1, 5, 111],
+            other_focused_client_names: BTreeMap::new(),
             active_swap_layout_name: None,
             is_swap_layout_dirty: true,
         },
@@ -1382,6 +1523,7 @@ fn serialize_session_update_event_with_non_default_values() {
         tabs: tab_infos,
         panes: PaneManifest { panes },
         connected_clients: 2,
+        connected_client_names: BTreeMap::from([(1, "alice".to_owned()), (2, "bob".to_owned())]),
         is_current_session: true,
     };
     let session_info_2 = SessionInfo {
@@ -1391,6 +1533,7 @@ fn serialize_session_update_event_with_non_default_values() {
             panes: HashMap::new(),
         },
         connected_clients: 0,
+        connected_client_names: BTreeMap::new(),
         is_current_session: false,
     };
     let session_infos = vec![session_info_1, session_info_2];
@@ -1407,3 +1550,54 @@ fn serialize_session_update_event_with_non_default_values() {
         "Event properly serialized/deserialized without change"
     );
 }
+
+#[test]
+fn serialize_notification_event() {
+    use prost::Message;
+    let notification_event = Event::Notification("alice uploaded notes.txt".to_owned());
+    let protobuf_event: ProtobufEvent = notification_event.clone().try_into().unwrap();
+    let serialized_protobuf_event = protobuf_event.encode_to_vec();
+    let deserialized_protobuf_event: ProtobufEvent =
+        Message::decode(serialized_protobuf_event.as_slice()).unwrap();
+    let deserialized_event: Event = deserialized_protobuf_event.try_into().unwrap();
+    assert_eq!(
+        notification_event, deserialized_event,
+        "Event properly serialized/deserialized without change"
+    );
+}
+
+#[test]
+fn serialize_connected_clients_event() {
+    use prost::Message;
+    let connected_clients_event = Event::ConnectedClients(vec![
+        ConnectedClientInfo {
+            client_id: 1,
+            role: ClientRole::Owner,
+            rows: 40,
+            cols: 120,
+            focused_pane: Some((0, false)),
+            is_current_client: true,
+            ..Default::default()
+        },
+        ConnectedClientInfo {
+            client_id: 2,
+            ssh_user: Some("alice".to_owned()),
+            peer_address: Some("10.0.0.5".to_owned()),
+            role: ClientRole::Viewer,
+            rows: 24,
+            cols: 80,
+            focused_pane: Some((3, true)),
+            following: Some(1),
+            is_current_client: false,
+        },
+    ]);
+    let protobuf_event: ProtobufEvent = connected_clients_event.clone().try_into().unwrap();
+    let serialized_protobuf_event = protobuf_event.encode_to_vec();
+    let deserialized_protobuf_event: ProtobufEvent =
+        Message::decode(serialized_protobuf_event.as_slice()).unwrap();
+    let deserialized_event: Event = deserialized_protobuf_event.try_into().unwrap();
+    assert_eq!(
+        connected_clients_event, deserialized_event,
+        "Event properly serialized/deserialized without change"
+    );
+}
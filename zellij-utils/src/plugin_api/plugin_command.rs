@@ -1,14 +1,14 @@
 pub use super::generated_api::api::{
     action::{PaneIdAndShouldFloat, SwitchToModePayload},
-    event::{EventNameList as ProtobufEventNameList, Header},
+    event::{ClientRole as ProtobufClientRole, EventNameList as ProtobufEventNameList, Header},
     input_mode::InputMode as ProtobufInputMode,
     plugin_command::{
-        plugin_command::Payload, CommandName, ContextItem, EnvVariable, ExecCmdPayload,
-        HttpVerb as ProtobufHttpVerb, IdAndNewName, MovePayload, OpenCommandPanePayload,
-        OpenFilePayload, PluginCommand as ProtobufPluginCommand, PluginMessagePayload,
-        RequestPluginPermissionPayload, ResizePayload, RunCommandPayload, SetTimeoutPayload,
-        SubscribePayload, SwitchSessionPayload, SwitchTabToPayload, UnsubscribePayload,
-        WebRequestPayload,
+        plugin_command::Payload, AnswerPromptPayload, CommandName, ContextItem, EnvVariable,
+        ExecCmdPayload, HttpVerb as ProtobufHttpVerb, IdAndNewName, MovePayload,
+        OpenCommandPanePayload, OpenFilePayload, PluginCommand as ProtobufPluginCommand,
+        PluginMessagePayload, RequestPluginPermissionPayload, ResizePayload, RunCommandPayload,
+        SetClientRolePayload, SetTimeoutPayload, SubscribePayload, SwitchSessionPayload,
+        SwitchTabToPayload, UnsubscribePayload, WebRequestPayload,
     },
     plugin_permission::PermissionType as ProtobufPermissionType,
     resize::ResizeAction as ProtobufResizeAction,
@@ -641,6 +641,37 @@ impl TryFrom<ProtobufPluginCommand> for PluginCommand {
                 },
                 _ => Err("Mismatched payload for RenameSession"),
             },
+            Some(CommandName::AnswerPrompt) => match protobuf_plugin_command.payload {
+                Some(Payload::AnswerPromptPayload(answer_prompt_payload)) => {
+                    Ok(PluginCommand::AnswerPrompt(
+                        answer_prompt_payload.prompt_id,
+                        answer_prompt_payload.answer,
+                    ))
+                },
+                _ => Err("Mismatched payload for AnswerPrompt"),
+            },
+            Some(CommandName::ListConnectedClients) => match protobuf_plugin_command.payload {
+                Some(_) => Err("ListConnectedClients should not have a payload"),
+                None => Ok(PluginCommand::ListConnectedClients),
+            },
+            Some(CommandName::DisconnectClient) => match protobuf_plugin_command.payload {
+                Some(Payload::DisconnectClientPayload(client_id)) => {
+                    let client_id = u16::try_from(client_id)
+                        .map_err(|_| "Malformed client id for DisconnectClient")?;
+                    Ok(PluginCommand::DisconnectClient(client_id))
+                },
+                _ => Err("Mismatched payload for DisconnectClient"),
+            },
+            Some(CommandName::SetClientRole) => match protobuf_plugin_command.payload {
+                Some(Payload::SetClientRolePayload(set_client_role_payload)) => {
+                    let client_id = u16::try_from(set_client_role_payload.client_id)
+                        .map_err(|_| "Malformed client id for SetClientRole")?;
+                    let role = ProtobufClientRole::from_i32(set_client_role_payload.role)
+                        .ok_or("Unknown role for SetClientRole")?;
+                    Ok(PluginCommand::SetClientRole(client_id, role.into()))
+                },
+                _ => Err("Mismatched payload for SetClientRole"),
+            },
             None => Err("Unrecognized plugin command"),
         }
     }
@@ -1069,6 +1100,28 @@ impl TryFrom<PluginCommand> for ProtobufPluginCommand {
                 name: CommandName::RenameSession as i32,
                 payload: Some(Payload::RenameSessionPayload(new_session_name)),
             }),
+            PluginCommand::AnswerPrompt(prompt_id, answer) => Ok(ProtobufPluginCommand {
+                name: CommandName::AnswerPrompt as i32,
+                payload: Some(Payload::AnswerPromptPayload(AnswerPromptPayload {
+                    prompt_id,
+                    answer,
+                })),
+            }),
+            PluginCommand::ListConnectedClients => Ok(ProtobufPluginCommand {
+                name: CommandName::ListConnectedClients as i32,
+                payload: None,
+            }),
+            PluginCommand::DisconnectClient(client_id) => Ok(ProtobufPluginCommand {
+                name: CommandName::DisconnectClient as i32,
+                payload: Some(Payload::DisconnectClientPayload(client_id as u32)),
+            }),
+            PluginCommand::SetClientRole(client_id, role) => Ok(ProtobufPluginCommand {
+                name: CommandName::SetClientRole as i32,
+                payload: Some(Payload::SetClientRolePayload(SetClientRolePayload {
+                    client_id: client_id as u32,
+                    role: ProtobufClientRole::from(role) as i32,
+                })),
+            }),
         }
     }
 }
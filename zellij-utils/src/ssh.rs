@@ -1,8 +1,313 @@
-use clap::Args;
+use clap::{ArgEnum, Args, Subcommand};
 use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, str::FromStr};
 
-#[derive(Debug, Default, Clone, Args, Serialize, Deserialize)]
+pub const DEFAULT_SSH_PORT: u16 = 6222;
+
+/// Options of the `zellij ssh` server, from the `ssh` block of the config file and the cli
+#[derive(Debug, Default, Clone, Args, Serialize, Deserialize, PartialEq)]
 pub struct Ssh {
-    #[clap(long, short, default_value = "6222")]
-    pub port: u16,
+    /// Port to listen on [default: 6222]
+    #[clap(long, short, value_parser)]
+    pub port: Option<u16>,
+
+    /// Address to listen on, an ip address (`::1`), an address and port (`10.0.0.1:2222`) or
+    /// the path of a Unix socket. Can be given multiple times [default: all IPv6 and IPv4
+    /// addresses]
+    #[clap(long = "bind-address", value_parser)]
+    pub bind_addresses: Vec<String>,
+
+    /// Serve a single connection over stdin and stdout instead of listening, e.g. as an OpenSSH
+    /// `ForceCommand` or `ProxyCommand`, or under inetd-style socket activation
+    #[clap(long, value_parser)]
+    pub stdio: bool,
+
+    /// host:port of a `zellij ssh relay` to dial out to, guests then join through the relay
+    /// when they can't reach this machine directly
+    #[clap(long, value_parser)]
+    pub relay: Option<String>,
+
+    /// Seconds a connection may stay idle before it is closed, 0 keeps idle connections open
+    /// [default: 3600]
+    #[clap(long, value_parser)]
+    pub inactivity_timeout: Option<u64>,
+
+    /// Seconds to wait before answering a failed authentication attempt [default: 3]
+    #[clap(long, value_parser)]
+    pub auth_rejection_time: Option<u64>,
+
+    /// Seconds to wait before answering the first failed authentication attempt of a
+    /// connection, which usually is the client probing with the none method [default: 0]
+    #[clap(long, value_parser)]
+    pub auth_rejection_time_initial: Option<u64>,
+
+    /// Seconds connected clients are warned for before they are detached when the server
+    /// shuts down [default: 10]
+    #[clap(long, value_parser)]
+    pub shutdown_grace_period: Option<u64>,
+
+    /// Character that starts an escape sequence when typed at the start of a line, e.g. `~.`
+    /// detaches and `~?` lists the others. `none` turns escape sequences off [default: ~]
+    #[clap(long, value_parser)]
+    pub escape_char: Option<String>,
+
+    /// Attach clients whose login doesn't pick a session straight to the session the server
+    /// was started with, instead of letting them pick one in the lobby
+    #[clap(long, value_parser)]
+    pub skip_lobby: bool,
+
+    /// Ask the first client attached to a session to allow, deny or make view-only each guest
+    /// before they join it. Guests are denied when nobody answers
+    #[clap(long, value_parser)]
+    pub approve_guests: bool,
+
+    /// Seconds the host gets to answer whether a guest may join [default: 60]
+    #[clap(long, value_parser)]
+    pub approval_timeout: Option<u64>,
+
+    /// Authentication method clients may use, can be given multiple times. Passcodes need
+    /// password or keyboard-interactive [default: all of them]
+    #[clap(long = "auth-method", arg_enum, value_parser)]
+    pub auth_methods: Vec<SshAuthMethod>,
+
+    /// Private host key file, can be given multiple times. Missing ed25519 and rsa keys are
+    /// generated on first run [default: ssh/ssh_host_ed25519_key and ssh/ssh_host_rsa_key in
+    /// the zellij data dir]
+    #[clap(long = "host-key", value_parser)]
+    pub host_keys: Vec<PathBuf>,
+
+    /// OpenSSH-format authorized_keys file listing the public keys allowed to connect
+    /// [default: ssh/authorized_keys in the zellij data dir]
+    #[clap(long, value_parser)]
+    pub authorized_keys: Option<PathBuf>,
+
+    /// Let clients connect without authenticating. Anyone who can reach the port will be able
+    /// to type into the session!
+    #[clap(long, value_parser)]
+    pub allow_auth_none: bool,
+
+    /// Directory served to sftp and scp clients, read-only viewers can only download from it
+    /// [default: the default_cwd option, or the directory zellij was started in]
+    #[clap(long, value_parser)]
+    pub sftp_root: Option<PathBuf>,
+
+    /// host:port clients may forward a local port to (`ssh -L 3000:localhost:3000`), can be
+    /// given multiple times. Port forwarding is disabled unless at least one is given
+    #[clap(long = "allow-forward", value_parser)]
+    pub allowed_forwards: Vec<String>,
+
+    /// Environment variable sent by clients (`SendEnv` in ssh_config) to pass on to the panes
+    /// they open, `*` matches any characters. Can be given multiple times [default: TERM,
+    /// COLORTERM, LANG and LC_*]
+    #[clap(long = "accept-env", value_parser)]
+    pub accepted_env: Vec<String>,
+
+    /// Most connections the server holds open at once [default: 100]
+    #[clap(long, value_parser)]
+    pub max_connections: Option<usize>,
+
+    /// Most connections the server holds open at once from a single address [default: 10]
+    #[clap(long, value_parser)]
+    pub max_connections_per_ip: Option<usize>,
+
+    /// Most ssh clients attached to a single session at once [default: no limit]
+    #[clap(long, value_parser)]
+    pub max_clients_per_session: Option<usize>,
+
+    /// Connections from an address that fail to authenticate before it is banned [default: 5]
+    #[clap(long, value_parser)]
+    pub max_auth_failures: Option<u32>,
+
+    /// Minutes an address stays banned after too many failed logins [default: 10]
+    #[clap(long, value_parser)]
+    pub ban_minutes: Option<u64>,
+
+    /// Address or CIDR range (`10.0.0.0/8`) allowed to connect, can be given multiple times.
+    /// When one is given, connections from anywhere else are refused
+    #[clap(long = "allow-from", value_parser)]
+    pub allow_from: Vec<String>,
+
+    /// Address or CIDR range refused even when it is in an --allow-from range, can be given
+    /// multiple times
+    #[clap(long = "deny-from", value_parser)]
+    pub deny_from: Vec<String>,
+
+    #[clap(subcommand)]
+    pub command: Option<SshCommand>,
+}
+
+#[derive(Debug, Subcommand, Clone, Serialize, Deserialize, PartialEq)]
+pub enum SshCommand {
+    /// Print the ssh commands that join running sessions, read-write or read-only
+    Share {
+        /// Name of the session to share [default: all running sessions]
+        session_name: Option<String>,
+    },
+    /// List the ports forwarded through the running ssh server, by client
+    Forwards,
+    /// Show who connected to the ssh server, and when
+    Audit {
+        /// Only show connections to this session
+        #[clap(long, value_parser)]
+        session: Option<String>,
+        /// Only show connections open after this time, rfc3339 (2023-11-01T09:00:00Z) or how
+        /// long ago (2h)
+        #[clap(long, value_parser)]
+        since: Option<String>,
+        /// Only show connections open before this time, in the same format as --since
+        #[clap(long, value_parser)]
+        until: Option<String>,
+        /// Print the records as JSON lines
+        #[clap(long, value_parser)]
+        json: bool,
+    },
+    /// Run a relay, which routes guests to hosts that dialed out to it with --relay
+    Relay,
+    /// Manage one-time passcodes, which let guests without a registered key join a session
+    #[clap(subcommand)]
+    Passcode(PasscodeCommand),
+}
+
+#[derive(Debug, Subcommand, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PasscodeCommand {
+    /// Generate a passcode, guests enter it when ssh asks for a password
+    New {
+        /// Name of the session the passcode joins [default: the current session]
+        session_name: Option<String>,
+        /// What the guest may do in the session
+        #[clap(long, arg_enum, default_value = "viewer", value_parser)]
+        role: ShareRole,
+        /// Minutes the passcode can be used for
+        #[clap(long, default_value = "15", value_parser)]
+        valid_for: u64,
+    },
+    /// Revoke a passcode that hasn't been used yet
+    Revoke { passcode: String },
+    /// List the passcodes that haven't been used yet
+    List,
+}
+
+/// An ssh authentication method, the none method is controlled by `allow_auth_none`
+#[derive(ArgEnum, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SshAuthMethod {
+    /// Keys listed in the authorized keys file
+    Publickey,
+    /// One-time passcodes, entered as a password
+    Password,
+    /// One-time passcodes, entered at a prompt
+    KeyboardInteractive,
+}
+
+impl FromStr for SshAuthMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "publickey" => Ok(SshAuthMethod::Publickey),
+            "password" => Ok(SshAuthMethod::Password),
+            "keyboard-interactive" => Ok(SshAuthMethod::KeyboardInteractive),
+            _ => Err(format!("Unknown authentication method: {}", s)),
+        }
+    }
+}
+
+/// What a guest joining through a share may do in the session
+#[derive(ArgEnum, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareRole {
+    /// Watch the session without typing into it
+    Viewer,
+    /// Type into the session like the host
+    Collaborator,
+}
+
+impl std::fmt::Display for ShareRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShareRole::Viewer => write!(f, "viewer"),
+            ShareRole::Collaborator => write!(f, "collaborator"),
+        }
+    }
+}
+
+impl Ssh {
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_SSH_PORT)
+    }
+
+    /// Merges two [`Ssh`] structs, values set in `other` (usually coming from the cli) take
+    /// precedence
+    pub fn merge(&self, other: Ssh) -> Ssh {
+        let host_keys = if other.host_keys.is_empty() {
+            self.host_keys.clone()
+        } else {
+            other.host_keys
+        };
+        let allowed_forwards = if other.allowed_forwards.is_empty() {
+            self.allowed_forwards.clone()
+        } else {
+            other.allowed_forwards
+        };
+        let bind_addresses = if other.bind_addresses.is_empty() {
+            self.bind_addresses.clone()
+        } else {
+            other.bind_addresses
+        };
+        let auth_methods = if other.auth_methods.is_empty() {
+            self.auth_methods.clone()
+        } else {
+            other.auth_methods
+        };
+        let accepted_env = if other.accepted_env.is_empty() {
+            self.accepted_env.clone()
+        } else {
+            other.accepted_env
+        };
+        let allow_from = if other.allow_from.is_empty() {
+            self.allow_from.clone()
+        } else {
+            other.allow_from
+        };
+        let deny_from = if other.deny_from.is_empty() {
+            self.deny_from.clone()
+        } else {
+            other.deny_from
+        };
+        Ssh {
+            port: other.port.or(self.port),
+            bind_addresses,
+            stdio: other.stdio,
+            relay: other.relay.or_else(|| self.relay.clone()),
+            inactivity_timeout: other.inactivity_timeout.or(self.inactivity_timeout),
+            auth_rejection_time: other.auth_rejection_time.or(self.auth_rejection_time),
+            auth_rejection_time_initial: other
+                .auth_rejection_time_initial
+                .or(self.auth_rejection_time_initial),
+            shutdown_grace_period: other.shutdown_grace_period.or(self.shutdown_grace_period),
+            escape_char: other.escape_char.or_else(|| self.escape_char.clone()),
+            skip_lobby: other.skip_lobby || self.skip_lobby,
+            approve_guests: other.approve_guests || self.approve_guests,
+            approval_timeout: other.approval_timeout.or(self.approval_timeout),
+            auth_methods,
+            host_keys,
+            authorized_keys: other
+                .authorized_keys
+                .or_else(|| self.authorized_keys.clone()),
+            allow_auth_none: other.allow_auth_none || self.allow_auth_none,
+            sftp_root: other.sftp_root.or_else(|| self.sftp_root.clone()),
+            allowed_forwards,
+            accepted_env,
+            max_connections: other.max_connections.or(self.max_connections),
+            max_connections_per_ip: other.max_connections_per_ip.or(self.max_connections_per_ip),
+            max_clients_per_session: other
+                .max_clients_per_session
+                .or(self.max_clients_per_session),
+            max_auth_failures: other.max_auth_failures.or(self.max_auth_failures),
+            ban_minutes: other.ban_minutes.or(self.ban_minutes),
+            allow_from,
+            deny_from,
+            command: other.command,
+        }
+    }
 }